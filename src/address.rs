@@ -1,8 +1,12 @@
-use base58::ToBase58;
-use bech32::{ToBase32, Variant, encode, u5};
+use crate::codec::{Address, Network, Wif};
+use crate::error::{Error, Result};
+use crate::hash::hash160_into;
+use crate::script::{ScriptType, ScriptTypes};
 use ripemd::Ripemd160;
-use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey};
 use sha2::{Digest, Sha256};
+use std::fmt;
+use std::sync::OnceLock;
 
 fn sha256_digest(data: &[u8]) -> Vec<u8> {
     Sha256::digest(data).to_vec()
@@ -12,57 +16,226 @@ fn ripemd160_digest(data: &[u8]) -> Vec<u8> {
     Ripemd160::digest(data).to_vec()
 }
 
-fn hash160(data: &[u8]) -> Vec<u8> {
+/// RIPEMD-160 of SHA-256, as used for Bitcoin public key hashes
+pub fn hash160(data: &[u8]) -> Vec<u8> {
     ripemd160_digest(&sha256_digest(data))
 }
 
-fn to_p2pkh(pubkey: &[u8]) -> String {
-    let hash = hash160(pubkey);
-    let mut address = vec![0x00];
-    address.extend(&hash);
-    let checksum = &sha256_digest(&sha256_digest(&address))[..4];
-    address.extend(checksum);
-    address.to_base58()
+/// BIP-340 tagged hash: SHA256(SHA256(tag) || SHA256(tag) || data)
+fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+    Sha256::new()
+        .chain_update(tag_hash)
+        .chain_update(tag_hash)
+        .chain_update(data)
+        .finalize()
+        .into()
 }
 
-fn to_p2sh(pubkey: &[u8]) -> String {
-    let pubkey_hash = hash160(pubkey);
-    let redeem_script: Vec<u8> = [&[0x00u8, 0x14][..], &pubkey_hash[..]].concat();
-    let script_hash = hash160(&redeem_script);
-    let mut address = vec![0x05];
-    address.extend(&script_hash);
-    let checksum = &sha256_digest(&sha256_digest(&address))[..4];
-    address.extend(checksum);
-    address.to_base58()
+/// BIP-341 key-path output key: the x-only internal key tweaked with an
+/// empty script tree
+pub fn taproot_output_key(public_key: &PublicKey) -> Result<[u8; 32]> {
+    let secp = Secp256k1::verification_only();
+    let (internal, _) = public_key.x_only_public_key();
+    let tweak = Scalar::from_be_bytes(tagged_hash("TapTweak", &internal.serialize()))
+        .map_err(|e| Error::Encoding(format!("taproot tweak: {}", e)))?;
+    let (output, _) = internal
+        .add_tweak(&secp, &tweak)
+        .map_err(|e| Error::Encoding(format!("taproot tweak: {}", e)))?;
+    Ok(output.serialize())
 }
 
-fn to_bech32(pubkey: &[u8]) -> String {
-    let prog = hash160(pubkey);
-    let mut bech32_data = vec![u5::try_from_u8(0).unwrap()];
-    bech32_data.extend(prog.to_base32());
-    encode("bc", bech32_data, Variant::Bech32).unwrap()
+/// An address derived from a public key. The string form is only encoded
+/// when first asked for, so matching on the raw hash stays cheap.
+#[derive(Debug, Clone)]
+pub struct DerivedAddress {
+    pub script: ScriptType,
+    /// Whether the address commits to the compressed public key
+    pub compressed: bool,
+    pub network: Network,
+    program: [u8; 65],
+    encoded: OnceLock<String>,
 }
 
-pub fn derive_addresses(raw_key: &[u8; 32]) -> Vec<String> {
+impl DerivedAddress {
+    /// An address from its hash, witness program or public key, which must
+    /// be 32 bytes for P2TR, the SEC key for P2PK and 20 bytes otherwise
+    pub fn new(script: ScriptType, network: Network, payload: &[u8]) -> Self {
+        let mut program = [0u8; 65];
+        program[..payload.len()].copy_from_slice(payload);
+        Self {
+            script,
+            compressed: !matches!(
+                script,
+                ScriptType::P2pkhUncompressed | ScriptType::P2pkUncompressed
+            ),
+            network,
+            program,
+            encoded: OnceLock::new(),
+        }
+    }
+
+    /// The public key hash, script hash, output key or public key the
+    /// address pays to
+    pub fn payload(&self) -> &[u8] {
+        match self.script {
+            ScriptType::P2tr => &self.program[..32],
+            ScriptType::P2pkCompressed => &self.program[..33],
+            ScriptType::P2pkUncompressed => &self.program,
+            _ => &self.program[..20],
+        }
+    }
+
+    /// The address, or `None` for bare P2PK outputs, which have none
+    pub fn to_address(&self) -> Option<Address> {
+        let network = self.network;
+        let mut hash = [0u8; 20];
+        hash.copy_from_slice(&self.program[..20]);
+        Some(match self.script {
+            ScriptType::P2pkhCompressed | ScriptType::P2pkhUncompressed => {
+                Address::P2pkh { network, hash }
+            }
+            ScriptType::P2shP2wpkh => Address::P2sh { network, hash },
+            ScriptType::P2wpkh => Address::P2wpkh { network, hash },
+            ScriptType::P2tr => {
+                let mut output_key = [0u8; 32];
+                output_key.copy_from_slice(&self.program[..32]);
+                Address::P2tr {
+                    network,
+                    output_key,
+                }
+            }
+            ScriptType::P2pkCompressed | ScriptType::P2pkUncompressed => return None,
+        })
+    }
+
+    /// The encoded address, or the hex public key of a P2PK output
+    pub fn as_str(&self) -> &str {
+        self.encoded.get_or_init(|| match self.to_address() {
+            Some(address) => address.to_string(),
+            None => hex::encode(self.payload()),
+        })
+    }
+}
+
+impl PartialEq for DerivedAddress {
+    fn eq(&self, other: &Self) -> bool {
+        self.script == other.script
+            && self.network == other.network
+            && self.program == other.program
+    }
+}
+
+impl Eq for DerivedAddress {}
+
+impl fmt::Display for DerivedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Compute the public key of a raw private key
+pub fn public_key(raw_key: &[u8; 32]) -> Result<PublicKey> {
     let secp = Secp256k1::new();
-    let secret_key = SecretKey::from_slice(raw_key).unwrap();
-    let public_key = PublicKey::from_secret_key(&secp, &secret_key);
-
-    vec![
-        to_p2pkh(&public_key.serialize()),
-        to_p2pkh(&public_key.serialize_uncompressed()),
-        to_p2sh(&public_key.serialize()),
-        to_bech32(&public_key.serialize()),
-    ]
-}
-
-pub fn private_key_to_wif(key: &[u8; 32], compressed: bool) -> String {
-    let mut data = vec![0x80]; // Mainnet prefix
-    data.extend_from_slice(key);
-    if compressed {
-        data.push(0x01);
+    let secret_key = SecretKey::from_slice(raw_key)
+        .map_err(|_| Error::InvalidPrivateKey(hex::encode(raw_key)))?;
+    Ok(PublicKey::from_secret_key(&secp, &secret_key))
+}
+
+/// The mainnet addresses of a private key for the default script types
+pub fn derive_addresses(raw_key: &[u8; 32]) -> Result<Vec<DerivedAddress>> {
+    addresses_for(&public_key(raw_key)?)
+}
+
+/// The mainnet addresses of a public key for the default script types, in
+/// the order of `derive_addresses`
+pub fn addresses_for(public_key: &PublicKey) -> Result<Vec<DerivedAddress>> {
+    derive(public_key, Network::Mainnet, ScriptTypes::DEFAULT)
+}
+
+/// The addresses of a public key on a network for a set of script types
+pub fn derive(
+    public_key: &PublicKey,
+    network: Network,
+    types: ScriptTypes,
+) -> Result<Vec<DerivedAddress>> {
+    derive_with_hashes(public_key, &KeyHashes::of(public_key), network, types)
+}
+
+/// The hash160 values the hashed script types of a key are built on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyHashes {
+    /// Of the compressed public key
+    pub compressed: [u8; 20],
+    /// Of the uncompressed public key
+    pub uncompressed: [u8; 20],
+    /// Of the P2SH-P2WPKH redeem script
+    pub nested: [u8; 20],
+}
+
+impl KeyHashes {
+    /// Every hash of `public_key`, one at a time
+    pub fn of(public_key: &PublicKey) -> Self {
+        let mut hashes = KeyHashes::default();
+        hash160_into(&public_key.serialize(), &mut hashes.compressed);
+        hash160_into(
+            &public_key.serialize_uncompressed(),
+            &mut hashes.uncompressed,
+        );
+        hash160_into(
+            &nested_redeem_script(&hashes.compressed),
+            &mut hashes.nested,
+        );
+        hashes
+    }
+}
+
+/// The P2SH-P2WPKH redeem script paying to a compressed public key hash
+pub fn nested_redeem_script(compressed_hash: &[u8; 20]) -> [u8; 22] {
+    let mut script = [0u8; 22];
+    script[..2].copy_from_slice(&[0x00, 0x14]);
+    script[2..].copy_from_slice(compressed_hash);
+    script
+}
+
+/// Like [`derive`], reusing hashes the caller already has; only the hashes
+/// of the script types in `types` are read
+pub fn derive_with_hashes(
+    public_key: &PublicKey,
+    hashes: &KeyHashes,
+    network: Network,
+    types: ScriptTypes,
+) -> Result<Vec<DerivedAddress>> {
+    types
+        .iter()
+        .map(|script| {
+            Ok(match script {
+                ScriptType::P2pkhCompressed | ScriptType::P2wpkh => {
+                    DerivedAddress::new(script, network, &hashes.compressed)
+                }
+                ScriptType::P2pkhUncompressed => {
+                    DerivedAddress::new(script, network, &hashes.uncompressed)
+                }
+                ScriptType::P2shP2wpkh => DerivedAddress::new(script, network, &hashes.nested),
+                ScriptType::P2tr => {
+                    DerivedAddress::new(script, network, &taproot_output_key(public_key)?)
+                }
+                ScriptType::P2pkCompressed => {
+                    DerivedAddress::new(script, network, &public_key.serialize())
+                }
+                ScriptType::P2pkUncompressed => {
+                    DerivedAddress::new(script, network, &public_key.serialize_uncompressed())
+                }
+            })
+        })
+        .collect()
+}
+
+pub fn private_key_to_wif(key: &[u8; 32], compressed: bool, network: Network) -> String {
+    Wif {
+        key: *key,
+        compressed,
+        network,
     }
-    let checksum = &Sha256::digest(&Sha256::digest(&data))[..4];
-    data.extend(checksum);
-    data.to_base58()
+    .encode()
 }
@@ -0,0 +1,223 @@
+use crate::address::derive_addresses;
+use crate::chunk::{ChunkMetadata, ChunkStatus};
+use crate::error::{Error, Result};
+use crate::proof::{chunk_bounds, verify_proof};
+use crate::reference::{reference_addresses_for, reference_hash160, reference_public_key};
+use crate::search::TargetSet;
+use crate::validate::chunk_files;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Random windows rescanned when checking a chunk's proof during an audit
+const PROOF_WINDOWS: usize = 2;
+
+/// Keys per proof window, widened to at least the mean gap between
+/// distinguished keys
+const PROOF_WINDOW_KEYS: u64 = 256;
+
+/// A problem found by re-verifying a finished chunk
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuditIssue {
+    NotFinished(ChunkStatus),
+    Incomplete,
+    Proof(String),
+    AddressMismatch {
+        key: String,
+        format: &'static str,
+        fast: String,
+        reference: String,
+    },
+    UnlistedDistinguished {
+        offset: u64,
+    },
+    FalseDistinguished {
+        offset: u64,
+    },
+    MissedMatch {
+        key: String,
+        address: String,
+    },
+}
+
+impl fmt::Display for AuditIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditIssue::NotFinished(status) => write!(f, "chunk is {}, not finished", status),
+            AuditIssue::Incomplete => {
+                write!(f, "last_processed_hex is not the last key of the walk")
+            }
+            AuditIssue::Proof(e) => write!(f, "proof check failed: {}", e),
+            AuditIssue::AddressMismatch {
+                key,
+                format,
+                fast,
+                reference,
+            } => write!(
+                f,
+                "{} address of {} is {} but the reference path gives {}",
+                format, key, fast, reference
+            ),
+            AuditIssue::UnlistedDistinguished { offset } => write!(
+                f,
+                "key at offset {} is distinguished but missing from the proof",
+                offset
+            ),
+            AuditIssue::FalseDistinguished { offset } => write!(
+                f,
+                "proof lists offset {} but the reference path says it is not distinguished",
+                offset
+            ),
+            AuditIssue::MissedMatch { key, address } => write!(
+                f,
+                "key {} gives {} which is in the address database",
+                key, address
+            ),
+        }
+    }
+}
+
+/// Options for [`audit_folder`]
+#[derive(Debug, Clone)]
+pub struct AuditOptions {
+    /// Audit at most this many randomly chosen chunks; all when `None`
+    pub chunks: Option<usize>,
+    /// Random keys, and again as many listed distinguished keys, per chunk
+    pub keys: usize,
+    /// Also audit chunks already marked verified
+    pub recheck: bool,
+    /// Report without changing chunk files
+    pub dry_run: bool,
+}
+
+/// Outcome of auditing a chunk folder
+#[derive(Debug, Default)]
+pub struct AuditReport {
+    pub checked: usize,
+    pub verified: Vec<String>,
+    pub flagged: Vec<(String, Vec<AuditIssue>)>,
+}
+
+/// Re-verify a finished chunk.
+///
+/// The proof is checked first, which also rejects a proof listing too few
+/// distinguished keys for the chunk to have been scanned in full. Then
+/// `keys` random keys and up to `keys` distinguished keys from the proof are
+/// derived again on the slow reference path, which must agree with the fast
+/// path, with the proof's list of distinguished keys, and with the scan
+/// finding no match for the script types the chunk was checked for.
+pub fn audit_chunk(
+    meta: &ChunkMetadata,
+    targets: &TargetSet,
+    keys: usize,
+) -> Result<Vec<AuditIssue>> {
+    let mut issues = Vec::new();
+    if !meta.status.is_done() {
+        issues.push(AuditIssue::NotFinished(meta.status.clone()));
+    }
+    if meta.last_processed_hex != meta.final_key_hex()? {
+        issues.push(AuditIssue::Incomplete);
+    }
+    match verify_proof(meta, PROOF_WINDOWS, PROOF_WINDOW_KEYS) {
+        Ok(()) => {}
+        Err(Error::Verification(message)) => issues.push(AuditIssue::Proof(message)),
+        Err(e) => return Err(e),
+    }
+
+    let (walk, total) = chunk_bounds(meta)?;
+    let mut rng = rand::thread_rng();
+    let mut offsets: BTreeSet<u64> = (0..keys).map(|_| rng.gen_range(0..total)).collect();
+    if let Some(proof) = &meta.proof {
+        offsets.extend(proof.distinguished.choose_multiple(&mut rng, keys));
+    }
+
+    for offset in offsets {
+        let raw_key = walk.raw_key_at(offset);
+        let key = hex::encode(raw_key);
+        let fast = derive_addresses(&raw_key)?;
+        let (compressed, uncompressed) = reference_public_key(&raw_key)?;
+        let reference = reference_addresses_for(&compressed, &uncompressed);
+
+        let checked = meta.checked_types();
+        for (fast, reference) in fast.iter().zip(reference) {
+            if fast.as_str() != reference {
+                issues.push(AuditIssue::AddressMismatch {
+                    key: key.clone(),
+                    format: fast.script.label(),
+                    fast: fast.to_string(),
+                    reference,
+                });
+            }
+            if checked.contains(fast.script) && targets.contains(fast) {
+                issues.push(AuditIssue::MissedMatch {
+                    key: key.clone(),
+                    address: fast.to_string(),
+                });
+            }
+        }
+
+        if let Some(proof) = &meta.proof {
+            let distinguished = proof.is_distinguished(&reference_hash160(&compressed));
+            let listed = proof.distinguished.binary_search(&offset).is_ok();
+            if distinguished && !listed {
+                issues.push(AuditIssue::UnlistedDistinguished { offset });
+            } else if listed && !distinguished {
+                issues.push(AuditIssue::FalseDistinguished { offset });
+            }
+        }
+    }
+    Ok(issues)
+}
+
+/// Audit finished chunks of a folder.
+///
+/// Chunks that pass are marked `verified`. Chunks with issues are reset to
+/// `pending` from their start, dropping their proof, so they get rescanned.
+pub fn audit_folder(
+    base_path: &str,
+    targets: &TargetSet,
+    options: &AuditOptions,
+) -> Result<AuditReport> {
+    let mut candidates = Vec::new();
+    for path in chunk_files(base_path)? {
+        let path = path.to_string_lossy().into_owned();
+        let Ok(meta) = ChunkMetadata::load(&path) else {
+            continue;
+        };
+        let wanted = match meta.status {
+            ChunkStatus::Finished => true,
+            ChunkStatus::Verified => options.recheck,
+            _ => false,
+        };
+        if wanted {
+            candidates.push((path, meta));
+        }
+    }
+    if let Some(limit) = options.chunks {
+        candidates.shuffle(&mut rand::thread_rng());
+        candidates.truncate(limit);
+    }
+
+    let mut report = AuditReport::default();
+    for (path, mut meta) in candidates {
+        report.checked += 1;
+        let issues = audit_chunk(&meta, targets, options.keys)?;
+        if issues.is_empty() {
+            if !options.dry_run {
+                meta.status = ChunkStatus::Verified;
+                meta.save(&path)?;
+            }
+            report.verified.push(meta.chunk_id);
+        } else {
+            if !options.dry_run {
+                meta.status = ChunkStatus::Pending;
+                meta.set_walk(meta.walk())?;
+                meta.proof = None;
+                meta.save(&path)?;
+            }
+            report.flagged.push((meta.chunk_id, issues));
+        }
+    }
+    Ok(report)
+}
@@ -1,11 +1,18 @@
+use crate::error::{Error, Result};
+use crate::keygen::{KeyTemplate, KeyWalk, WalkRange, decode_key, key_bytes};
+use crate::proof::ChunkProof;
 use crate::puzzles::PuzzleRange;
+use crate::script::ScriptTypes;
+use crate::symmetry::Symmetry;
 use num_bigint::{BigUint, RandBigInt};
-use num_traits::One;
-use rand::thread_rng;
+use num_traits::{One, Zero};
+use rand::{Rng, thread_rng};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs::{File, create_dir_all};
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Write};
 use std::path::Path;
+use std::str::FromStr;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 #[serde(rename_all = "lowercase")]
@@ -13,6 +20,41 @@ pub enum ChunkStatus {
     Pending,
     Processing,
     Finished,
+    /// Finished and confirmed by a re-verification sample
+    Verified,
+}
+
+impl ChunkStatus {
+    /// Whether every key of the chunk has been scanned
+    pub fn is_done(&self) -> bool {
+        matches!(self, ChunkStatus::Finished | ChunkStatus::Verified)
+    }
+}
+
+impl fmt::Display for ChunkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ChunkStatus::Pending => "pending",
+            ChunkStatus::Processing => "processing",
+            ChunkStatus::Finished => "finished",
+            ChunkStatus::Verified => "verified",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for ChunkStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(ChunkStatus::Pending),
+            "processing" => Ok(ChunkStatus::Processing),
+            "finished" => Ok(ChunkStatus::Finished),
+            "verified" => Ok(ChunkStatus::Verified),
+            _ => Err(format!("unknown chunk status '{}'", s)),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -22,21 +64,105 @@ pub struct ChunkMetadata {
     pub end_hex: String,
     pub last_processed_hex: String,
     pub status: ChunkStatus,
+    /// Proof of work, built up as the chunk is scanned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<ChunkProof>,
+    /// Script types checked over the scanned part; missing in chunks
+    /// written before the set was selectable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script_types: Option<ScriptTypes>,
+    /// Symmetric keys checked along with the scanned part, when any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symmetry: Option<Symmetry>,
+    /// Order the keys are scanned in, when not ascending
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub walk: Option<Box<KeyWalk>>,
 }
 
 impl ChunkMetadata {
     /// Load an existing chunk metadata file from disk
-    pub fn load(path: &str) -> Self {
-        let file = File::open(path).expect("Unable to read chunk metadata file");
+    pub fn load(path: &str) -> Result<Self> {
+        let file = File::open(path).map_err(|e| Error::io(path, e))?;
         let reader = BufReader::new(file);
-        serde_json::from_reader(reader).expect("Invalid chunk metadata JSON")
+        serde_json::from_reader(reader).map_err(|e| Error::json(path, e))
     }
 
     /// Save chunk metadata to disk
-    pub fn save(&self, path: &str) {
-        let file = File::create(path).expect("Unable to write chunk metadata file");
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, self).expect("Failed to serialize chunk metadata");
+    pub fn save(&self, path: &str) -> Result<()> {
+        let file = File::create(path).map_err(|e| Error::io(path, e))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self).map_err(|e| Error::json(path, e))?;
+        writer.flush().map_err(|e| Error::io(path, e))
+    }
+
+    /// First and last key of the chunk
+    pub fn key_bounds(&self) -> Result<(BigUint, BigUint)> {
+        Ok((
+            BigUint::from_bytes_be(&decode_key(&self.start_hex)?),
+            BigUint::from_bytes_be(&decode_key(&self.end_hex)?),
+        ))
+    }
+
+    /// Script types every scanned key of the chunk was checked against
+    pub fn checked_types(&self) -> ScriptTypes {
+        self.script_types.unwrap_or_default()
+    }
+
+    /// Symmetric keys every scanned key of the chunk was checked with
+    pub fn checked_symmetry(&self) -> Symmetry {
+        self.symmetry.unwrap_or_default()
+    }
+
+    /// The order the chunk's keys are scanned in
+    pub fn walk(&self) -> KeyWalk {
+        self.walk.as_deref().cloned().unwrap_or_default()
+    }
+
+    /// The chunk's walk over its key range
+    pub fn walk_range(&self) -> Result<WalkRange> {
+        let (start, end) = self.key_bounds()?;
+        WalkRange::new(&self.walk(), start, end)
+    }
+
+    /// Start the chunk over on `walk`, from its first key. A permuted walk
+    /// without a key gets a random one.
+    pub fn set_walk(&mut self, walk: KeyWalk) -> Result<()> {
+        let walk = walk.resolved();
+        self.walk = (walk != KeyWalk::Ascending).then(|| Box::new(walk));
+        let range = self.walk_range()?;
+        self.last_processed_hex = hex::encode(key_bytes(&range.key_at(&BigUint::ZERO)));
+        Ok(())
+    }
+
+    /// Number of keys the chunk's walk visits
+    pub fn keys_total(&self) -> Result<BigUint> {
+        Ok(self.walk_range()?.len().clone())
+    }
+
+    /// Position of `last_processed_hex` along the walk, the next key to scan
+    pub fn position(&self) -> Result<BigUint> {
+        let last = BigUint::from_bytes_be(&decode_key(&self.last_processed_hex)?);
+        self.walk_range()?.position_of(&last).ok_or_else(|| {
+            Error::InvalidRange(format!(
+                "chunk {}: last_processed_hex is not a key of its {} walk",
+                self.chunk_id,
+                self.walk()
+            ))
+        })
+    }
+
+    /// Number of keys already scanned, based on `last_processed_hex`
+    pub fn keys_done(&self) -> Result<BigUint> {
+        if self.status.is_done() {
+            return self.keys_total();
+        }
+        self.position()
+    }
+
+    /// The key `last_processed_hex` holds once the whole walk is scanned
+    pub fn final_key_hex(&self) -> Result<String> {
+        let range = self.walk_range()?;
+        Ok(hex::encode(key_bytes(&range.key_at(&(range.len() - 1u32)))))
     }
 
     pub fn path(chunk_id: &BigUint, base_path: &str) -> String {
@@ -52,28 +178,67 @@ impl ChunkMetadata {
         chunk_size: &BigUint,
         base_path: &str,
         puzzle_range: Option<&PuzzleRange>,
-    ) -> Self {
-        let padded_id = format!("{:0>5}", chunk_id.to_str_radix(10));
-        let (start_hex, end_hex) = if let Some(range) = puzzle_range {
-            let base = &range.start + (chunk_id * chunk_size);
-            let start = &base + BigUint::one();
-            let end = &start + chunk_size - BigUint::one();
-            (format!("{:064x}", start), format!("{:064x}", end))
-        } else {
-            calculate_chunk_range(chunk_id, chunk_size)
-        };
+    ) -> Result<Self> {
+        let (start, end) = chunk_key_range(chunk_id, chunk_size, puzzle_range)?;
+        Self::create_with_bounds(chunk_id, base_path, &start, &end)
+    }
+
+    /// Create a chunk of a custom key range.
+    ///
+    /// Unlike puzzle chunks, chunk 0 starts exactly at `range.start`, so
+    /// the chunks tile the range with no gaps.
+    pub fn create_in_range(
+        chunk_id: &BigUint,
+        chunk_size: &BigUint,
+        base_path: &str,
+        range: &PuzzleRange,
+    ) -> Result<Self> {
+        let (start, end) = range_chunk_key_range(chunk_id, chunk_size, range)?;
+        Self::create_with_bounds(chunk_id, base_path, &start, &end)
+    }
+
+    /// Create a chunk of the keys matching a template: chunk `i` holds
+    /// matching keys `i * chunk_size` onwards, walked in ascending order.
+    pub fn create_on_template(
+        chunk_id: &BigUint,
+        chunk_size: &BigUint,
+        base_path: &str,
+        template: &KeyTemplate,
+    ) -> Result<Self> {
+        let (start, end) = template_chunk_key_range(chunk_id, chunk_size, template)?;
+        let mut meta = Self::create_with_bounds(chunk_id, base_path, &start, &end)?;
+        meta.set_walk(KeyWalk::Template(template.clone()))?;
+        meta.save(&Self::path(chunk_id, base_path))?;
+        Ok(meta)
+    }
 
-        let meta = ChunkMetadata {
-            chunk_id: padded_id,
+    /// A chunk of the keys `start..=end` that is being scanned from its start,
+    /// without writing it to disk
+    pub fn with_bounds(chunk_id: &BigUint, start: &BigUint, end: &BigUint) -> Self {
+        let start_hex = format!("{:064x}", start);
+        ChunkMetadata {
+            chunk_id: format!("{:0>5}", chunk_id.to_str_radix(10)),
             start_hex: start_hex.clone(),
-            end_hex: end_hex.clone(),
+            end_hex: format!("{:064x}", end),
             last_processed_hex: start_hex,
             status: ChunkStatus::Processing,
-        };
+            proof: None,
+            script_types: None,
+            symmetry: None,
+            walk: None,
+        }
+    }
 
-        create_dir_all(base_path).expect("Failed to create chunk folder");
-        meta.save(&Self::path(chunk_id, base_path));
-        meta
+    fn create_with_bounds(
+        chunk_id: &BigUint,
+        base_path: &str,
+        start: &BigUint,
+        end: &BigUint,
+    ) -> Result<Self> {
+        let meta = Self::with_bounds(chunk_id, start, end);
+        create_dir_all(base_path).map_err(|e| Error::io(base_path, e))?;
+        meta.save(&Self::path(chunk_id, base_path))?;
+        Ok(meta)
     }
 }
 
@@ -81,6 +246,21 @@ pub fn format_chunk_filename(chunk_id: &BigUint) -> String {
     format!("chunk_{}.json", chunk_id.to_str_radix(10))
 }
 
+/// Parse a zero-padded decimal chunk id such as `"00042"` or `"00000"`
+pub fn parse_chunk_id(chunk_id: &str) -> Result<BigUint> {
+    if chunk_id.is_empty() || !chunk_id.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(Error::InvalidChunkId(chunk_id.to_string()));
+    }
+    BigUint::parse_bytes(chunk_id.as_bytes(), 10)
+        .ok_or_else(|| Error::InvalidChunkId(chunk_id.to_string()))
+}
+
+/// Extract the chunk id from a `chunk_<id>.json` file name
+pub fn chunk_id_from_filename(file_name: &str) -> Option<BigUint> {
+    let id = file_name.strip_prefix("chunk_")?.strip_suffix(".json")?;
+    parse_chunk_id(id).ok()
+}
+
 /// Return the start and end hex of a chunk ID
 pub fn calculate_chunk_range(chunk_id: &BigUint, chunk_size: &BigUint) -> (String, String) {
     let base = chunk_id * chunk_size;
@@ -89,19 +269,116 @@ pub fn calculate_chunk_range(chunk_id: &BigUint, chunk_size: &BigUint) -> (Strin
     (format!("{:064x}", start), format!("{:064x}", end))
 }
 
-pub fn random_chunk_id(chunk_size: &BigUint) -> BigUint {
-    let max_key = BigUint::parse_bytes(
+/// First and last key of a chunk as laid out by `ChunkMetadata::create_new`
+pub fn chunk_key_range(
+    chunk_id: &BigUint,
+    chunk_size: &BigUint,
+    puzzle_range: Option<&PuzzleRange>,
+) -> Result<(BigUint, BigUint)> {
+    let Some(range) = puzzle_range else {
+        let start = chunk_id * chunk_size + BigUint::one();
+        let end = &start + chunk_size - BigUint::one();
+        return Ok((start, end));
+    };
+    // Puzzle chunks start one key past their offset
+    tile_range(chunk_id, chunk_size, range, 1)
+}
+
+/// First and last key of a chunk as laid out by `ChunkMetadata::create_in_range`
+pub fn range_chunk_key_range(
+    chunk_id: &BigUint,
+    chunk_size: &BigUint,
+    range: &PuzzleRange,
+) -> Result<(BigUint, BigUint)> {
+    tile_range(chunk_id, chunk_size, range, 0)
+}
+
+/// Keys of chunk `chunk_id` of `range`, starting `skip` keys past the
+/// chunk's offset and clamped to the range end. Ranges below 2^128 use
+/// native integers.
+fn tile_range(
+    chunk_id: &BigUint,
+    chunk_size: &BigUint,
+    range: &PuzzleRange,
+    skip: u32,
+) -> Result<(BigUint, BigUint)> {
+    if let Some((range_start, range_end)) = range.narrow()
+        && let (Ok(id), Ok(size)) = (u128::try_from(chunk_id), u128::try_from(chunk_size))
+        && let Some(start) = id
+            .checked_mul(size)
+            .and_then(|offset| offset.checked_add(range_start))
+            .and_then(|start| start.checked_add(skip.into()))
+    {
+        if start > range_end {
+            return Err(Error::EmptyRange);
+        }
+        let end = start.saturating_add(size.saturating_sub(1)).min(range_end);
+        return Ok((start.into(), end.into()));
+    }
+    let start = &range.start + chunk_id * chunk_size + skip;
+    if start > range.end {
+        return Err(Error::EmptyRange);
+    }
+    let end = (&start + chunk_size - BigUint::one()).min(range.end.clone());
+    Ok((start, end))
+}
+
+/// Number of chunks needed to tile a custom range with `create_in_range`
+pub fn range_chunk_count(chunk_size: &BigUint, range: &PuzzleRange) -> BigUint {
+    (range.size() + chunk_size - BigUint::one()) / chunk_size
+}
+
+/// First and last key of a chunk as laid out by `ChunkMetadata::create_on_template`
+pub fn template_chunk_key_range(
+    chunk_id: &BigUint,
+    chunk_size: &BigUint,
+    template: &KeyTemplate,
+) -> Result<(BigUint, BigUint)> {
+    let first = chunk_id * chunk_size;
+    let count = template.count();
+    if first >= count {
+        return Err(Error::EmptyRange);
+    }
+    let last = (&first + chunk_size).min(count) - BigUint::one();
+    Ok((template.key_at(&first), template.key_at(&last)))
+}
+
+/// Number of chunks needed to cover a template with `create_on_template`
+pub fn template_chunk_count(chunk_size: &BigUint, template: &KeyTemplate) -> BigUint {
+    (template.count() + chunk_size - BigUint::one()) / chunk_size
+}
+
+/// The largest valid private key, n - 1
+pub fn max_private_key() -> BigUint {
+    BigUint::parse_bytes(
         b"fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364140",
         16,
     )
-    .unwrap();
+    .unwrap()
+}
+
+pub fn random_chunk_id(chunk_size: &BigUint) -> BigUint {
+    let max_key = max_private_key();
     let mut rng = thread_rng();
     let max_chunks = &max_key / chunk_size;
     rng.gen_biguint_below(&max_chunks)
 }
 
-pub fn random_chunk_id_within_range(chunk_size: &BigUint, range: &PuzzleRange) -> BigUint {
+pub fn random_chunk_id_within_range(chunk_size: &BigUint, range: &PuzzleRange) -> Result<BigUint> {
+    if let Some((start, end)) = range.narrow()
+        && let Ok(size) = u128::try_from(chunk_size)
+        && size > 0
+    {
+        let max_chunks = end.saturating_sub(start) / size;
+        if max_chunks == 0 {
+            return Err(Error::EmptyRange);
+        }
+        return Ok(thread_rng().gen_range(0..max_chunks).into());
+    }
     let max_chunks = (&range.end - &range.start) / chunk_size;
+    if max_chunks.is_zero() {
+        return Err(Error::EmptyRange);
+    }
     let mut rng = thread_rng();
-    rng.gen_biguint_below(&max_chunks)
+    Ok(rng.gen_biguint_below(&max_chunks))
 }
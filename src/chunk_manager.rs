@@ -1,59 +1,256 @@
-use crate::chunk::{ChunkMetadata, ChunkStatus, random_chunk_id, random_chunk_id_within_range};
+use crate::chunk::{
+    ChunkMetadata, ChunkStatus, chunk_key_range, parse_chunk_id, random_chunk_id,
+    random_chunk_id_within_range, range_chunk_count, range_chunk_key_range, template_chunk_count,
+    template_chunk_key_range,
+};
+use crate::coverage::IntervalSet;
+use crate::error::{Error, Result};
+use crate::keygen::KeyTemplate;
 use crate::puzzles::PuzzleRange;
+use crate::shard::{Shard, ShardLayout, chunk_id_space, random_chunk_id_in_shard};
+use crate::validate::{check_chunk_file, chunk_files, quarantine_file};
 use num_bigint::BigUint;
+use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
 
+/// Random picks tried before a nearly exhausted range is reported as empty
+const RANDOM_PICK_ATTEMPTS: usize = 1000;
+
+/// Restrictions on the chunks handed out by [`acquire_chunk`] and
+/// [`acquire_range_chunk`]
+#[derive(Debug, Clone, Default)]
+pub struct ChunkFilter {
+    /// Only resume or create chunk ids owned by this shard
+    pub shard: Option<Shard>,
+    /// Never hand out a chunk whose keys all lie in these intervals
+    pub excluded: Option<IntervalSet>,
+}
+
+impl ChunkFilter {
+    fn excludes(&self, start: &BigUint, end: &BigUint) -> bool {
+        self.excluded_until(start, end).is_some()
+    }
+
+    /// The last key of the excluded interval covering `start..=end`
+    fn excluded_until(&self, start: &BigUint, end: &BigUint) -> Option<&BigUint> {
+        self.excluded.as_ref()?.covering_end(start, end)
+    }
+
+    fn excludes_chunk(&self, chunk: &ChunkMetadata) -> Result<bool> {
+        if self.excluded.is_none() {
+            return Ok(false);
+        }
+        let (start, end) = chunk.key_bounds()?;
+        Ok(self.excludes(&start, &end))
+    }
+}
+
+/// Pick the chunk to work on next.
+///
+/// Chunk files that fail validation are moved to the quarantine folder
+/// instead of being silently skipped; a broken chunk asked for by id is
+/// reported with its issues and started over. With a shard, only chunk ids
+/// owned by that shard are resumed or picked; chunks covered by the filter's
+/// excluded intervals are neither resumed nor picked.
 pub fn acquire_chunk(
     base_path: &str,
     chunk_size: &BigUint,
     cli_chunk_id: Option<BigUint>,
     puzzle_range: Option<&PuzzleRange>,
-) -> (ChunkMetadata, BigUint) {
-    let entries =
-        fs::read_dir(base_path).unwrap_or_else(|_| panic!("Failed to read {}", base_path));
+    filter: &ChunkFilter,
+) -> Result<(ChunkMetadata, BigUint)> {
+    fs::create_dir_all(base_path).map_err(|e| Error::io(base_path, e))?;
+    let id_space = chunk_id_space(chunk_size, puzzle_range);
+    let shard = filter.shard.as_ref();
 
     if let Some(cli_id) = cli_chunk_id {
+        if let Some(shard) = shard {
+            shard.check_owns(&cli_id, &id_space)?;
+        }
         let path = ChunkMetadata::path(&cli_id, base_path);
         if Path::new(&path).exists() {
-            let mut chunk = ChunkMetadata::load(&path);
-            match chunk.status {
-                ChunkStatus::Pending => {
-                    chunk.status = ChunkStatus::Processing;
-                    chunk.save(&path);
-                    return (chunk, cli_id);
+            let (loaded, issues) = check_chunk_file(Path::new(&path), puzzle_range);
+            match loaded {
+                Some(mut chunk) if issues.is_empty() => {
+                    if chunk.status == ChunkStatus::Pending {
+                        chunk.status = ChunkStatus::Processing;
+                        chunk.save(&path)?;
+                        return Ok((chunk, cli_id));
+                    }
+                    // Otherwise fall back to the next available chunk
                 }
                 _ => {
-                    // Fallback to next available chunk
+                    let moved = quarantine_file(base_path, Path::new(&path), &issues)?;
+                    let issues: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+                    eprintln!(
+                        "⚠️  Chunk {} was broken ({}); moved to {} and started over",
+                        cli_id,
+                        issues.join(", "),
+                        moved.display()
+                    );
+                    let chunk =
+                        ChunkMetadata::create_new(&cli_id, chunk_size, base_path, puzzle_range)?;
+                    return Ok((chunk, cli_id));
                 }
             }
         } else {
-            let chunk = ChunkMetadata::create_new(&cli_id, chunk_size, base_path, puzzle_range);
-            return (chunk, cli_id);
-        }
-    }
-
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if let Ok(meta) = fs::read_to_string(&path) {
-            if let Ok(mut chunk) = serde_json::from_str::<ChunkMetadata>(&meta) {
-                if chunk.status == ChunkStatus::Pending {
-                    let id =
-                        BigUint::parse_bytes(chunk.chunk_id.trim_start_matches('0').as_bytes(), 10)
-                            .expect("Invalid chunk ID");
-                    chunk.status = ChunkStatus::Processing;
-                    chunk.save(path.to_str().unwrap());
-                    return (chunk, id);
-                }
+            let chunk = ChunkMetadata::create_new(&cli_id, chunk_size, base_path, puzzle_range)?;
+            return Ok((chunk, cli_id));
+        }
+    }
+
+    for path in chunk_files(base_path)? {
+        let (loaded, issues) = check_chunk_file(&path, puzzle_range);
+        let mut chunk = match loaded {
+            Some(chunk) if issues.is_empty() => chunk,
+            _ => {
+                quarantine_file(base_path, &path, &issues)?;
+                continue;
+            }
+        };
+        if chunk.status == ChunkStatus::Pending {
+            let id = parse_chunk_id(&chunk.chunk_id)?;
+            if shard.is_some_and(|s| !s.owns(&id, &id_space)) || filter.excludes_chunk(&chunk)? {
+                continue;
             }
+            chunk.status = ChunkStatus::Processing;
+            chunk.save(&path.to_string_lossy())?;
+            return Ok((chunk, id));
         }
     }
 
-    let new_id = if let Some(r) = puzzle_range {
-        random_chunk_id_within_range(chunk_size, r)
-    } else {
-        random_chunk_id(chunk_size)
+    // Never overwrite an existing chunk file when the random pick collides
+    for _ in 0..RANDOM_PICK_ATTEMPTS {
+        let new_id = match (shard, puzzle_range) {
+            (Some(shard), _) => random_chunk_id_in_shard(chunk_size, puzzle_range, shard)?,
+            (None, Some(r)) => random_chunk_id_within_range(chunk_size, r)?,
+            (None, None) => random_chunk_id(chunk_size),
+        };
+        if ChunkMetadata::exists(&new_id, base_path) {
+            continue;
+        }
+        let (start, end) = chunk_key_range(&new_id, chunk_size, puzzle_range)?;
+        if filter.excludes(&start, &end) {
+            continue;
+        }
+        let chunk = ChunkMetadata::create_new(&new_id, chunk_size, base_path, puzzle_range)?;
+        return Ok((chunk, new_id));
+    }
+    Err(Error::EmptyRange)
+}
+
+/// Pick the next chunk of a custom range, walking the range in order.
+///
+/// Pending chunks are resumed first, then the lowest chunk id without a
+/// file is created. Returns `None` once every chunk of the range exists and
+/// none is pending. With a shard, only chunk ids owned by that shard count,
+/// and chunks covered by the filter's excluded intervals are passed over.
+pub fn acquire_range_chunk(
+    base_path: &str,
+    chunk_size: &BigUint,
+    range: &PuzzleRange,
+    filter: &ChunkFilter,
+) -> Result<Option<(ChunkMetadata, BigUint)>> {
+    acquire_in_order(
+        base_path,
+        range,
+        &range_chunk_count(chunk_size, range),
+        filter,
+        |id| range_chunk_key_range(id, chunk_size, range),
+        |id| ChunkMetadata::create_in_range(id, chunk_size, base_path, range),
+    )
+}
+
+/// Pick the next chunk of the keys matching a template, like
+/// [`acquire_range_chunk`]. Each chunk holds `chunk_size` matching keys.
+pub fn acquire_template_chunk(
+    base_path: &str,
+    chunk_size: &BigUint,
+    template: &KeyTemplate,
+    filter: &ChunkFilter,
+) -> Result<Option<(ChunkMetadata, BigUint)>> {
+    let keys = PuzzleRange {
+        start: template.key_at(&BigUint::ZERO),
+        end: template.key_at(&(template.count() - 1u32)),
     };
-    let chunk = ChunkMetadata::create_new(&new_id, chunk_size, base_path, puzzle_range);
-    (chunk, new_id)
+    acquire_in_order(
+        base_path,
+        &keys,
+        &template_chunk_count(chunk_size, template),
+        filter,
+        |id| template_chunk_key_range(id, chunk_size, template),
+        |id| ChunkMetadata::create_on_template(id, chunk_size, base_path, template),
+    )
+}
+
+/// Resume a pending chunk of `range`, or create the lowest of its `total`
+/// chunk ids without a file. Chunk key ranges grow with the id, so an
+/// excluded interval is passed over in one binary search
+fn acquire_in_order(
+    base_path: &str,
+    range: &PuzzleRange,
+    total: &BigUint,
+    filter: &ChunkFilter,
+    bounds: impl Fn(&BigUint) -> Result<(BigUint, BigUint)>,
+    create: impl Fn(&BigUint) -> Result<ChunkMetadata>,
+) -> Result<Option<(ChunkMetadata, BigUint)>> {
+    fs::create_dir_all(base_path).map_err(|e| Error::io(base_path, e))?;
+    let shard = filter.shard.as_ref();
+
+    let mut existing = HashSet::new();
+    for path in chunk_files(base_path)? {
+        let (loaded, issues) = check_chunk_file(&path, Some(range));
+        let mut chunk = match loaded {
+            Some(chunk) if issues.is_empty() => chunk,
+            _ => {
+                quarantine_file(base_path, &path, &issues)?;
+                continue;
+            }
+        };
+        let id = parse_chunk_id(&chunk.chunk_id)?;
+        if shard.is_some_and(|s| !s.owns(&id, total)) {
+            continue;
+        }
+        if chunk.status == ChunkStatus::Pending && !filter.excludes_chunk(&chunk)? {
+            chunk.status = ChunkStatus::Processing;
+            chunk.save(&path.to_string_lossy())?;
+            return Ok(Some((chunk, id)));
+        }
+        existing.insert(id);
+    }
+
+    let whole = Shard {
+        index: 0,
+        count: 1,
+        layout: ShardLayout::Contiguous,
+    };
+    let shard = shard.unwrap_or(&whole);
+    let owned = shard.chunk_count(total);
+    let mut k = BigUint::ZERO;
+    while k < owned {
+        let next = shard.nth_chunk(&k, total);
+        k += 1u32;
+        if existing.contains(&next) {
+            continue;
+        }
+        let (start, end) = bounds(&next)?;
+        if let Some(last) = filter.excluded_until(&start, &end) {
+            // Jump past every following chunk inside the same interval
+            let mut high = owned.clone();
+            while k < high {
+                let mid: BigUint = (&k + &high) / 2u32;
+                let (_, end) = bounds(&shard.nth_chunk(&mid, total))?;
+                if end <= *last {
+                    k = mid + 1u32;
+                } else {
+                    high = mid;
+                }
+            }
+            continue;
+        }
+        let chunk = create(&next)?;
+        return Ok(Some((chunk, next)));
+    }
+    Ok(None)
 }
@@ -0,0 +1,487 @@
+use btc_key_matcher::chunk::ChunkStatus;
+use btc_key_matcher::codec::Network;
+use btc_key_matcher::config::{Config, TargetFile};
+use btc_key_matcher::error::{Error, Result};
+use btc_key_matcher::keygen::KeyWalk;
+use btc_key_matcher::puzzles::PuzzleRange;
+use btc_key_matcher::range::{load_ranges_file, parse_key_count, parse_range_spec};
+use btc_key_matcher::scanner::{ScanLimits, parse_duration};
+use btc_key_matcher::script::ScriptTypes;
+use btc_key_matcher::shard::{Shard, ShardLayout};
+use btc_key_matcher::symmetry::Symmetry;
+use clap::{Args, Parser, Subcommand};
+use num_bigint::BigUint;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "btc_key_matcher",
+    version,
+    about = "Scan private key ranges for known Bitcoin addresses"
+)]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Scan options used when no subcommand is given, e.g. `--puzzle-id 71`
+    #[command(flatten)]
+    pub scan: ScanArgs,
+}
+
+/// Config file location and overrides for its values
+#[derive(Args, Debug, Clone, Default)]
+pub struct GlobalArgs {
+    /// TOML config file (defaults to ./btc_key_matcher.toml when present)
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+
+    /// Sorted address database
+    #[arg(long, global = true)]
+    pub addr_file: Option<String>,
+
+    /// Labelled address file matched alongside the others (repeatable), as
+    /// `[LABEL=]FILE[,priority=N][,action=stop|continue|notify]`
+    #[arg(long = "target", global = true)]
+    pub targets: Vec<TargetFile>,
+
+    /// Root folder holding chunk metadata
+    #[arg(long, global = true)]
+    pub chunk_folder: Option<String>,
+
+    /// Keys per chunk
+    #[arg(long, global = true)]
+    pub chunk_size: Option<u64>,
+
+    /// Keys per batch between checkpoints
+    #[arg(long, global = true)]
+    pub batch_size: Option<usize>,
+
+    /// Worker threads (defaults to one per CPU)
+    #[arg(long, global = true)]
+    pub threads: Option<usize>,
+
+    /// Script types to derive, e.g. `p2pkh-c,p2tr`
+    #[arg(long, global = true)]
+    pub script_types: Option<ScriptTypes>,
+
+    /// Network to derive addresses for: mainnet, testnet, signet or regtest
+    #[arg(long, global = true)]
+    pub network: Option<Network>,
+
+    /// Symmetric keys to check for every scanned key in full-range scans:
+    /// off, negation or full
+    #[arg(long, global = true)]
+    pub symmetry: Option<Symmetry>,
+}
+
+impl GlobalArgs {
+    /// Load the config file and apply command-line overrides on top
+    pub fn resolve(&self) -> Result<Config> {
+        let mut config = Config::load_or_default(self.config.as_deref())?;
+        if let Some(v) = &self.addr_file {
+            config.addr_file = v.clone();
+            config.targets.clear();
+        }
+        if !self.targets.is_empty() {
+            config.targets = self.targets.clone();
+        }
+        if let Some(v) = &self.chunk_folder {
+            config.chunk_folder = v.clone();
+        }
+        if let Some(v) = self.chunk_size {
+            config.chunk_size = v;
+        }
+        if let Some(v) = self.batch_size {
+            config.batch_size = v;
+        }
+        if let Some(v) = self.threads {
+            config.threads = Some(v);
+        }
+        if let Some(v) = self.script_types {
+            config.script_types = v;
+        }
+        if let Some(v) = self.network {
+            config.network = v;
+        }
+        if let Some(v) = self.symmetry {
+            config.symmetry = v;
+        }
+        config.check().map_err(|message| Error::Config {
+            path: "command line".to_string(),
+            message: message.to_string(),
+        })?;
+        Ok(config)
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Scan a chunk for matching addresses
+    Scan(ScanArgs),
+    /// Summarise chunk progress for a puzzle or the full key range
+    Status(PuzzleArgs),
+    /// List chunk files with their status and progress
+    Chunks(ChunksArgs),
+    /// Manage the sorted address database
+    #[command(subcommand)]
+    Db(DbCommand),
+    /// Check the derivation pipeline, the address database and finished chunks
+    Verify(VerifyArgs),
+    /// Measure key derivation throughput
+    Bench(BenchArgs),
+    /// Show derived addresses for a key, or details of a chunk file
+    Inspect(InspectArgs),
+    /// Validate chunk files and quarantine broken ones
+    Fsck(FsckArgs),
+    /// Merge chunk folders from other machines into the local chunk folder
+    Merge(MergeArgs),
+    /// Export or import scanned key ranges as portable hex intervals
+    #[command(subcommand)]
+    Coverage(CoverageCommand),
+    /// Serve a chunk store to workers over TCP
+    Coordinator(CoordinatorArgs),
+    /// Scan chunks leased from a coordinator
+    Worker(WorkerArgs),
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct PuzzleArgs {
+    /// Puzzle number to restrict the key range to
+    #[arg(long)]
+    pub puzzle_id: Option<u32>,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct ScanArgs {
+    #[command(flatten)]
+    pub puzzle: PuzzleArgs,
+
+    /// Scan this chunk instead of picking one
+    #[arg(
+        long,
+        value_parser = parse_biguint,
+        conflicts_with_all = ["start", "ranges", "ranges_file"]
+    )]
+    pub chunk_id: Option<BigUint>,
+
+    /// First key of a custom range (hex with `0x`, or decimal)
+    #[arg(long, requires = "end", conflicts_with = "puzzle_id")]
+    pub start: Option<String>,
+
+    /// Last key of a custom range, inclusive
+    #[arg(long, requires = "start")]
+    pub end: Option<String>,
+
+    /// Custom range such as `0x1000-0x1fff` or `puzzle:71@40%-41%` (repeatable)
+    #[arg(long = "range", conflicts_with = "puzzle_id")]
+    pub ranges: Vec<String>,
+
+    /// File with one custom range per line
+    #[arg(long, conflicts_with = "puzzle_id")]
+    pub ranges_file: Option<String>,
+
+    /// Order keys are scanned in: ascending, descending, stride:N,
+    /// center[:KEY], permuted[:KEY] for a pseudorandom order, or
+    /// template:PATTERN to scan only keys such as `4???ff`
+    #[arg(long, default_value_t = KeyWalk::Ascending)]
+    pub walk: KeyWalk,
+
+    /// Sample a puzzle with windows of this many keys at random offsets,
+    /// e.g. `2^24`, instead of scanning whole chunks. Windows lying wholly
+    /// inside scanned chunks or windows of another size are skipped, but a
+    /// window may still partly overlap windows of another size.
+    #[arg(
+        long,
+        requires = "puzzle_id",
+        conflicts_with_all = ["start", "ranges", "ranges_file"],
+        value_parser = parse_window
+    )]
+    pub sample_window: Option<BigUint>,
+
+    /// Only take chunks owned by worker `i` of `N` (0-based), e.g. `2/8`
+    #[arg(long = "worker")]
+    pub worker: Option<String>,
+
+    /// How `--worker` divides the chunk ids: interleaved or contiguous
+    #[arg(long, default_value_t = ShardLayout::Interleaved)]
+    pub shard_layout: ShardLayout,
+
+    /// Skip new chunks whose keys are all covered by imported coverage
+    #[arg(long)]
+    pub exclude_imported: bool,
+
+    /// Keep acquiring chunks until a limit is reached
+    #[arg(long)]
+    pub continuous: bool,
+
+    #[command(flatten)]
+    pub limits: LimitArgs,
+
+    #[command(flatten)]
+    pub peers: PeerArgs,
+}
+
+/// Coverage sharing with other scanners, without a coordinator
+#[derive(Args, Debug, Clone, Default)]
+pub struct PeerArgs {
+    /// Listen for peer scanners on this address, e.g. `0.0.0.0:7879`
+    #[arg(long)]
+    pub peer_listen: Option<String>,
+
+    /// Address of another scanner (repeatable); all of them must use the same chunk size
+    #[arg(long = "peer", requires = "peer_listen")]
+    pub peers: Vec<String>,
+
+    /// Name announced to peers, unique in the group (defaults to the listen address)
+    #[arg(long)]
+    pub node_name: Option<String>,
+
+    /// Seconds between coverage syncs with peers
+    #[arg(long, default_value_t = 10)]
+    pub sync_secs: u64,
+}
+
+/// Limits that stop a scan or a worker
+#[derive(Args, Debug, Clone, Default)]
+pub struct LimitArgs {
+    /// Stop after finishing this many chunks
+    #[arg(long)]
+    pub max_chunks: Option<u64>,
+
+    /// Stop after scanning this many keys
+    #[arg(long)]
+    pub max_keys: Option<u64>,
+
+    /// Stop after this much wall-clock time, e.g. `90m` or `12h`
+    #[arg(long, value_parser = parse_duration)]
+    pub max_time: Option<Duration>,
+
+    /// Stop at the next checkpoint once this file exists
+    #[arg(long)]
+    pub stop_file: Option<PathBuf>,
+}
+
+impl LimitArgs {
+    pub fn to_limits(&self) -> ScanLimits {
+        ScanLimits {
+            max_chunks: self.max_chunks,
+            max_keys: self.max_keys,
+            max_time: self.max_time,
+            stop_file: self.stop_file.clone(),
+        }
+    }
+}
+
+impl ScanArgs {
+    /// All custom ranges given with `--start/--end`, `--range` and `--ranges-file`
+    pub fn custom_ranges(&self) -> Result<Vec<PuzzleRange>> {
+        let mut ranges = Vec::new();
+        if let (Some(start), Some(end)) = (&self.start, &self.end) {
+            ranges.push(parse_range_spec(&format!("{}..{}", start, end))?);
+        }
+        for spec in &self.ranges {
+            ranges.push(parse_range_spec(spec)?);
+        }
+        if let Some(path) = &self.ranges_file {
+            ranges.extend(load_ranges_file(path)?);
+        }
+        Ok(ranges)
+    }
+
+    /// The shard given with `--worker`, if any
+    pub fn shard(&self) -> Result<Option<Shard>> {
+        self.worker
+            .as_deref()
+            .map(|spec| Shard::parse(spec, self.shard_layout))
+            .transpose()
+    }
+
+    pub fn limits(&self) -> ScanLimits {
+        let mut limits = self.limits.to_limits();
+        if !self.continuous {
+            limits.max_chunks = Some(1);
+        }
+        limits
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ChunksArgs {
+    #[command(flatten)]
+    pub puzzle: PuzzleArgs,
+
+    /// Only list chunks with this status
+    #[arg(long)]
+    pub status: Option<ChunkStatus>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DbCommand {
+    /// Build the sorted, de-duplicated address database from raw address lists
+    Build {
+        /// Input files with one address per line (extra columns are ignored)
+        #[arg(required = true)]
+        inputs: Vec<String>,
+
+        /// Output file (defaults to the configured address file)
+        #[arg(long)]
+        output: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CoverageCommand {
+    /// Write the keys scanned so far as sorted hex intervals
+    Export {
+        #[command(flatten)]
+        puzzle: PuzzleArgs,
+
+        /// Output file; `.json` writes JSON, anything else CSV
+        output: String,
+
+        /// Provenance recorded in the file (defaults to the host name)
+        #[arg(long)]
+        source: Option<String>,
+    },
+    /// Store another team's coverage file for `scan --exclude-imported`
+    Import {
+        #[command(flatten)]
+        puzzle: PuzzleArgs,
+
+        /// Coverage file in the JSON or CSV export format
+        input: String,
+
+        /// Name to store the import under (defaults to the file name)
+        #[arg(long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct VerifyArgs {
+    #[command(flatten)]
+    pub puzzle: PuzzleArgs,
+
+    /// Re-verify at most this many random finished chunks (all by default)
+    #[arg(long)]
+    pub chunks: Option<usize>,
+
+    /// Keys per chunk derived again on the reference path
+    #[arg(long, default_value_t = 8)]
+    pub keys: usize,
+
+    /// Also re-verify chunks already marked verified
+    #[arg(long)]
+    pub recheck: bool,
+
+    /// Report problems without marking or resetting chunks
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BenchArgs {
+    /// Number of keys to derive
+    #[arg(long, default_value_t = 100_000)]
+    pub keys: usize,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct InspectArgs {
+    /// A 64-digit hex private key or the path to a chunk file
+    pub target: String,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct FsckArgs {
+    #[command(flatten)]
+    pub puzzle: PuzzleArgs,
+
+    /// Report problems without moving files
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct MergeArgs {
+    #[command(flatten)]
+    pub puzzle: PuzzleArgs,
+
+    /// Chunk folders to merge in
+    #[arg(required = true)]
+    pub sources: Vec<String>,
+
+    /// Report what would change without writing files
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CoordinatorArgs {
+    #[command(flatten)]
+    pub puzzle: PuzzleArgs,
+
+    /// Address to listen on (port 0 picks a free port)
+    #[arg(long, default_value = "127.0.0.1:7878")]
+    pub listen: String,
+
+    /// Seconds a lease stays valid without a heartbeat
+    #[arg(long, default_value_t = 600)]
+    pub lease_secs: u64,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct WorkerArgs {
+    /// Coordinator address, e.g. `127.0.0.1:7878` or `tcp://host:7878`
+    #[arg(long)]
+    pub coordinator: String,
+
+    /// Name reported to the coordinator (defaults to host name and process id)
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Folder for leased chunks and queued reports (defaults to `<chunk folder>/worker_cache`)
+    #[arg(long)]
+    pub cache_folder: Option<String>,
+
+    /// Seconds between checkpoints sent to the coordinator
+    #[arg(long, default_value_t = 60)]
+    pub heartbeat_secs: u64,
+
+    /// Seconds to wait before retrying an unreachable coordinator
+    #[arg(long, default_value_t = 10)]
+    pub retry_secs: u64,
+
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+impl WorkerArgs {
+    /// Coordinator address with any `tcp://` scheme removed
+    pub fn coordinator_addr(&self) -> &str {
+        self.coordinator
+            .strip_prefix("tcp://")
+            .unwrap_or(&self.coordinator)
+            .trim_end_matches('/')
+    }
+
+    pub fn worker_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "worker".to_string());
+            format!("{}-{}", host, std::process::id())
+        })
+    }
+}
+
+fn parse_window(value: &str) -> std::result::Result<BigUint, String> {
+    parse_key_count(value).map_err(|e| e.to_string())
+}
+
+fn parse_biguint(value: &str) -> std::result::Result<BigUint, String> {
+    BigUint::parse_bytes(value.as_bytes(), 10)
+        .ok_or_else(|| format!("'{}' is not a decimal number", value))
+}
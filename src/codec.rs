@@ -0,0 +1,484 @@
+use crate::error::{Error, Result};
+use base58::{FromBase58, ToBase58};
+use bech32::{FromBase32, ToBase32, Variant, u5};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+/// The Bitcoin network an address or key belongs to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+/// Version bytes and segwit prefix of a network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkParams {
+    pub name: &'static str,
+    pub p2pkh_version: u8,
+    pub p2sh_version: u8,
+    pub wif_version: u8,
+    /// Human-readable part of segwit addresses
+    pub hrp: &'static str,
+}
+
+const MAINNET: NetworkParams = NetworkParams {
+    name: "mainnet",
+    p2pkh_version: 0x00,
+    p2sh_version: 0x05,
+    wif_version: 0x80,
+    hrp: "bc",
+};
+
+const TESTNET: NetworkParams = NetworkParams {
+    name: "testnet",
+    p2pkh_version: 0x6f,
+    p2sh_version: 0xc4,
+    wif_version: 0xef,
+    hrp: "tb",
+};
+
+const SIGNET: NetworkParams = NetworkParams {
+    name: "signet",
+    ..TESTNET
+};
+
+const REGTEST: NetworkParams = NetworkParams {
+    name: "regtest",
+    hrp: "bcrt",
+    ..TESTNET
+};
+
+impl Network {
+    /// Every network. Signet and regtest share testnet's version bytes, so
+    /// decoding without a network in mind resolves them to testnet.
+    pub const ALL: [Network; 4] = [
+        Network::Mainnet,
+        Network::Testnet,
+        Network::Signet,
+        Network::Regtest,
+    ];
+
+    pub fn params(self) -> &'static NetworkParams {
+        match self {
+            Network::Mainnet => &MAINNET,
+            Network::Testnet => &TESTNET,
+            Network::Signet => &SIGNET,
+            Network::Regtest => &REGTEST,
+        }
+    }
+
+    pub fn p2pkh_version(self) -> u8 {
+        self.params().p2pkh_version
+    }
+
+    pub fn p2sh_version(self) -> u8 {
+        self.params().p2sh_version
+    }
+
+    pub fn wif_version(self) -> u8 {
+        self.params().wif_version
+    }
+
+    /// Human-readable part of segwit addresses
+    pub fn hrp(self) -> &'static str {
+        self.params().hrp
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.params().name)
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Network::ALL
+            .into_iter()
+            .find(|n| n.params().name.eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                format!(
+                    "unknown network '{}', expected mainnet, testnet, signet or regtest",
+                    s
+                )
+            })
+    }
+}
+
+fn checksum(data: &[u8]) -> [u8; 4] {
+    let hash = Sha256::digest(Sha256::digest(data));
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Base58 of a version byte, the payload and a 4-byte double-SHA-256 checksum
+pub fn base58check_encode(version: u8, payload: &[u8]) -> String {
+    let mut data = Vec::with_capacity(payload.len() + 5);
+    data.push(version);
+    data.extend_from_slice(payload);
+    data.extend(checksum(&data));
+    data.to_base58()
+}
+
+/// Split a Base58Check string into its version byte and payload
+pub fn base58check_decode(encoded: &str) -> Result<(u8, Vec<u8>)> {
+    let data = encoded
+        .from_base58()
+        .map_err(|e| Error::Encoding(format!("'{}' is not base58: {:?}", encoded, e)))?;
+    if data.len() < 5 {
+        return Err(Error::Encoding(format!("'{}' is too short", encoded)));
+    }
+    let (body, check) = data.split_at(data.len() - 4);
+    if checksum(body) != check {
+        return Err(Error::Encoding(format!("'{}' has a bad checksum", encoded)));
+    }
+    Ok((body[0], body[1..].to_vec()))
+}
+
+/// Encode a segwit address: bech32 for version 0, bech32m for later versions
+pub fn segwit_encode(hrp: &str, version: u8, program: &[u8]) -> Result<String> {
+    check_witness_program(version, program)?;
+    let variant = if version == 0 {
+        Variant::Bech32
+    } else {
+        Variant::Bech32m
+    };
+    let mut data = vec![u5::try_from_u8(version).map_err(|e| Error::Encoding(e.to_string()))?];
+    data.extend(program.to_base32());
+    bech32::encode(hrp, data, variant).map_err(|e| Error::Encoding(e.to_string()))
+}
+
+/// Decode a segwit address into its human-readable part, witness version
+/// and program, checking the checksum variant required by the version
+pub fn segwit_decode(encoded: &str) -> Result<(String, u8, Vec<u8>)> {
+    let invalid = |reason: &str| Error::Encoding(format!("'{}' {}", encoded, reason));
+    let (hrp, data, variant) =
+        bech32::decode(encoded).map_err(|e| invalid(&format!("is not bech32: {}", e)))?;
+    let (version, program) = data.split_first().ok_or_else(|| invalid("has no data"))?;
+    let version = version.to_u8();
+    let program = Vec::<u8>::from_base32(program).map_err(|e| invalid(&e.to_string()))?;
+    let expected = if version == 0 {
+        Variant::Bech32
+    } else {
+        Variant::Bech32m
+    };
+    if variant != expected {
+        return Err(invalid(&format!(
+            "uses the wrong checksum for witness version {}",
+            version
+        )));
+    }
+    check_witness_program(version, &program).map_err(|_| {
+        invalid(&format!(
+            "has a {}-byte program for witness version {}",
+            program.len(),
+            version
+        ))
+    })?;
+    Ok((hrp, version, program))
+}
+
+/// Witness program length rules of BIP-141
+fn check_witness_program(version: u8, program: &[u8]) -> Result<()> {
+    let valid = match version {
+        0 => program.len() == 20 || program.len() == 32,
+        1..=16 => (2..=40).contains(&program.len()),
+        _ => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::Encoding(format!(
+            "invalid witness version {} program of {} bytes",
+            version,
+            program.len()
+        )))
+    }
+}
+
+/// A decoded Wallet Import Format private key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wif {
+    pub key: [u8; 32],
+    /// Whether the key's addresses use the compressed public key
+    pub compressed: bool,
+    pub network: Network,
+}
+
+impl Wif {
+    pub fn encode(&self) -> String {
+        let mut payload = self.key.to_vec();
+        if self.compressed {
+            payload.push(0x01);
+        }
+        base58check_encode(self.network.wif_version(), &payload)
+    }
+
+    pub fn decode(encoded: &str) -> Result<Self> {
+        let (version, payload) = base58check_decode(encoded)?;
+        let network = Network::ALL
+            .into_iter()
+            .find(|n| n.wif_version() == version)
+            .ok_or_else(|| {
+                Error::Encoding(format!(
+                    "'{}' has unknown WIF version {:#04x}",
+                    encoded, version
+                ))
+            })?;
+        let compressed = match payload.len() {
+            32 => false,
+            33 if payload[32] == 0x01 => true,
+            _ => {
+                return Err(Error::Encoding(format!(
+                    "'{}' is not a 32-byte key with an optional compression flag",
+                    encoded
+                )));
+            }
+        };
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&payload[..32]);
+        Ok(Self {
+            key,
+            compressed,
+            network,
+        })
+    }
+}
+
+/// A bare pay-to-pubkey output, written as the hex of its compressed or
+/// uncompressed SEC public key
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct P2pk {
+    pub key: Vec<u8>,
+}
+
+impl P2pk {
+    pub fn compressed(&self) -> bool {
+        self.key.len() == 33
+    }
+}
+
+impl FromStr for P2pk {
+    type Err = Error;
+
+    /// Parse a 33-byte `02`/`03` or 65-byte `04` key that lies on the curve
+    fn from_str(s: &str) -> Result<Self> {
+        let key = hex::decode(s)
+            .map_err(|e| Error::Encoding(format!("'{}' is not a hex public key: {}", s, e)))?;
+        let sec_length = matches!((key.first(), key.len()), (Some(2 | 3), 33) | (Some(4), 65));
+        if !sec_length || secp256k1::PublicKey::from_slice(&key).is_err() {
+            return Err(Error::Encoding(format!(
+                "'{}' is not a compressed or uncompressed SEC public key",
+                s
+            )));
+        }
+        Ok(Self { key })
+    }
+}
+
+impl fmt::Display for P2pk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(&self.key))
+    }
+}
+
+/// A parsed Bitcoin address
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Address {
+    P2pkh {
+        network: Network,
+        hash: [u8; 20],
+    },
+    P2sh {
+        network: Network,
+        hash: [u8; 20],
+    },
+    P2wpkh {
+        network: Network,
+        hash: [u8; 20],
+    },
+    P2wsh {
+        network: Network,
+        hash: [u8; 32],
+    },
+    P2tr {
+        network: Network,
+        output_key: [u8; 32],
+    },
+    /// A segwit output of a version without a defined script type
+    Witness {
+        network: Network,
+        version: u8,
+        program: Vec<u8>,
+    },
+}
+
+impl Address {
+    pub fn network(&self) -> Network {
+        match self {
+            Address::P2pkh { network, .. }
+            | Address::P2sh { network, .. }
+            | Address::P2wpkh { network, .. }
+            | Address::P2wsh { network, .. }
+            | Address::P2tr { network, .. }
+            | Address::Witness { network, .. } => *network,
+        }
+    }
+
+    /// Short name of the output type, e.g. `p2wpkh`
+    pub fn script_type(&self) -> &'static str {
+        match self {
+            Address::P2pkh { .. } => "p2pkh",
+            Address::P2sh { .. } => "p2sh",
+            Address::P2wpkh { .. } => "p2wpkh",
+            Address::P2wsh { .. } => "p2wsh",
+            Address::P2tr { .. } => "p2tr",
+            Address::Witness { .. } => "witness",
+        }
+    }
+
+    /// The hash or witness program the address commits to
+    pub fn payload(&self) -> &[u8] {
+        match self {
+            Address::P2pkh { hash, .. }
+            | Address::P2sh { hash, .. }
+            | Address::P2wpkh { hash, .. } => hash,
+            Address::P2wsh { hash, .. } => hash,
+            Address::P2tr { output_key, .. } => output_key,
+            Address::Witness { program, .. } => program,
+        }
+    }
+
+    /// Parse an address that must belong to `network`. Unlike `parse`, this
+    /// tells signet and regtest addresses apart from testnet ones, since
+    /// they only differ from it by which network is expected.
+    pub fn parse_for(encoded: &str, network: Network) -> Result<Self> {
+        let mut address: Address = encoded.parse()?;
+        let params = network.params();
+        let found = address.network().params();
+        let same_encoding = match address {
+            Address::P2pkh { .. } => found.p2pkh_version == params.p2pkh_version,
+            Address::P2sh { .. } => found.p2sh_version == params.p2sh_version,
+            _ => found.hrp == params.hrp,
+        };
+        if !same_encoding {
+            return Err(Error::Encoding(format!(
+                "'{}' is a {} address, not {}",
+                encoded,
+                address.network(),
+                network
+            )));
+        }
+        match &mut address {
+            Address::P2pkh { network: n, .. }
+            | Address::P2sh { network: n, .. }
+            | Address::P2wpkh { network: n, .. }
+            | Address::P2wsh { network: n, .. }
+            | Address::P2tr { network: n, .. }
+            | Address::Witness { network: n, .. } => *n = network,
+        }
+        Ok(address)
+    }
+
+    fn from_base58(encoded: &str) -> Result<Self> {
+        let (version, payload) = base58check_decode(encoded)?;
+        let hash: [u8; 20] = payload.as_slice().try_into().map_err(|_| {
+            Error::Encoding(format!(
+                "'{}' has a {}-byte hash, not 20",
+                encoded,
+                payload.len()
+            ))
+        })?;
+        for network in Network::ALL {
+            if version == network.p2pkh_version() {
+                return Ok(Address::P2pkh { network, hash });
+            }
+            if version == network.p2sh_version() {
+                return Ok(Address::P2sh { network, hash });
+            }
+        }
+        Err(Error::Encoding(format!(
+            "'{}' has unknown version byte {:#04x}",
+            encoded, version
+        )))
+    }
+
+    fn from_segwit(encoded: &str) -> Result<Self> {
+        let (hrp, version, program) = segwit_decode(encoded)?;
+        let network = Network::ALL
+            .into_iter()
+            .find(|n| n.hrp() == hrp)
+            .ok_or_else(|| {
+                Error::Encoding(format!("'{}' has unknown prefix '{}'", encoded, hrp))
+            })?;
+        Ok(match (version, program.len()) {
+            (0, 20) => Address::P2wpkh {
+                network,
+                hash: program.as_slice().try_into().unwrap_or_default(),
+            },
+            (0, _) => Address::P2wsh {
+                network,
+                hash: program.as_slice().try_into().unwrap_or_default(),
+            },
+            (1, 32) => Address::P2tr {
+                network,
+                output_key: program.as_slice().try_into().unwrap_or_default(),
+            },
+            _ => Address::Witness {
+                network,
+                version,
+                program,
+            },
+        })
+    }
+
+    fn to_encoded(&self) -> Result<String> {
+        let network = self.network();
+        Ok(match self {
+            Address::P2pkh { hash, .. } => base58check_encode(network.p2pkh_version(), hash),
+            Address::P2sh { hash, .. } => base58check_encode(network.p2sh_version(), hash),
+            Address::P2wpkh { hash, .. } => segwit_encode(network.hrp(), 0, hash)?,
+            Address::P2wsh { hash, .. } => segwit_encode(network.hrp(), 0, hash)?,
+            Address::P2tr { output_key, .. } => segwit_encode(network.hrp(), 1, output_key)?,
+            Address::Witness {
+                version, program, ..
+            } => segwit_encode(network.hrp(), *version, program)?,
+        })
+    }
+}
+
+impl FromStr for Address {
+    type Err = Error;
+
+    /// Parse and fully validate an address, including its checksum
+    fn from_str(s: &str) -> Result<Self> {
+        let is_segwit = Network::ALL.iter().any(|n| {
+            s.get(..n.hrp().len() + 1)
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}1", n.hrp())))
+        });
+        if is_segwit {
+            Self::from_segwit(s)
+        } else {
+            Self::from_base58(s)
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    /// A hand-built witness program of an invalid length has no encoding
+    /// and is shown as a placeholder, so formatting never fails
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_encoded() {
+            Ok(encoded) => f.write_str(&encoded),
+            Err(e) => write!(f, "<invalid address: {}>", e),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::cli::BenchArgs;
+use btc_key_matcher::address::{derive_addresses, public_key};
+use btc_key_matcher::error::Result;
+use btc_key_matcher::hash::{HashBackend, hash160_batch_with};
+use btc_key_matcher::keygen::HexKeyGenerator;
+use rayon::prelude::*;
+use std::time::Instant;
+
+pub fn run(args: &BenchArgs) -> Result<()> {
+    let end = format!("{:064x}", args.keys.max(1));
+    let mut generator = HexKeyGenerator::new(&format!("{:064x}", 1), &end)?;
+    let batch = generator.next_batch(args.keys.max(1));
+
+    println!(
+        "⏱️  Deriving addresses for {} keys on {} threads",
+        batch.len(),
+        rayon::current_num_threads()
+    );
+    let start = Instant::now();
+    batch
+        .par_iter()
+        .try_for_each(|raw_key| derive_addresses(raw_key).map(|_| ()))?;
+    let elapsed = start.elapsed();
+
+    println!(
+        "✅ {} keys in {:.2?} ({:.0} keys/s)",
+        batch.len(),
+        elapsed,
+        batch.len() as f64 / elapsed.as_secs_f64()
+    );
+
+    let keys = batch
+        .par_iter()
+        .map(|raw_key| Ok(public_key(raw_key)?.serialize()))
+        .collect::<Result<Vec<_>>>()?;
+    let mut hashes = vec![[0u8; 20]; keys.len()];
+    println!(
+        "⏱️  Hashing {} compressed keys on one thread (detected {})",
+        keys.len(),
+        HashBackend::detect()
+    );
+    for backend in HashBackend::ALL.into_iter().filter(|b| b.is_available()) {
+        let start = Instant::now();
+        hash160_batch_with(backend, &keys, &mut hashes);
+        let elapsed = start.elapsed();
+        println!(
+            "   {:<8} {:.2?} ({:.0} hashes/s)",
+            backend.name(),
+            elapsed,
+            keys.len() as f64 / elapsed.as_secs_f64()
+        );
+    }
+    Ok(())
+}
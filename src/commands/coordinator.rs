@@ -0,0 +1,29 @@
+use crate::cli::CoordinatorArgs;
+use crate::commands::puzzle_range;
+use btc_key_matcher::config::Config;
+use btc_key_matcher::coordinator::{Coordinator, serve};
+use btc_key_matcher::error::{Error, Result};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub fn run(config: &Config, args: &CoordinatorArgs) -> Result<()> {
+    let puzzle_range = puzzle_range(args.puzzle.puzzle_id)?;
+    let base_folder = config.chunk_folder_for(args.puzzle.puzzle_id);
+    let coordinator = Coordinator::open(
+        &base_folder,
+        config.chunk_size(),
+        puzzle_range,
+        Duration::from_secs(args.lease_secs),
+    )?;
+
+    let listener = TcpListener::bind(&args.listen).map_err(|e| Error::io(&args.listen, e))?;
+    let addr = listener
+        .local_addr()
+        .map_err(|e| Error::io(&args.listen, e))?;
+    println!("📡 Coordinator listening on {}", addr);
+    println!("   Chunk store: {}", base_folder);
+    println!("   Lease time:  {}s", args.lease_secs);
+
+    serve(listener, Arc::new(Mutex::new(coordinator)))
+}
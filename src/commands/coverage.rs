@@ -0,0 +1,84 @@
+use crate::cli::CoverageCommand;
+use crate::commands::{chunk_folders, puzzle_range};
+use btc_key_matcher::config::Config;
+use btc_key_matcher::coverage::{Coverage, IntervalSet, folder_coverage, store_imported};
+use btc_key_matcher::error::{Error, Result};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn run(config: &Config, command: &CoverageCommand) -> Result<()> {
+    match command {
+        CoverageCommand::Export {
+            puzzle,
+            output,
+            source,
+        } => {
+            let mut intervals = IntervalSet::default();
+            for folder in chunk_folders(config, puzzle.puzzle_id)? {
+                intervals.extend(&folder_coverage(&folder, config.script_types)?);
+            }
+            let coverage = Coverage {
+                puzzle: puzzle.puzzle_id,
+                source: source.clone().unwrap_or_else(|| {
+                    std::env::var("HOSTNAME").unwrap_or_else(|_| "btc_key_matcher".to_string())
+                }),
+                exported_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .ok()
+                    .map(|d| d.as_secs()),
+                script_types: config.script_types,
+                intervals,
+            };
+            coverage.save(output)?;
+            println!(
+                "📤 Exported {} interval(s), {} keys checked for {}, to {}",
+                coverage.intervals.intervals().len(),
+                coverage.intervals.key_count(),
+                coverage.script_types,
+                output
+            );
+            Ok(())
+        }
+        CoverageCommand::Import {
+            puzzle,
+            input,
+            name,
+        } => {
+            let mut coverage = Coverage::load(input)?;
+            if let (Some(ours), Some(theirs)) = (puzzle.puzzle_id, coverage.puzzle)
+                && ours != theirs
+            {
+                return Err(Error::InvalidRange(format!(
+                    "{} covers puzzle {}, not puzzle {}",
+                    input, theirs, ours
+                )));
+            }
+            coverage.puzzle = coverage.puzzle.or(puzzle.puzzle_id);
+            if let Some(range) = puzzle_range(coverage.puzzle)? {
+                coverage.check_within(&range)?;
+            }
+
+            let name = match name {
+                Some(name) => name.clone(),
+                None => Path::new(input)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "imported".to_string()),
+            };
+            let stored = store_imported(&config.chunk_folder, &name, &coverage)?;
+            println!(
+                "📥 Imported {} interval(s), {} keys checked for {}, from {} into {}",
+                coverage.intervals.intervals().len(),
+                coverage.intervals.key_count(),
+                coverage.script_types,
+                if coverage.source.is_empty() {
+                    "an unnamed source"
+                } else {
+                    &coverage.source
+                },
+                stored.display()
+            );
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use crate::cli::DbCommand;
+use btc_key_matcher::config::Config;
+use btc_key_matcher::error::Result;
+use btc_key_matcher::search::build_address_database;
+
+pub fn run(config: &Config, command: &DbCommand) -> Result<()> {
+    match command {
+        DbCommand::Build { inputs, output } => {
+            let output = output.as_deref().unwrap_or(&config.addr_file);
+            println!(
+                "🏗️  Building {} address database from {} file(s)",
+                config.network,
+                inputs.len()
+            );
+            let report = build_address_database(inputs, output, config.network)?;
+            if !report.rejected.is_empty() {
+                println!(
+                    "⚠️  Skipped {} entries that are not {} addresses or P2PK keys",
+                    report.rejected.len(),
+                    config.network
+                );
+                for (location, reason) in report.rejected.iter().take(5) {
+                    println!("   {}: {}", location, reason);
+                }
+            }
+            println!("✅ Wrote {} unique addresses to {}", report.written, output);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use crate::cli::FsckArgs;
+use crate::commands::puzzle_range;
+use btc_key_matcher::config::Config;
+use btc_key_matcher::error::Result;
+use btc_key_matcher::validate::fsck;
+
+pub fn run(config: &Config, args: &FsckArgs) -> Result<()> {
+    let puzzle_range = puzzle_range(args.puzzle.puzzle_id)?;
+    let base_folder = config.chunk_folder_for(args.puzzle.puzzle_id);
+
+    println!("🔍 Checking chunk files in {}", base_folder);
+    let report = fsck(&base_folder, puzzle_range.as_ref(), args.dry_run)?;
+    for (path, issues) in &report.broken {
+        println!("❌ {}", path.display());
+        for issue in issues {
+            println!("   - {}", issue);
+        }
+    }
+    println!(
+        "✅ Checked {} chunk files, {} broken, {} quarantined",
+        report.checked,
+        report.broken.len(),
+        report.quarantined.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use crate::cli::InspectArgs;
+use crate::commands::percent;
+use btc_key_matcher::address::{derive, private_key_to_wif, public_key};
+use btc_key_matcher::chunk::ChunkMetadata;
+use btc_key_matcher::codec::Network;
+use btc_key_matcher::config::Config;
+use btc_key_matcher::error::Result;
+use btc_key_matcher::keygen::decode_key;
+use btc_key_matcher::script::ScriptTypes;
+use btc_key_matcher::validate::check_chunk_file;
+use std::path::Path;
+
+pub fn run(config: &Config, args: &InspectArgs) -> Result<()> {
+    let path = Path::new(&args.target);
+    if path.is_file() {
+        inspect_chunk(path)
+    } else {
+        inspect_key(&args.target, config.network)
+    }
+}
+
+fn inspect_key(key_hex: &str, network: Network) -> Result<()> {
+    let raw_key = decode_key(key_hex)?;
+    println!("🔑 Private key: {}", hex::encode(raw_key));
+    if network != Network::Mainnet {
+        println!("   Network:            {}", network);
+    }
+    println!(
+        "   WIF (compressed):   {}",
+        private_key_to_wif(&raw_key, true, network)
+    );
+    println!(
+        "   WIF (uncompressed): {}",
+        private_key_to_wif(&raw_key, false, network)
+    );
+    for address in derive(&public_key(&raw_key)?, network, ScriptTypes::ALL)? {
+        println!("   {:<20} {}", address.script.label(), address);
+    }
+    Ok(())
+}
+
+fn inspect_chunk(path: &Path) -> Result<()> {
+    let meta = ChunkMetadata::load(&path.to_string_lossy())?;
+    println!("📄 {}", path.display());
+    println!("   Chunk ID:  {}", meta.chunk_id);
+    println!("   Status:    {}", meta.status);
+    println!("   Start Key: {}", meta.start_hex);
+    println!("   End Key:   {}", meta.end_hex);
+    println!("   Last Key:  {}", meta.last_processed_hex);
+    if let Some(walk) = &meta.walk {
+        println!("   Walk:      {}", walk);
+    }
+    println!("   Types:     {}", meta.checked_types());
+    if let Some(symmetry) = meta.symmetry {
+        println!("   Symmetry:  {}", symmetry);
+    }
+    println!(
+        "   Progress:  {:.4}%",
+        percent(&meta.keys_done()?, &meta.keys_total()?)
+    );
+    if let Some(proof) = &meta.proof {
+        println!(
+            "   Proof:     {} distinguished key(s) at {} bits, fingerprint {}",
+            proof.distinguished.len(),
+            proof.bits,
+            proof.fingerprint
+        );
+    }
+
+    let (_, issues) = check_chunk_file(path, None);
+    for issue in &issues {
+        println!("   ⚠️  {}", issue);
+    }
+    Ok(())
+}
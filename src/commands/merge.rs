@@ -0,0 +1,24 @@
+use crate::cli::MergeArgs;
+use crate::commands::puzzle_range;
+use btc_key_matcher::config::Config;
+use btc_key_matcher::error::Result;
+use btc_key_matcher::merge::merge_chunk_folders;
+
+pub fn run(config: &Config, args: &MergeArgs) -> Result<()> {
+    let puzzle_range = puzzle_range(args.puzzle.puzzle_id)?;
+    let dest = config.chunk_folder_for(args.puzzle.puzzle_id);
+
+    println!("🔀 Merging {} folder(s) into {}", args.sources.len(), dest);
+    let report = merge_chunk_folders(&args.sources, &dest, puzzle_range.as_ref(), args.dry_run)?;
+    for (path, reason) in &report.skipped {
+        println!("⚠️  Skipped {}: {}", path.display(), reason);
+    }
+    println!(
+        "✅ {} copied, {} updated, {} unchanged, {} skipped",
+        report.copied,
+        report.updated,
+        report.unchanged,
+        report.skipped.len()
+    );
+    Ok(())
+}
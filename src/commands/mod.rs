@@ -0,0 +1,74 @@
+pub mod bench;
+pub mod coordinator;
+pub mod coverage;
+pub mod db;
+pub mod fsck;
+pub mod inspect;
+pub mod merge;
+pub mod scan;
+pub mod status;
+pub mod verify;
+pub mod worker;
+
+use btc_key_matcher::config::Config;
+use btc_key_matcher::error::{Error, Result};
+use btc_key_matcher::puzzles::{PuzzleRange, get_puzzle_ranges};
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+use std::fs;
+use std::path::Path;
+
+/// Look up the key range of a puzzle, rejecting unknown puzzle numbers
+pub fn puzzle_range(puzzle_id: Option<u32>) -> Result<Option<PuzzleRange>> {
+    match puzzle_id {
+        Some(pid) => get_puzzle_ranges()
+            .remove(&pid)
+            .map(Some)
+            .ok_or(Error::UnknownPuzzle(pid)),
+        None => Ok(None),
+    }
+}
+
+/// The puzzle's chunk folder, or without a puzzle the chunk root and the
+/// puzzle and range folders below it, each followed by its sample window
+/// folders
+pub fn chunk_folders(config: &Config, puzzle_id: Option<u32>) -> Result<Vec<String>> {
+    let base_folder = config.chunk_folder_for(puzzle_id);
+    if !Path::new(&base_folder).exists() {
+        return Ok(Vec::new());
+    }
+    let mut folders = vec![base_folder.clone()];
+    if puzzle_id.is_none() {
+        folders.extend(subfolders(&base_folder, &["puzzle_", "range_"])?);
+    }
+    let mut with_windows = Vec::new();
+    for folder in folders {
+        let windows = subfolders(&folder, &["window_"])?;
+        with_windows.push(folder);
+        with_windows.extend(windows);
+    }
+    Ok(with_windows)
+}
+
+/// Sorted subfolders of `folder` whose name starts with one of `prefixes`
+fn subfolders(folder: &str, prefixes: &[&str]) -> Result<Vec<String>> {
+    let entries = fs::read_dir(folder).map_err(|e| Error::io(folder, e))?;
+    let mut nested: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| prefixes.iter().any(|prefix| name.starts_with(prefix)))
+        .map(|name| format!("{}/{}", folder, name))
+        .collect();
+    nested.sort();
+    Ok(nested)
+}
+
+/// `part` as a percentage of `whole`
+pub fn percent(part: &BigUint, whole: &BigUint) -> f64 {
+    if whole.is_zero() {
+        return 0.0;
+    }
+    let scaled = part * 1_000_000u32 / whole;
+    scaled.to_f64().unwrap_or(0.0) / 10_000.0
+}
@@ -0,0 +1,538 @@
+use crate::cli::{PeerArgs, ScanArgs};
+use crate::commands::{puzzle_range, subfolders};
+use btc_key_matcher::chunk::{ChunkMetadata, ChunkStatus};
+use btc_key_matcher::chunk_manager::{
+    ChunkFilter, acquire_chunk, acquire_range_chunk, acquire_template_chunk,
+};
+use btc_key_matcher::codec::Network;
+use btc_key_matcher::config::Config;
+use btc_key_matcher::coverage::{IntervalSet, folder_coverage, load_imported};
+use btc_key_matcher::error::{Error, Result};
+use btc_key_matcher::keygen::{KeyTemplate, KeyWalk};
+use btc_key_matcher::peer::{PeerNode, claim_with_peers, serve_peers, sync_with_peers};
+use btc_key_matcher::puzzles::PuzzleRange;
+use btc_key_matcher::range::{range_folder_name, template_folder_name, window_folder_name};
+use btc_key_matcher::scanner::{BatchProgress, ChunkOutcome, MatchReport, ScanStats, scan_chunk};
+use btc_key_matcher::search::{MatchAction, TargetSet};
+use btc_key_matcher::symmetry::Symmetry;
+
+use num_bigint::BigUint;
+use num_traits::Zero;
+use std::collections::HashSet;
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Matches are appended here, one CSV line each
+pub const MATCH_FILE: &str = "match_found.csv";
+
+/// The chunk currently being scanned, reset to pending on Ctrl+C
+pub type ActiveChunk = Arc<Mutex<Option<(ChunkMetadata, String)>>>;
+
+/// Where the next chunk comes from
+enum ScanTarget {
+    /// Random chunks of a puzzle or of the full key space
+    Random {
+        base_folder: String,
+        puzzle_range: Option<PuzzleRange>,
+    },
+    /// Custom ranges, each walked in order in its own folder
+    Ranges(Vec<(PuzzleRange, String)>),
+    /// The keys matching a template, walked in order
+    Template {
+        template: KeyTemplate,
+        folder: String,
+    },
+    /// Windows of a puzzle at random offsets, kept apart from its chunks
+    Windows {
+        folder: String,
+        chunk_folder: String,
+        puzzle_range: PuzzleRange,
+        window: BigUint,
+    },
+}
+
+impl ScanTarget {
+    fn from_args(config: &Config, args: &ScanArgs) -> Result<Self> {
+        let ranges = args.custom_ranges()?;
+        if let KeyWalk::Template(template) = &args.walk {
+            if args.sample_window.is_some() {
+                return Err(Error::Config {
+                    path: "walk".to_string(),
+                    message: "a template picks its own keys and cannot walk sample windows"
+                        .to_string(),
+                });
+            }
+            if args.puzzle.puzzle_id.is_some() || !ranges.is_empty() {
+                return Err(Error::Config {
+                    path: "walk".to_string(),
+                    message: "a template picks its own keys and cannot be combined with a puzzle or custom ranges".to_string(),
+                });
+            }
+            return Ok(ScanTarget::Template {
+                template: template.clone(),
+                folder: format!("{}/{}", config.chunk_folder, template_folder_name(template)),
+            });
+        }
+        if ranges.is_empty() {
+            let puzzle_id = args.puzzle.puzzle_id;
+            if let (Some(window), Some(puzzle_range)) =
+                (&args.sample_window, puzzle_range(puzzle_id)?)
+            {
+                let chunk_folder = config.chunk_folder_for(puzzle_id);
+                return Ok(ScanTarget::Windows {
+                    folder: format!("{}/{}", chunk_folder, window_folder_name(window)),
+                    chunk_folder,
+                    puzzle_range,
+                    window: window.clone(),
+                });
+            }
+            return Ok(ScanTarget::Random {
+                base_folder: config.chunk_folder_for(puzzle_id),
+                puzzle_range: puzzle_range(puzzle_id)?,
+            });
+        }
+        Ok(ScanTarget::Ranges(
+            ranges
+                .into_iter()
+                .map(|r| {
+                    let folder = format!("{}/{}", config.chunk_folder, range_folder_name(&r));
+                    (r, folder)
+                })
+                .collect(),
+        ))
+    }
+
+    /// Folders holding this target's chunks
+    fn folders(&self) -> Vec<&str> {
+        match self {
+            ScanTarget::Random { base_folder, .. } => vec![base_folder.as_str()],
+            ScanTarget::Ranges(ranges) => ranges.iter().map(|(_, f)| f.as_str()).collect(),
+            ScanTarget::Template { folder, .. } => vec![folder.as_str()],
+            ScanTarget::Windows { folder, .. } => vec![folder.as_str()],
+        }
+    }
+
+    /// Acquire the next chunk and the folder it lives in
+    fn next_chunk(
+        &self,
+        chunk_size: &BigUint,
+        cli_chunk_id: Option<BigUint>,
+        filter: &ChunkFilter,
+    ) -> Result<Option<(ChunkMetadata, BigUint, String)>> {
+        match self {
+            ScanTarget::Random {
+                base_folder,
+                puzzle_range,
+            } => {
+                let (meta, id) = acquire_chunk(
+                    base_folder,
+                    chunk_size,
+                    cli_chunk_id,
+                    puzzle_range.as_ref(),
+                    filter,
+                )?;
+                Ok(Some((meta, id, base_folder.clone())))
+            }
+            ScanTarget::Ranges(ranges) => {
+                for (range, folder) in ranges {
+                    if let Some((meta, id)) =
+                        acquire_range_chunk(folder, chunk_size, range, filter)?
+                    {
+                        return Ok(Some((meta, id, folder.clone())));
+                    }
+                }
+                Ok(None)
+            }
+            ScanTarget::Template { template, folder } => Ok(acquire_template_chunk(
+                folder, chunk_size, template, filter,
+            )?
+            .map(|(meta, id)| (meta, id, folder.clone()))),
+            ScanTarget::Windows {
+                folder,
+                puzzle_range,
+                window,
+                ..
+            } => {
+                let (meta, id) =
+                    acquire_chunk(folder, window, cli_chunk_id, Some(puzzle_range), filter)?;
+                Ok(Some((meta, id, folder.clone())))
+            }
+        }
+    }
+}
+
+pub fn run(config: &Config, args: &ScanArgs) -> Result<()> {
+    let chunk_size = config.chunk_size();
+    let target = ScanTarget::from_args(config, args)?;
+    let full_range = matches!(
+        target,
+        ScanTarget::Random {
+            puzzle_range: None,
+            ..
+        }
+    );
+    if config.symmetry != Symmetry::Off && !full_range {
+        return Err(Error::Config {
+            path: "symmetry".to_string(),
+            message: "symmetric keys fall outside puzzles and custom ranges, so symmetry only applies to full-range scans".to_string(),
+        });
+    }
+    let limits = args.limits();
+    // One permutation key for the session, so chunks it starts compare equal
+    let walk = args.walk.clone().resolved();
+    let mut skipped = if args.exclude_imported {
+        Some(load_imported(&config.chunk_folder, config.script_types)?)
+    } else {
+        None
+    };
+    let mut sampling = None;
+    // Windows already scanned as part of whole chunks or of windows of
+    // another size are not sampled again
+    if let ScanTarget::Windows {
+        folder,
+        chunk_folder,
+        window,
+        ..
+    } = &target
+    {
+        let mut chunks = IntervalSet::default();
+        if Path::new(chunk_folder).exists() {
+            chunks.extend(&folder_coverage(chunk_folder, config.script_types)?);
+            for other in subfolders(chunk_folder, &["window_"])? {
+                if other != *folder {
+                    chunks.extend(&folder_coverage(&other, config.script_types)?);
+                }
+            }
+        }
+        sampling = Some((window.clone(), chunks.key_count()));
+        skipped.get_or_insert_default().extend(&chunks);
+    }
+    let mut filter = ChunkFilter {
+        shard: args.shard()?,
+        excluded: skipped.clone(),
+    };
+
+    let active: ActiveChunk = Arc::new(Mutex::new(None));
+    set_interrupt_handler(Arc::clone(&active));
+
+    println!("\n🚀 Starting BTC Key Matcher");
+    let targets = load_targets(config)?
+        .with_script_types(config.script_types)
+        .with_symmetry(config.symmetry);
+    if targets.unmatchable() > 0 {
+        println!(
+            "⚠️  Skipping {} entries that cannot be derived from a key on {}",
+            targets.unmatchable(),
+            config.network
+        );
+    }
+    if config.network != Network::Mainnet {
+        println!("🌐 Network: {}", config.network);
+    }
+    if config.symmetry != Symmetry::Off {
+        println!(
+            "🪞 Symmetry: {} ({} keys per point)",
+            config.symmetry,
+            config.symmetry.keys_per_point()
+        );
+    }
+    println!("🧬 Script types: {}\n", config.script_types);
+    if let Some(shard) = &filter.shard {
+        println!("🧩 Worker {}\n", shard);
+    }
+    if let Some((window, scanned)) = &sampling {
+        println!(
+            "🪟 Sampling windows of {} keys outside {} keys already scanned\n",
+            window, scanned
+        );
+    }
+    let peers = start_peers(config, &args.peers, &target)?;
+    if let Some(excluded) = &filter.excluded {
+        println!(
+            "🚫 Excluding {} interval(s), {} keys\n",
+            excluded.intervals().len(),
+            excluded.key_count()
+        );
+    }
+
+    let mut stats = ScanStats::default();
+    let mut cli_chunk_id = args.chunk_id.clone();
+
+    loop {
+        if let Some(reason) = limits.reached(&stats) {
+            if args.continuous {
+                println!("⏹️  Limit reached ({:?})", reason);
+            }
+            break;
+        }
+
+        if let Some(peers) = &peers {
+            let mut excluded = skipped.clone().unwrap_or_default();
+            excluded.extend(&peers.lock().excluded());
+            filter.excluded = Some(excluded);
+        }
+        let Some((mut meta, chunk_id, base_folder)) =
+            target.next_chunk(&chunk_size, cli_chunk_id.take(), &filter)?
+        else {
+            match target {
+                ScanTarget::Template { .. } => println!("🏁 All template keys are covered"),
+                _ => println!("🏁 All custom ranges are covered"),
+            }
+            break;
+        };
+        let path = ChunkMetadata::path(&chunk_id, &base_folder);
+        if meta.walk() != walk {
+            if meta.keys_done()?.is_zero() {
+                meta.set_walk(walk.clone())?;
+                meta.save(&path)?;
+            } else {
+                println!(
+                    "↪️  Chunk {} was started on a {} walk, continuing it",
+                    chunk_id,
+                    meta.walk()
+                );
+            }
+        }
+        let (first_key, last_key) = meta.key_bounds()?;
+        if let Some(peers) = &peers
+            && !claim_with_peers(&peers.node, &peers.addrs, &first_key, &last_key)
+        {
+            meta.status = ChunkStatus::Pending;
+            meta.save(&path)?;
+            println!("🤝 Chunk {} is taken by a peer, picking another", chunk_id);
+            continue;
+        }
+        *active.lock().unwrap_or_else(|e| e.into_inner()) = Some((meta.clone(), path.clone()));
+
+        print_chunk(&meta);
+
+        let start_chunk_time = Instant::now();
+        let outcome = scan_chunk(
+            &mut meta,
+            &path,
+            &targets,
+            config.batch_size,
+            &limits,
+            &mut stats,
+            |progress| {
+                print_batch(progress);
+                for report in progress.matches {
+                    if let Err(e) = record_match(report) {
+                        eprintln!("⚠️  Failed to record match: {}", e);
+                    }
+                }
+                *active.lock().unwrap_or_else(|e| e.into_inner()) =
+                    Some((progress.meta.clone(), path.clone()));
+            },
+        )?;
+        *active.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        if let Some(peers) = &peers {
+            let mut node = peers.lock();
+            // Chunks walked with gaps leave most of their range unscanned
+            match outcome {
+                ChunkOutcome::Finished if meta.walk().visits_every_key() => node.record_scanned(
+                    &first_key,
+                    &last_key,
+                    meta.checked_types(),
+                    meta.checked_symmetry(),
+                ),
+                _ => node.release(&first_key),
+            }
+        }
+
+        match outcome {
+            ChunkOutcome::Finished => println!(
+                "🏁 Finished chunk {} in {:.2?}\n",
+                chunk_id,
+                start_chunk_time.elapsed()
+            ),
+            ChunkOutcome::Matched(reports) => {
+                for report in &reports {
+                    record_match(report)?;
+                }
+                return Ok(());
+            }
+            ChunkOutcome::Stopped(reason) => {
+                println!(
+                    "⏹️  Stopped ({:?}); chunk {} reset to pending",
+                    reason, chunk_id
+                );
+                break;
+            }
+        }
+    }
+
+    print_summary(&stats);
+    Ok(())
+}
+
+/// This scanner's node in a peer group
+struct Peers {
+    node: Arc<Mutex<PeerNode>>,
+    addrs: Vec<String>,
+}
+
+impl Peers {
+    fn lock(&self) -> MutexGuard<'_, PeerNode> {
+        self.node.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Start answering peers and syncing with them in the background
+fn start_peers(config: &Config, args: &PeerArgs, target: &ScanTarget) -> Result<Option<Peers>> {
+    let Some(listen) = &args.peer_listen else {
+        return Ok(None);
+    };
+    let listener = TcpListener::bind(listen).map_err(|e| Error::io(listen, e))?;
+    let local = listener
+        .local_addr()
+        .map_err(|e| Error::io(listen, e))?
+        .to_string();
+    let name = args.node_name.clone().unwrap_or_else(|| local.clone());
+
+    let mut scanned = IntervalSet::default();
+    for folder in target.folders() {
+        if Path::new(folder).exists() {
+            scanned.extend(&folder_coverage(folder, config.script_types)?);
+        }
+    }
+    let node = Arc::new(Mutex::new(PeerNode::open(
+        &name,
+        &config.chunk_folder,
+        &scanned,
+        config.script_types,
+    )?));
+    println!(
+        "🤝 Peer node {} listening on {} with {} peer(s)\n",
+        name,
+        local,
+        args.peers.len()
+    );
+    {
+        let node = Arc::clone(&node);
+        thread::spawn(move || serve_peers(listener, node));
+    }
+
+    let peers = args.peers.clone();
+    let mut down = report_unreachable(&sync_with_peers(&node, &peers), &HashSet::new());
+    {
+        let node = Arc::clone(&node);
+        let peers = peers.clone();
+        let interval = Duration::from_secs(args.sync_secs.max(1));
+        thread::spawn(move || {
+            loop {
+                thread::sleep(interval);
+                down = report_unreachable(&sync_with_peers(&node, &peers), &down);
+            }
+        });
+    }
+    Ok(Some(Peers { node, addrs: peers }))
+}
+
+/// Print peers that went down or came back since the last sync
+fn report_unreachable(failed: &[(String, Error)], before: &HashSet<String>) -> HashSet<String> {
+    let now: HashSet<String> = failed.iter().map(|(addr, _)| addr.clone()).collect();
+    for (addr, e) in failed {
+        if !before.contains(addr) {
+            println!("⚠️  Peer {} unreachable: {}", addr, e);
+        }
+    }
+    for addr in before.difference(&now) {
+        println!("🤝 Peer {} is back", addr);
+    }
+    now
+}
+
+/// Load the configured address files as labelled target groups
+pub fn load_targets(config: &Config) -> Result<TargetSet> {
+    let files = config.target_files();
+    for file in &files {
+        println!("📁 Loading address database from: {}", file.file);
+    }
+    let targets = TargetSet::load_files(&files, config.network)?;
+    println!("✅ Loaded {} addresses", targets.len());
+    if files.len() > 1 {
+        for group in targets.groups() {
+            println!(
+                "   {:<12} {} addresses, priority {}, {} on match",
+                group.label(),
+                group.len(),
+                group.priority(),
+                group.action()
+            );
+        }
+    }
+    for (script, count) in targets.unreachable(config.script_types) {
+        println!(
+            "⚠️  {} targets need script type {}, which is not selected, and can never match",
+            count, script
+        );
+    }
+    Ok(targets)
+}
+
+/// Announce a match and append it to `match_found.csv`
+pub fn record_match(report: &MatchReport) -> Result<()> {
+    let sets = report.sets.join(", ");
+    match report.action {
+        MatchAction::Stop | MatchAction::Notify => println!(
+            "🎯 MATCH FOUND in {}: {} -> {}",
+            sets, report.hex_key, report.address
+        ),
+        MatchAction::Continue => println!(
+            "📝 Match in {} recorded: {} -> {}",
+            sets, report.hex_key, report.address
+        ),
+    }
+    report.append_csv(MATCH_FILE)
+}
+
+pub fn print_batch(progress: &BatchProgress) {
+    println!(
+        "✅ Batch #{:03} ({} keys from {}) completed in {:.2?}. Last key: {}",
+        progress.stats.batches,
+        progress.batch_keys,
+        progress.batch_start_hex,
+        progress.elapsed,
+        progress.meta.last_processed_hex
+    );
+}
+
+pub fn print_chunk(meta: &ChunkMetadata) {
+    println!("➡️  Chunk ID: {}", meta.chunk_id);
+    println!("   Start Key: {}", meta.start_hex);
+    println!("   End Key:   {}", meta.end_hex);
+    if let Some(walk) = &meta.walk {
+        println!("   Walk:      {}", walk);
+    }
+    println!("   Last Key:  {}\n", meta.last_processed_hex);
+}
+
+pub fn print_summary(stats: &ScanStats) {
+    println!(
+        "📈 {} chunk(s), {} keys in {:.2?} ({:.0} keys/s)",
+        stats.chunks,
+        stats.keys,
+        stats.started.elapsed(),
+        stats.keys_per_second()
+    );
+}
+
+pub fn set_interrupt_handler(active: ActiveChunk) {
+    ctrlc::set_handler(move || {
+        let active = active.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((meta, path)) = active.as_ref() {
+            let mut meta = meta.clone();
+            meta.status = ChunkStatus::Pending;
+            match meta.save(path) {
+                Ok(()) => println!("\n🛑 Interrupted. Chunk status reset to pending."),
+                Err(e) => eprintln!("\n🛑 Interrupted, but failed to reset chunk: {}", e),
+            }
+        }
+        std::process::exit(0);
+    })
+    .unwrap_or_else(|e| eprintln!("⚠️  Could not set Ctrl+C handler: {}", e));
+}
@@ -0,0 +1,88 @@
+use crate::cli::{ChunksArgs, PuzzleArgs};
+use crate::commands::{percent, puzzle_range};
+use btc_key_matcher::chunk::{ChunkMetadata, ChunkStatus};
+use btc_key_matcher::config::Config;
+use btc_key_matcher::error::Result;
+use btc_key_matcher::validate::chunk_files;
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use std::path::Path;
+
+pub fn run_status(config: &Config, args: &PuzzleArgs) -> Result<()> {
+    let puzzle_range = puzzle_range(args.puzzle_id)?;
+    let base_folder = config.chunk_folder_for(args.puzzle_id);
+    if !Path::new(&base_folder).exists() {
+        println!("📭 No chunks in {}", base_folder);
+        return Ok(());
+    }
+
+    let (mut pending, mut processing, mut finished, mut verified, mut unreadable) = (0, 0, 0, 0, 0);
+    let mut keys_done = BigUint::zero();
+    let mut keys_total = BigUint::zero();
+    for path in chunk_files(&base_folder)? {
+        let Ok(meta) = ChunkMetadata::load(&path.to_string_lossy()) else {
+            unreadable += 1;
+            continue;
+        };
+        match meta.status {
+            ChunkStatus::Pending => pending += 1,
+            ChunkStatus::Processing => processing += 1,
+            ChunkStatus::Finished => finished += 1,
+            ChunkStatus::Verified => verified += 1,
+        }
+        keys_done += meta.keys_done()?;
+        keys_total += meta.keys_total()?;
+    }
+
+    println!("📊 Chunk status for {}", base_folder);
+    println!("   Pending:    {}", pending);
+    println!("   Processing: {}", processing);
+    println!("   Finished:   {}", finished);
+    println!("   Verified:   {}", verified);
+    if unreadable > 0 {
+        println!("   Unreadable: {} (run `fsck`)", unreadable);
+    }
+    println!(
+        "   Keys scanned: {} of {} in chunks ({:.4}%)",
+        keys_done,
+        keys_total,
+        percent(&keys_done, &keys_total)
+    );
+    if let Some(range) = puzzle_range {
+        let range_size = &range.end - &range.start + BigUint::one();
+        println!(
+            "   Puzzle coverage: {:.10}%",
+            percent(&keys_done, &range_size)
+        );
+    }
+    Ok(())
+}
+
+pub fn run_chunks(config: &Config, args: &ChunksArgs) -> Result<()> {
+    let base_folder = config.chunk_folder_for(args.puzzle.puzzle_id);
+    if !Path::new(&base_folder).exists() {
+        println!("📭 No chunks in {}", base_folder);
+        return Ok(());
+    }
+
+    for path in chunk_files(&base_folder)? {
+        let meta = match ChunkMetadata::load(&path.to_string_lossy()) {
+            Ok(meta) => meta,
+            Err(e) => {
+                println!("❌ {}", e);
+                continue;
+            }
+        };
+        if args.status.as_ref().is_some_and(|s| *s != meta.status) {
+            continue;
+        }
+        println!(
+            "{:>20}  {:<10}  {:>8.4}%  {}",
+            meta.chunk_id,
+            meta.status,
+            percent(&meta.keys_done()?, &meta.keys_total()?),
+            meta.last_processed_hex
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use crate::cli::VerifyArgs;
+use crate::commands::chunk_folders;
+use btc_key_matcher::address::{derive, private_key_to_wif, public_key};
+use btc_key_matcher::audit::{AuditOptions, audit_folder};
+use btc_key_matcher::codec::Network;
+use btc_key_matcher::config::Config;
+use btc_key_matcher::error::{Error, Result};
+use btc_key_matcher::reference::reference_addresses;
+use btc_key_matcher::script::ScriptTypes;
+use btc_key_matcher::search::{TargetSet, first_unsorted, load_sorted_addresses};
+use std::path::Path;
+
+/// Addresses and WIFs for private key 1, for every script type in order
+const KEY_ONE_ADDRESSES: [&str; 7] = [
+    "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+    "1EHNa6Q4Jz2uvNExL497mE43ikXhwF6kZm",
+    "3JvL6Ymt8MVWiCNHC7oWU6nLeHNJKLZGLN",
+    "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+    "bc1pmfr3p9j00pfxjh0zmgp99y8zftmd3s5pmedqhyptwy6lm87hf5sspknck9",
+    "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+    "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8",
+];
+const KEY_ONE_WIF_COMPRESSED: &str = "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn";
+const KEY_ONE_WIF_UNCOMPRESSED: &str = "5HpHagT65TZzG1PH3CSu63k8DbpvD8s5ip4nEB3kEsreAnchuDf";
+
+pub fn run(config: &Config, args: &VerifyArgs) -> Result<()> {
+    let mut failures = 0;
+
+    let mut key_one = [0u8; 32];
+    key_one[31] = 1;
+    let derived = derive(&public_key(&key_one)?, Network::Mainnet, ScriptTypes::ALL)?;
+    for (expected, actual) in KEY_ONE_ADDRESSES.iter().zip(&derived) {
+        failures += report(actual.script.label(), expected, actual.as_str());
+    }
+    let reference = reference_addresses(&key_one)?;
+    for ((expected, actual), fast) in KEY_ONE_ADDRESSES.iter().zip(&reference).zip(&derived) {
+        failures += report(&format!("{} (ref)", fast.script.label()), expected, actual);
+    }
+    failures += report(
+        "WIF compressed",
+        KEY_ONE_WIF_COMPRESSED,
+        &private_key_to_wif(&key_one, true, Network::Mainnet),
+    );
+    failures += report(
+        "WIF uncompressed",
+        KEY_ONE_WIF_UNCOMPRESSED,
+        &private_key_to_wif(&key_one, false, Network::Mainnet),
+    );
+
+    let mut addresses = Vec::new();
+    if Path::new(&config.addr_file).exists() {
+        addresses = load_sorted_addresses(&config.addr_file)?;
+        match first_unsorted(&addresses) {
+            None => println!(
+                "✅ Address database {} is sorted ({} entries)",
+                config.addr_file,
+                addresses.len()
+            ),
+            Some(line) => {
+                failures += 1;
+                println!(
+                    "❌ Address database {} is not sorted or has duplicates at line {}",
+                    config.addr_file,
+                    line + 1
+                );
+            }
+        }
+    } else {
+        println!(
+            "⚠️  Address database {} not found, skipped",
+            config.addr_file
+        );
+    }
+
+    let targets = TargetSet::for_network(config.network, &addresses);
+    for folder in chunk_folders(config, args.puzzle.puzzle_id)? {
+        failures += verify_chunks(&folder, &targets, args)?;
+    }
+
+    if failures > 0 {
+        return Err(Error::Verification(format!("{} check(s) failed", failures)));
+    }
+    println!("✅ All checks passed");
+    Ok(())
+}
+
+fn report(label: &str, expected: &str, actual: &str) -> usize {
+    if expected == actual {
+        println!("✅ {:<26} {}", label, actual);
+        0
+    } else {
+        println!("❌ {:<26} {} (expected {})", label, actual, expected);
+        1
+    }
+}
+
+/// Re-verify finished chunks, returning the number of chunks flagged
+fn verify_chunks(base_folder: &str, targets: &TargetSet, args: &VerifyArgs) -> Result<usize> {
+    println!("🔍 Re-verifying finished chunks in {}", base_folder);
+    let options = AuditOptions {
+        chunks: args.chunks,
+        keys: args.keys,
+        recheck: args.recheck,
+        dry_run: args.dry_run,
+    };
+    let report = audit_folder(base_folder, targets, &options)?;
+    for chunk_id in &report.verified {
+        println!("✅ Chunk {} verified", chunk_id);
+    }
+    for (chunk_id, issues) in &report.flagged {
+        if args.dry_run {
+            println!("❌ Chunk {}", chunk_id);
+        } else {
+            println!("❌ Chunk {} reset to pending for a rescan", chunk_id);
+        }
+        for issue in issues {
+            println!("   - {}", issue);
+        }
+    }
+    println!(
+        "📊 Checked {} chunk(s): {} verified, {} flagged",
+        report.checked,
+        report.verified.len(),
+        report.flagged.len()
+    );
+    Ok(report.flagged.len())
+}
@@ -0,0 +1,163 @@
+use crate::cli::WorkerArgs;
+use crate::commands::scan::{
+    ActiveChunk, load_targets, print_batch, print_chunk, print_summary, record_match,
+    set_interrupt_handler,
+};
+use btc_key_matcher::codec::Network;
+use btc_key_matcher::config::Config;
+use btc_key_matcher::error::Result;
+use btc_key_matcher::protocol::Response;
+use btc_key_matcher::scanner::{ChunkOutcome, ScanStats, scan_chunk};
+use btc_key_matcher::worker::{LeaseOutcome, OUTBOX_FILE, WorkerCache, WorkerClient};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub fn run(config: &Config, args: &WorkerArgs) -> Result<()> {
+    let addr = args.coordinator_addr();
+    let name = args.worker_name();
+    let cache_folder = args
+        .cache_folder
+        .clone()
+        .unwrap_or_else(|| format!("{}/worker_cache", config.chunk_folder));
+    let client = WorkerClient::new(addr, &name, WorkerCache::open(&cache_folder)?);
+    let limits = args.limits.to_limits();
+    let heartbeat_every = Duration::from_secs(args.heartbeat_secs);
+    let retry_every = Duration::from_secs(args.retry_secs);
+
+    let active: ActiveChunk = Arc::new(Mutex::new(None));
+    set_interrupt_handler(Arc::clone(&active));
+
+    println!("\n🚀 Starting worker {} for coordinator {}", name, addr);
+    let targets = load_targets(config)?.with_script_types(config.script_types);
+    if targets.unmatchable() > 0 {
+        println!(
+            "⚠️  Skipping {} entries that cannot be derived from a key on {}",
+            targets.unmatchable(),
+            config.network
+        );
+    }
+    if config.network != Network::Mainnet {
+        println!("🌐 Network: {}", config.network);
+    }
+    println!("🧬 Script types: {}\n", config.script_types);
+
+    let mut stats = ScanStats::default();
+
+    loop {
+        if let Some(reason) = limits.reached(&stats) {
+            println!("⏹️  Limit reached ({:?})", reason);
+            break;
+        }
+
+        let (lease_id, mut meta, path) = match client.lease()? {
+            LeaseOutcome::Leased {
+                lease_id,
+                chunk,
+                path,
+            } => (lease_id, chunk, path),
+            LeaseOutcome::NoWork => {
+                println!("🏁 The coordinator has no work left");
+                break;
+            }
+            LeaseOutcome::Offline => {
+                println!(
+                    "📡 Coordinator unreachable, retrying in {}s",
+                    args.retry_secs
+                );
+                thread::sleep(retry_every);
+                continue;
+            }
+        };
+        *active.lock().unwrap_or_else(|e| e.into_inner()) = Some((meta.clone(), path.clone()));
+
+        println!("📡 Lease #{}", lease_id);
+        print_chunk(&meta);
+
+        let start_chunk_time = Instant::now();
+        let mut last_heartbeat = Instant::now();
+        let outcome = scan_chunk(
+            &mut meta,
+            &path,
+            &targets,
+            config.batch_size,
+            &limits,
+            &mut stats,
+            |progress| {
+                print_batch(progress);
+                for report in progress.matches {
+                    let reported =
+                        record_match(report).and_then(|_| client.report_match(lease_id, report));
+                    if let Err(e) = reported {
+                        eprintln!("⚠️  Failed to record match: {}", e);
+                    }
+                }
+                *active.lock().unwrap_or_else(|e| e.into_inner()) =
+                    Some((progress.meta.clone(), path.clone()));
+                if last_heartbeat.elapsed() < heartbeat_every {
+                    return;
+                }
+                last_heartbeat = Instant::now();
+                match client.heartbeat(lease_id, progress.meta) {
+                    Ok(Some(Response::LeaseLost)) => println!(
+                        "⚠️  Lease #{} has expired; chunk {} may be scanned twice",
+                        lease_id, progress.meta.chunk_id
+                    ),
+                    Ok(Some(Response::Error { message })) => println!(
+                        "⚠️  Coordinator rejected the checkpoint for chunk {}: {}",
+                        progress.meta.chunk_id, message
+                    ),
+                    Ok(Some(_)) => {}
+                    Ok(None) => println!("📡 Coordinator unreachable, checkpoint queued"),
+                    Err(e) => eprintln!("⚠️  Failed to queue checkpoint: {}", e),
+                }
+            },
+        )?;
+        *active.lock().unwrap_or_else(|e| e.into_inner()) = None;
+
+        match outcome {
+            ChunkOutcome::Finished => {
+                println!(
+                    "🏁 Finished chunk {} in {:.2?}\n",
+                    meta.chunk_id,
+                    start_chunk_time.elapsed()
+                );
+                if let Some(Response::Error { message }) = client.finish(lease_id, &meta)? {
+                    println!(
+                        "⚠️  Coordinator did not accept chunk {}: {}",
+                        meta.chunk_id, message
+                    );
+                }
+            }
+            ChunkOutcome::Matched(reports) => {
+                for report in &reports {
+                    record_match(report)?;
+                    client.report_match(lease_id, report)?;
+                }
+                client.release(lease_id, &meta)?;
+                break;
+            }
+            ChunkOutcome::Stopped(reason) => {
+                client.release(lease_id, &meta)?;
+                println!(
+                    "⏹️  Stopped ({:?}); chunk {} handed back to the coordinator",
+                    reason, meta.chunk_id
+                );
+                break;
+            }
+        }
+    }
+
+    client.cache().flush(addr)?;
+    let queued = client.cache().outbox()?.len();
+    if queued > 0 {
+        println!(
+            "⚠️  {} report(s) still queued in {}/{}; they are sent on the next run",
+            queued,
+            client.cache().folder(),
+            OUTBOX_FILE
+        );
+    }
+    print_summary(&stats);
+    Ok(())
+}
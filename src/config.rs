@@ -0,0 +1,195 @@
+use crate::codec::Network;
+use crate::error::{Error, Result};
+use crate::script::ScriptTypes;
+use crate::search::MatchAction;
+use crate::symmetry::Symmetry;
+use num_bigint::BigUint;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Config file picked up from the working directory when `--config` is not given
+pub const DEFAULT_CONFIG_FILE: &str = "btc_key_matcher.toml";
+
+/// Runtime settings, read from a TOML file and overridden on the command line
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub addr_file: String,
+    pub chunk_folder: String,
+    pub chunk_size: u64,
+    pub batch_size: usize,
+    /// Worker threads for rayon, `None` uses one per CPU
+    pub threads: Option<usize>,
+    /// Script types derived from every key
+    pub script_types: ScriptTypes,
+    /// Network addresses are derived and targets decoded for
+    pub network: Network,
+    /// Symmetric keys checked for every scanned key in full-range scans
+    pub symmetry: Symmetry,
+    /// Labelled address files matched together; `addr_file` is used when
+    /// there are none
+    pub targets: Vec<TargetFile>,
+}
+
+/// An address file matched as one labelled group of targets
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TargetFile {
+    /// Name reported with matches, the file name without its extension
+    /// when not given
+    #[serde(default)]
+    pub label: Option<String>,
+    pub file: String,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub action: MatchAction,
+}
+
+impl TargetFile {
+    pub fn label(&self) -> String {
+        self.label.clone().unwrap_or_else(|| {
+            Path::new(&self.file)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| self.file.clone())
+        })
+    }
+}
+
+impl FromStr for TargetFile {
+    type Err = String;
+
+    /// Parse `[LABEL=]FILE[,priority=N][,action=ACTION]`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parts = s.split(',').map(str::trim);
+        let first = parts.next().unwrap_or_default();
+        let (label, file) = match first.split_once('=') {
+            Some((label, file)) => (Some(label.trim().to_string()), file.trim()),
+            None => (None, first),
+        };
+        if file.is_empty() {
+            return Err(format!("'{}' names no address file", s));
+        }
+        let mut target = TargetFile {
+            label,
+            file: file.to_string(),
+            priority: 0,
+            action: MatchAction::Stop,
+        };
+        for option in parts {
+            match option.split_once('=') {
+                Some(("priority", value)) => {
+                    target.priority = value
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("'{}' is not a priority", value))?;
+                }
+                Some(("action", value)) => target.action = value.parse()?,
+                _ => {
+                    return Err(format!(
+                        "unknown target option '{}', expected priority=N or action=ACTION",
+                        option
+                    ));
+                }
+            }
+        }
+        Ok(target)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            addr_file: "resources/addresses/Bitcoin_addresses_sorted.txt".to_string(),
+            chunk_folder: "resources/chunks".to_string(),
+            chunk_size: 100_000_000_000,
+            batch_size: 5_000_000,
+            threads: None,
+            script_types: ScriptTypes::DEFAULT,
+            network: Network::Mainnet,
+            symmetry: Symmetry::Off,
+            targets: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Parse a TOML config file
+    pub fn load(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        Self::from_toml(&content, path)
+    }
+
+    /// Parse TOML config text
+    pub fn parse(content: &str) -> Result<Self> {
+        Self::from_toml(content, "<inline>")
+    }
+
+    /// Load `path` if given, else the default config file if present, else defaults
+    pub fn load_or_default(path: Option<&str>) -> Result<Self> {
+        match path {
+            Some(p) => Self::load(p),
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Self::load(DEFAULT_CONFIG_FILE),
+            None => Ok(Self::default()),
+        }
+    }
+
+    fn from_toml(content: &str, path: &str) -> Result<Self> {
+        let config: Config = toml::from_str(content).map_err(|e| Error::Config {
+            path: path.to_string(),
+            message: e.message().to_string(),
+        })?;
+        config.check().map_err(|message| Error::Config {
+            path: path.to_string(),
+            message: message.to_string(),
+        })?;
+        Ok(config)
+    }
+
+    /// Reject values that would make a scan impossible
+    pub fn check(&self) -> std::result::Result<(), &'static str> {
+        if self.chunk_size == 0 {
+            return Err("chunk_size must be greater than zero");
+        }
+        if self.batch_size == 0 {
+            return Err("batch_size must be greater than zero");
+        }
+        if self.threads == Some(0) {
+            return Err("threads must be greater than zero");
+        }
+        let mut labels = HashSet::new();
+        if !self.targets.iter().all(|t| labels.insert(t.label())) {
+            return Err("target labels must be unique");
+        }
+        Ok(())
+    }
+
+    /// The address files to match, `addr_file` alone when no targets are set
+    pub fn target_files(&self) -> Vec<TargetFile> {
+        if !self.targets.is_empty() {
+            return self.targets.clone();
+        }
+        vec![TargetFile {
+            label: None,
+            file: self.addr_file.clone(),
+            priority: 0,
+            action: MatchAction::Stop,
+        }]
+    }
+
+    pub fn chunk_size(&self) -> BigUint {
+        BigUint::from(self.chunk_size)
+    }
+
+    /// Chunk folder for a puzzle, or the root chunk folder for full-range scans
+    pub fn chunk_folder_for(&self, puzzle_id: Option<u32>) -> String {
+        match puzzle_id {
+            Some(pid) => format!("{}/puzzle_{:03}", self.chunk_folder, pid),
+            None => self.chunk_folder.clone(),
+        }
+    }
+}
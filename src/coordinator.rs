@@ -0,0 +1,353 @@
+use crate::chunk::{ChunkMetadata, ChunkStatus};
+use crate::chunk_manager::{ChunkFilter, acquire_chunk};
+use crate::error::{Error, Result};
+use crate::keygen::decode_key;
+use crate::proof::{ChunkProof, verify_proof};
+use crate::protocol::{Request, Response, read_message, write_message};
+use crate::puzzles::PuzzleRange;
+use crate::scanner::MatchReport;
+use crate::script::ScriptTypes;
+use crate::validate::chunk_files;
+use num_bigint::BigUint;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// File in the chunk folder where reported matches are appended
+pub const MATCHES_FILE: &str = "matches.jsonl";
+
+/// Random windows rescanned to check the proof of a finished chunk
+pub const SPOT_CHECK_WINDOWS: usize = 4;
+
+/// Keys per spot-check window. Windows are widened to at least the mean gap
+/// between distinguished keys, so one that falls in a skipped stretch of a
+/// chunk finds an unlisted key about two times in three.
+pub const SPOT_CHECK_KEYS: u64 = 256;
+
+/// A chunk handed out to a worker
+#[derive(Debug, Clone)]
+pub struct Lease {
+    pub worker: String,
+    pub chunk_id: BigUint,
+    pub expires: Instant,
+}
+
+/// Owns a chunk store and hands its chunks out to workers as leases.
+///
+/// Every state change is written to the chunk files straight away, so the
+/// folder stays a valid local chunk store that `scan` and `fsck` can use.
+pub struct Coordinator {
+    base_folder: String,
+    chunk_size: BigUint,
+    puzzle_range: Option<PuzzleRange>,
+    lease_duration: Duration,
+    leases: HashMap<u64, Lease>,
+    next_lease_id: u64,
+    matches: usize,
+}
+
+impl Coordinator {
+    /// Open a chunk store, resetting chunks left `processing` by a previous
+    /// run to `pending` since their leases did not survive the restart
+    pub fn open(
+        base_folder: &str,
+        chunk_size: BigUint,
+        puzzle_range: Option<PuzzleRange>,
+        lease_duration: Duration,
+    ) -> Result<Self> {
+        std::fs::create_dir_all(base_folder).map_err(|e| Error::io(base_folder, e))?;
+        for path in chunk_files(base_folder)? {
+            let path = path.to_string_lossy();
+            let Ok(mut meta) = ChunkMetadata::load(&path) else {
+                continue;
+            };
+            if meta.status == ChunkStatus::Processing {
+                meta.status = ChunkStatus::Pending;
+                meta.save(&path)?;
+            }
+        }
+        Ok(Self {
+            base_folder: base_folder.to_string(),
+            chunk_size,
+            puzzle_range,
+            lease_duration,
+            leases: HashMap::new(),
+            next_lease_id: 1,
+            matches: 0,
+        })
+    }
+
+    pub fn active_leases(&self) -> &HashMap<u64, Lease> {
+        &self.leases
+    }
+
+    /// Handle one request, turning library errors into error responses
+    pub fn handle(&mut self, request: Request) -> Response {
+        self.handle_inner(request)
+            .unwrap_or_else(|e| Response::Error {
+                message: e.to_string(),
+            })
+    }
+
+    fn handle_inner(&mut self, request: Request) -> Result<Response> {
+        self.expire_leases()?;
+        match request {
+            Request::Lease { worker } => self.lease(worker),
+            Request::Heartbeat {
+                lease_id,
+                last_processed_hex,
+                proof,
+                script_types,
+            } => self.checkpoint(lease_id, &last_processed_hex, proof, script_types, false),
+            Request::Release {
+                lease_id,
+                last_processed_hex,
+                proof,
+                script_types,
+            } => self.checkpoint(lease_id, &last_processed_hex, proof, script_types, true),
+            Request::Finish {
+                lease_id,
+                proof,
+                script_types,
+            } => self.finish(lease_id, proof, script_types),
+            Request::Match { lease_id, report } => self.record_match(lease_id, &report),
+            Request::Status => self.status(),
+        }
+    }
+
+    fn chunk_path(&self, chunk_id: &BigUint) -> String {
+        ChunkMetadata::path(chunk_id, &self.base_folder)
+    }
+
+    /// Return chunks whose lease ran out to `pending`, keeping their checkpoint
+    pub fn expire_leases(&mut self) -> Result<()> {
+        let now = Instant::now();
+        let expired: Vec<u64> = self
+            .leases
+            .iter()
+            .filter(|(_, lease)| lease.expires <= now)
+            .map(|(id, _)| *id)
+            .collect();
+        for lease_id in expired {
+            if let Some(lease) = self.leases.remove(&lease_id) {
+                self.set_status(&lease.chunk_id, ChunkStatus::Pending)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_status(&self, chunk_id: &BigUint, status: ChunkStatus) -> Result<()> {
+        let path = self.chunk_path(chunk_id);
+        let mut meta = ChunkMetadata::load(&path)?;
+        meta.status = status;
+        meta.save(&path)
+    }
+
+    fn lease(&mut self, worker: String) -> Result<Response> {
+        let (chunk, chunk_id) = match acquire_chunk(
+            &self.base_folder,
+            &self.chunk_size,
+            None,
+            self.puzzle_range.as_ref(),
+            &ChunkFilter::default(),
+        ) {
+            Ok(acquired) => acquired,
+            Err(Error::EmptyRange) => return Ok(Response::NoWork),
+            Err(e) => return Err(e),
+        };
+        let lease_id = self.next_lease_id;
+        self.next_lease_id += 1;
+        self.leases.insert(
+            lease_id,
+            Lease {
+                worker,
+                chunk_id,
+                expires: Instant::now() + self.lease_duration,
+            },
+        );
+        Ok(Response::Leased {
+            lease_id,
+            chunk,
+            lease_secs: self.lease_duration.as_secs(),
+        })
+    }
+
+    fn checkpoint(
+        &mut self,
+        lease_id: u64,
+        last_processed_hex: &str,
+        proof: Option<ChunkProof>,
+        script_types: Option<ScriptTypes>,
+        release: bool,
+    ) -> Result<Response> {
+        let Some(lease) = self.leases.get_mut(&lease_id) else {
+            return Ok(Response::LeaseLost);
+        };
+        let path = ChunkMetadata::path(&lease.chunk_id, &self.base_folder);
+        let mut meta = ChunkMetadata::load(&path)?;
+
+        // Checkpoints must move forward along the chunk's walk
+        let current = meta.position()?;
+        let checkpoint = ChunkMetadata {
+            last_processed_hex: hex::encode(decode_key(last_processed_hex)?),
+            ..meta.clone()
+        };
+        match checkpoint.position() {
+            Ok(position) if position >= current => {}
+            _ => {
+                return Ok(Response::Error {
+                    message: format!(
+                        "checkpoint {} is not on the {} walk past {}",
+                        last_processed_hex,
+                        meta.walk(),
+                        meta.last_processed_hex
+                    ),
+                });
+            }
+        }
+        meta.last_processed_hex = checkpoint.last_processed_hex;
+        if proof.is_some() {
+            meta.proof = proof;
+        }
+        if script_types.is_some() {
+            meta.script_types = script_types;
+        }
+
+        if release {
+            meta.status = ChunkStatus::Pending;
+            self.leases.remove(&lease_id);
+        } else {
+            lease.expires = Instant::now() + self.lease_duration;
+        }
+        meta.save(&path)?;
+        Ok(Response::Ok)
+    }
+
+    /// Accept a finished chunk once its proof passes the spot checks.
+    ///
+    /// A chunk with a missing or bad proof is reset to its start and goes
+    /// back to `pending` for another worker.
+    fn finish(
+        &mut self,
+        lease_id: u64,
+        proof: Option<ChunkProof>,
+        script_types: Option<ScriptTypes>,
+    ) -> Result<Response> {
+        let Some(lease) = self.leases.remove(&lease_id) else {
+            return Ok(Response::LeaseLost);
+        };
+        let path = self.chunk_path(&lease.chunk_id);
+        let mut meta = ChunkMetadata::load(&path)?;
+        meta.proof = proof;
+        meta.script_types = script_types;
+        if let Err(e) = verify_proof(&meta, SPOT_CHECK_WINDOWS, SPOT_CHECK_KEYS) {
+            meta.set_walk(meta.walk())?;
+            meta.status = ChunkStatus::Pending;
+            meta.proof = None;
+            meta.save(&path)?;
+            return Ok(Response::Error {
+                message: format!("rejected: {}", e),
+            });
+        }
+        meta.last_processed_hex = meta.final_key_hex()?;
+        meta.status = ChunkStatus::Finished;
+        meta.save(&path)?;
+        Ok(Response::Ok)
+    }
+
+    fn record_match(&mut self, lease_id: u64, report: &MatchReport) -> Result<Response> {
+        let worker = self
+            .leases
+            .get(&lease_id)
+            .map(|l| l.worker.clone())
+            .unwrap_or_default();
+        let entry = serde_json::json!({
+            "lease_id": lease_id,
+            "worker": worker,
+            "report": report,
+        });
+        let path = format!("{}/{}", self.base_folder, MATCHES_FILE);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| Error::io(&path, e))?;
+        writeln!(file, "{}", entry).map_err(|e| Error::io(&path, e))?;
+        self.matches += 1;
+        Ok(Response::Ok)
+    }
+
+    fn status(&self) -> Result<Response> {
+        let (mut pending, mut processing, mut finished) = (0, 0, 0);
+        for path in chunk_files(&self.base_folder)? {
+            let Ok(meta) = ChunkMetadata::load(&path.to_string_lossy()) else {
+                continue;
+            };
+            match meta.status {
+                ChunkStatus::Pending => pending += 1,
+                ChunkStatus::Processing => processing += 1,
+                ChunkStatus::Finished | ChunkStatus::Verified => finished += 1,
+            }
+        }
+        Ok(Response::Status {
+            active_leases: self.leases.len(),
+            pending,
+            processing,
+            finished,
+            matches: self.matches,
+        })
+    }
+}
+
+/// Accept connections until the listener fails, one thread per connection
+pub fn serve(listener: TcpListener, coordinator: Arc<Mutex<Coordinator>>) -> Result<()> {
+    // Reap expired leases even when no worker is talking to us
+    {
+        let coordinator = Arc::clone(&coordinator);
+        thread::spawn(move || {
+            loop {
+                thread::sleep(Duration::from_secs(1));
+                let mut c = coordinator.lock().unwrap_or_else(|e| e.into_inner());
+                if let Err(e) = c.expire_leases() {
+                    eprintln!("⚠️  Failed to expire leases: {}", e);
+                }
+            }
+        });
+    }
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("⚠️  Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let coordinator = Arc::clone(&coordinator);
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &coordinator) {
+                eprintln!("⚠️  Connection error: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, coordinator: &Mutex<Coordinator>) -> Result<()> {
+    let mut writer = stream
+        .try_clone()
+        .map_err(|e| Error::io("coordinator connection", e))?;
+    let mut reader = BufReader::new(stream);
+    while let Some(request) = read_message::<Request>(&mut reader)? {
+        let response = coordinator
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .handle(request);
+        write_message(&mut writer, &response)?;
+    }
+    Ok(())
+}
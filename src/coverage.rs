@@ -0,0 +1,350 @@
+use crate::chunk::ChunkMetadata;
+use crate::error::{Error, Result};
+use crate::puzzles::PuzzleRange;
+use crate::range::parse_hex_key;
+use crate::script::ScriptTypes;
+use crate::symmetry::{Symmetry, mirror};
+use crate::validate::chunk_files;
+use num_bigint::BigUint;
+use num_traits::One;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Folder below the chunk root holding imported coverage files
+pub const IMPORTED_FOLDER: &str = "imported";
+
+/// Sorted, non-overlapping inclusive key intervals
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IntervalSet {
+    intervals: Vec<(BigUint, BigUint)>,
+}
+
+impl IntervalSet {
+    /// Build a set from intervals in any order, merging overlapping and
+    /// adjacent ones
+    pub fn from_intervals(intervals: impl IntoIterator<Item = (BigUint, BigUint)>) -> Self {
+        let mut sorted: Vec<_> = intervals.into_iter().filter(|(s, e)| s <= e).collect();
+        sorted.sort();
+        let mut merged: Vec<(BigUint, BigUint)> = Vec::with_capacity(sorted.len());
+        for (start, end) in sorted {
+            match merged.last_mut() {
+                Some((_, last_end)) if start <= &*last_end + BigUint::one() => {
+                    if end > *last_end {
+                        *last_end = end;
+                    }
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+        Self { intervals: merged }
+    }
+
+    pub fn intervals(&self) -> &[(BigUint, BigUint)] {
+        &self.intervals
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Union with another set
+    pub fn extend(&mut self, other: &IntervalSet) {
+        let mut all = std::mem::take(&mut self.intervals);
+        all.extend(other.intervals.iter().cloned());
+        *self = Self::from_intervals(all);
+    }
+
+    /// Whether every key from `start` to `end` inclusive is in the set
+    pub fn covers(&self, start: &BigUint, end: &BigUint) -> bool {
+        self.covering_end(start, end).is_some()
+    }
+
+    /// The last key of the interval holding every key from `start` to `end`
+    /// inclusive, if one does
+    pub fn covering_end(&self, start: &BigUint, end: &BigUint) -> Option<&BigUint> {
+        let i = self.intervals.partition_point(|(s, _)| s <= start);
+        let (_, last) = self.intervals.get(i.checked_sub(1)?)?;
+        (last >= end).then_some(last)
+    }
+
+    /// Total number of keys in the set
+    pub fn key_count(&self) -> BigUint {
+        self.intervals
+            .iter()
+            .map(|(s, e)| e - s + BigUint::one())
+            .sum()
+    }
+}
+
+/// Scanned key intervals with where they came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coverage {
+    pub puzzle: Option<u32>,
+    /// Who scanned these keys, e.g. a pool or team name
+    pub source: String,
+    /// Unix time of the export, when known
+    pub exported_at: Option<u64>,
+    /// Script types every key of the intervals was checked against
+    pub script_types: ScriptTypes,
+    pub intervals: IntervalSet,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CoverageFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    puzzle: Option<u32>,
+    #[serde(default)]
+    source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exported_at: Option<u64>,
+    #[serde(default)]
+    script_types: ScriptTypes,
+    intervals: Vec<IntervalRecord>,
+}
+
+/// An inclusive key interval as written to files and sent to peers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntervalRecord {
+    pub start: String,
+    pub end: String,
+}
+
+impl IntervalRecord {
+    pub fn new(start: &BigUint, end: &BigUint) -> Self {
+        Self {
+            start: hex_key(start),
+            end: hex_key(end),
+        }
+    }
+
+    pub fn parse(&self, context: &str) -> Result<(BigUint, BigUint)> {
+        parse_interval(&self.start, &self.end, context)
+    }
+}
+
+fn hex_key(value: &BigUint) -> String {
+    format!("0x{:x}", value)
+}
+
+/// Parse interval bounds, which are hex whether or not they carry `0x`
+fn parse_interval(start: &str, end: &str, context: &str) -> Result<(BigUint, BigUint)> {
+    let with_context = |e: Error| match e {
+        Error::InvalidRange(message) => Error::InvalidRange(format!("{}: {}", context, message)),
+        other => other,
+    };
+    let start = parse_hex_key(start).map_err(with_context)?;
+    let end = parse_hex_key(end).map_err(with_context)?;
+    if end < start {
+        return Err(Error::InvalidRange(format!(
+            "{}: interval end {:x} is below start {:x}",
+            context, end, start
+        )));
+    }
+    Ok((start, end))
+}
+
+impl Coverage {
+    /// Load a coverage file, JSON when the name ends in `.json` and CSV otherwise
+    pub fn load(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        if path.ends_with(".json") {
+            Self::from_json(&content, path)
+        } else {
+            Self::from_csv(&content, path)
+        }
+    }
+
+    /// Save as JSON when the name ends in `.json` and as CSV otherwise
+    pub fn save(&self, path: &str) -> Result<()> {
+        let content = if path.ends_with(".json") {
+            self.to_json()
+        } else {
+            self.to_csv()
+        };
+        fs::write(path, content).map_err(|e| Error::io(path, e))
+    }
+
+    pub fn to_json(&self) -> String {
+        let file = CoverageFile {
+            puzzle: self.puzzle,
+            source: self.source.clone(),
+            exported_at: self.exported_at,
+            script_types: self.script_types,
+            intervals: self
+                .intervals
+                .intervals()
+                .iter()
+                .map(|(s, e)| IntervalRecord::new(s, e))
+                .collect(),
+        };
+        serde_json::to_string_pretty(&file).unwrap_or_default() + "\n"
+    }
+
+    pub fn from_json(content: &str, path: &str) -> Result<Self> {
+        let file: CoverageFile = serde_json::from_str(content).map_err(|e| Error::json(path, e))?;
+        let intervals = file
+            .intervals
+            .iter()
+            .enumerate()
+            .map(|(i, r)| r.parse(&format!("{} interval {}", path, i)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            puzzle: file.puzzle,
+            source: file.source,
+            exported_at: file.exported_at,
+            script_types: file.script_types,
+            intervals: IntervalSet::from_intervals(intervals),
+        })
+    }
+
+    /// CSV with `# key: value` header comments for the provenance and one
+    /// `start,end` row per interval
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        if let Some(puzzle) = self.puzzle {
+            out.push_str(&format!("# puzzle: {}\n", puzzle));
+        }
+        out.push_str(&format!("# source: {}\n", self.source));
+        if let Some(at) = self.exported_at {
+            out.push_str(&format!("# exported_at: {}\n", at));
+        }
+        out.push_str(&format!("# script_types: {}\n", self.script_types));
+        out.push_str("start,end\n");
+        for (start, end) in self.intervals.intervals() {
+            out.push_str(&format!("{},{}\n", hex_key(start), hex_key(end)));
+        }
+        out
+    }
+
+    pub fn from_csv(content: &str, path: &str) -> Result<Self> {
+        let mut coverage = Self {
+            puzzle: None,
+            source: String::new(),
+            exported_at: None,
+            script_types: ScriptTypes::DEFAULT,
+            intervals: IntervalSet::default(),
+        };
+        let mut intervals = Vec::new();
+        for (n, line) in content.lines().enumerate() {
+            let line = line.trim();
+            let context = format!("{}:{}", path, n + 1);
+            if let Some(comment) = line.strip_prefix('#') {
+                let Some((key, value)) = comment.split_once(':') else {
+                    continue;
+                };
+                let value = value.trim();
+                let invalid =
+                    || Error::InvalidRange(format!("{}: bad {} '{}'", context, key.trim(), value));
+                match key.trim() {
+                    "puzzle" => coverage.puzzle = Some(value.parse().map_err(|_| invalid())?),
+                    "source" => coverage.source = value.to_string(),
+                    "exported_at" => {
+                        coverage.exported_at = Some(value.parse().map_err(|_| invalid())?)
+                    }
+                    "script_types" => {
+                        coverage.script_types = value.parse().map_err(|_| invalid())?
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if line.is_empty() || line.eq_ignore_ascii_case("start,end") {
+                continue;
+            }
+            let (start, end) = line
+                .split_once(',')
+                .ok_or_else(|| Error::InvalidRange(format!("{}: expected START,END", context)))?;
+            intervals.push(parse_interval(start, end, &context)?);
+        }
+        coverage.intervals = IntervalSet::from_intervals(intervals);
+        Ok(coverage)
+    }
+
+    /// Reject intervals that reach outside `range`
+    pub fn check_within(&self, range: &PuzzleRange) -> Result<()> {
+        for (start, end) in self.intervals.intervals() {
+            if *start < range.start || *end > range.end {
+                return Err(Error::InvalidRange(format!(
+                    "interval {:x}-{:x} is outside {:x}-{:x}",
+                    start, end, range.start, range.end
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Keys scanned for at least the `required` script types by the chunks of
+/// a folder: whole finished chunks, and the part of unfinished chunks before
+/// their checkpoint. Chunks walked with a stride or a template skip keys and
+/// cover no range; permuted chunks only cover their range once finished.
+/// Chunks scanned with negation symmetry also cover the
+/// mirrored keys `n - k`; the `λk` images of full symmetry are no key range
+/// and are left out.
+pub fn folder_coverage(base_path: &str, required: ScriptTypes) -> Result<IntervalSet> {
+    let mut intervals = Vec::new();
+    for path in chunk_files(base_path)? {
+        let Ok(meta) = ChunkMetadata::load(&path.to_string_lossy()) else {
+            continue;
+        };
+        if !meta.checked_types().is_superset(required) {
+            continue;
+        }
+        let Some((start, end)) = meta.walk_range()?.scanned(&meta.keys_done()?) else {
+            continue;
+        };
+        if meta.checked_symmetry() >= Symmetry::Negation
+            && let Some(mirrored) = mirror(&start, &end)
+        {
+            intervals.push(mirrored);
+        }
+        intervals.push((start, end));
+    }
+    Ok(IntervalSet::from_intervals(intervals))
+}
+
+/// Store a copy of imported coverage below the chunk root as `<name>.json`,
+/// with characters other than letters, digits, `-` and `.` in the name
+/// replaced so the file stays inside the imported folder
+pub fn store_imported(chunk_root: &str, name: &str, coverage: &Coverage) -> Result<PathBuf> {
+    let folder = Path::new(chunk_root).join(IMPORTED_FOLDER);
+    fs::create_dir_all(&folder).map_err(|e| Error::io(folder.to_string_lossy(), e))?;
+    let safe: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let path = folder.join(format!("{}.json", safe));
+    coverage.save(&path.to_string_lossy())?;
+    Ok(path)
+}
+
+/// Union of the coverage files imported below the chunk root that checked
+/// at least the `required` script types
+pub fn load_imported(chunk_root: &str, required: ScriptTypes) -> Result<IntervalSet> {
+    let folder = Path::new(chunk_root).join(IMPORTED_FOLDER);
+    let mut all = IntervalSet::default();
+    if !folder.exists() {
+        return Ok(all);
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(&folder)
+        .map_err(|e| Error::io(folder.to_string_lossy(), e))?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|x| x == "json"))
+        .collect();
+    files.sort();
+    for path in files {
+        let coverage = Coverage::load(&path.to_string_lossy())?;
+        if coverage.script_types.is_superset(required) {
+            all.extend(&coverage.intervals);
+        }
+    }
+    Ok(all)
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+use std::io;
+
+/// Errors returned by the library instead of panicking.
+#[derive(Debug)]
+pub enum Error {
+    /// A file or folder could not be read or written.
+    Io { path: String, source: io::Error },
+    /// A chunk metadata file could not be parsed or serialized.
+    Json {
+        path: String,
+        source: serde_json::Error,
+    },
+    /// A key string is not valid 32-byte hex.
+    InvalidHex {
+        value: String,
+        source: hex::FromHexError,
+    },
+    /// The bytes are not a valid secp256k1 private key (zero or >= n).
+    InvalidPrivateKey(String),
+    /// A chunk id could not be parsed as a decimal number.
+    InvalidChunkId(String),
+    /// A key range contains no whole chunk.
+    EmptyRange,
+    /// An address could not be encoded.
+    Encoding(String),
+    /// A custom key range specification could not be parsed.
+    InvalidRange(String),
+    /// No key range is known for this puzzle number.
+    UnknownPuzzle(u32),
+    /// A self-test or re-verification found wrong results.
+    Verification(String),
+    /// A coordinator or peer sent an unexpected or malformed message.
+    Protocol(String),
+    /// The configuration file or a command-line override is invalid.
+    Config { path: String, message: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn io(path: impl Into<String>, source: io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn json(path: impl Into<String>, source: serde_json::Error) -> Self {
+        Error::Json {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "I/O error on '{}': {}", path, source),
+            Error::Json { path, source } => {
+                write!(f, "invalid chunk metadata in '{}': {}", path, source)
+            }
+            Error::InvalidHex { value, source } => {
+                write!(f, "invalid hex key '{}': {}", value, source)
+            }
+            Error::InvalidPrivateKey(key) => write!(f, "invalid private key {}", key),
+            Error::InvalidChunkId(id) => write!(f, "invalid chunk id '{}'", id),
+            Error::EmptyRange => write!(f, "key range is smaller than one chunk"),
+            Error::Encoding(msg) => write!(f, "address encoding failed: {}", msg),
+            Error::InvalidRange(msg) => write!(f, "invalid key range: {}", msg),
+            Error::UnknownPuzzle(id) => write!(f, "unknown puzzle #{}", id),
+            Error::Verification(msg) => write!(f, "verification failed: {}", msg),
+            Error::Protocol(msg) => write!(f, "protocol error: {}", msg),
+            Error::Config { path, message } => {
+                write!(f, "invalid configuration in '{}': {}", path, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Json { source, .. } => Some(source),
+            Error::InvalidHex { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,545 @@
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Most keys hashed together by one multi-buffer pass
+pub const MAX_LANES: usize = 16;
+
+/// Longest input the multi-buffer backends take; SEC keys are 33 or 65 bytes
+pub const MAX_INPUT: usize = 119;
+
+/// How a batch of hash160s is computed.
+///
+/// The multi-buffer backends run one SHA-256 and RIPEMD-160 per SIMD lane,
+/// so 8 or 16 keys go through the rounds together. The scalar backends hash
+/// one key at a time with the `sha2` and `ripemd` crates; `ShaNi` pairs the
+/// SHA extensions `sha2` uses when present with multi-buffer RIPEMD-160.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashBackend {
+    Portable,
+    ShaNi,
+    Avx2,
+    Avx512,
+}
+
+impl HashBackend {
+    pub const ALL: [HashBackend; 4] = [
+        HashBackend::Portable,
+        HashBackend::ShaNi,
+        HashBackend::Avx2,
+        HashBackend::Avx512,
+    ];
+
+    /// The fastest backend this CPU supports, detected once
+    pub fn detect() -> Self {
+        static DETECTED: OnceLock<HashBackend> = OnceLock::new();
+        *DETECTED.get_or_init(|| {
+            [HashBackend::Avx512, HashBackend::ShaNi, HashBackend::Avx2]
+                .into_iter()
+                .find(|b| b.is_available())
+                .unwrap_or(HashBackend::Portable)
+        })
+    }
+
+    /// Whether the CPU has the instructions this backend needs
+    pub fn is_available(self) -> bool {
+        #[cfg(target_arch = "x86_64")]
+        {
+            match self {
+                HashBackend::Portable => true,
+                HashBackend::ShaNi => {
+                    std::arch::is_x86_feature_detected!("sha")
+                        && std::arch::is_x86_feature_detected!("avx2")
+                }
+                HashBackend::Avx2 => std::arch::is_x86_feature_detected!("avx2"),
+                HashBackend::Avx512 => std::arch::is_x86_feature_detected!("avx512f"),
+            }
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        {
+            self == HashBackend::Portable
+        }
+    }
+
+    /// Keys hashed per pass
+    pub fn lanes(self) -> usize {
+        match self {
+            HashBackend::Portable => 1,
+            HashBackend::ShaNi | HashBackend::Avx2 => 8,
+            HashBackend::Avx512 => 16,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HashBackend::Portable => "portable",
+            HashBackend::ShaNi => "sha-ni",
+            HashBackend::Avx2 => "avx2",
+            HashBackend::Avx512 => "avx512",
+        }
+    }
+}
+
+impl fmt::Display for HashBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for HashBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        HashBackend::ALL
+            .into_iter()
+            .find(|b| b.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                format!(
+                    "unknown hash backend '{}', expected portable, sha-ni, avx2 or avx512",
+                    s
+                )
+            })
+    }
+}
+
+/// hash160 of one input without allocating
+pub fn hash160_into(input: &[u8], output: &mut [u8; 20]) {
+    output.copy_from_slice(&Ripemd160::digest(Sha256::digest(input)));
+}
+
+/// hash160 of every input with the detected backend
+pub fn hash160_batch<const LEN: usize>(inputs: &[[u8; LEN]], output: &mut [[u8; 20]]) {
+    hash160_batch_with(HashBackend::detect(), inputs, output)
+}
+
+/// hash160 of every input with `backend`, or the portable one when the CPU
+/// lacks its instructions
+pub fn hash160_batch_with<const LEN: usize>(
+    backend: HashBackend,
+    inputs: &[[u8; LEN]],
+    output: &mut [[u8; 20]],
+) {
+    assert_eq!(inputs.len(), output.len(), "one output per input");
+    assert!(LEN <= MAX_INPUT, "inputs longer than {} bytes", MAX_INPUT);
+    if !backend.is_available() {
+        return hash160_batch_with(HashBackend::Portable, inputs, output);
+    }
+    match backend {
+        HashBackend::Portable => {
+            for (input, out) in inputs.iter().zip(output) {
+                hash160_into(input, out);
+            }
+        }
+        #[cfg(target_arch = "x86_64")]
+        HashBackend::ShaNi => {
+            for (inputs, output) in inputs.chunks(8).zip(output.chunks_mut(8)) {
+                let mut digests = [[0u32; 8]; 8];
+                for (lane, input) in inputs.iter().enumerate() {
+                    for (word, bytes) in Sha256::digest(input).chunks_exact(4).enumerate() {
+                        digests[word][lane] =
+                            u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                    }
+                }
+                // SAFETY: `is_available` checked the CPU has SHA and AVX2
+                write_digests(&unsafe { x86::ripemd160_avx2(&digests) }, output);
+            }
+        }
+        #[cfg(target_arch = "x86_64")]
+        HashBackend::Avx2 => hash160_lanes::<8, LEN>(inputs, output, |messages| {
+            // SAFETY: `is_available` checked the CPU has AVX2
+            unsafe { x86::hash160_avx2(messages, LEN) }
+        }),
+        #[cfg(target_arch = "x86_64")]
+        HashBackend::Avx512 => hash160_lanes::<16, LEN>(inputs, output, |messages| {
+            // SAFETY: `is_available` checked the CPU has AVX-512F
+            unsafe { x86::hash160_avx512(messages, LEN) }
+        }),
+        #[cfg(not(target_arch = "x86_64"))]
+        _ => unreachable!("only the portable backend exists off x86-64"),
+    }
+}
+
+/// Hash inputs `L` at a time with a kernel taking one message per lane
+#[cfg(target_arch = "x86_64")]
+fn hash160_lanes<const L: usize, const LEN: usize>(
+    inputs: &[[u8; LEN]],
+    output: &mut [[u8; 20]],
+    kernel: impl Fn(&mut [[u8; 128]; L]) -> [[u32; L]; 5],
+) {
+    for (inputs, output) in inputs.chunks(L).zip(output.chunks_mut(L)) {
+        let mut messages = [[0u8; 128]; L];
+        for (message, input) in messages.iter_mut().zip(inputs) {
+            message[..LEN].copy_from_slice(input);
+        }
+        write_digests(&kernel(&mut messages), output);
+    }
+}
+
+/// Copy the RIPEMD-160 state of each lane out as little-endian bytes
+#[cfg(target_arch = "x86_64")]
+fn write_digests<const L: usize>(state: &[[u32; L]; 5], output: &mut [[u8; 20]]) {
+    for (lane, out) in output.iter_mut().enumerate() {
+        for (word, bytes) in out.chunks_exact_mut(4).enumerate() {
+            bytes.copy_from_slice(&state[word][lane].to_le_bytes());
+        }
+    }
+}
+
+/// Multi-buffer SHA-256 and RIPEMD-160: lane `i` of every vector belongs to
+/// message `i`, so one instruction advances every message by the same step.
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    /// A vector of `u32` lanes. The kernels at the bottom are the only
+    /// callers and are compiled with the instructions each type needs, so
+    /// these always inline into code where the intrinsics are available.
+    trait Lanes: Copy {
+        fn splat(value: u32) -> Self;
+        /// One word per lane
+        fn load(words: &[u32]) -> Self;
+        fn store(self, words: &mut [u32]);
+        fn add(self, other: Self) -> Self;
+        fn xor(self, other: Self) -> Self;
+        fn and(self, other: Self) -> Self;
+        fn or(self, other: Self) -> Self;
+        /// `!self & other`
+        fn andnot(self, other: Self) -> Self;
+        fn rotl(self, bits: u32) -> Self;
+        fn shr(self, bits: u32) -> Self;
+
+        #[inline(always)]
+        fn not(self) -> Self {
+            self.xor(Self::splat(u32::MAX))
+        }
+
+        #[inline(always)]
+        fn rotr(self, bits: u32) -> Self {
+            self.rotl(32 - bits)
+        }
+
+        #[inline(always)]
+        fn swap_bytes(self) -> Self {
+            self.rotl(8)
+                .and(Self::splat(0x00ff_00ff))
+                .or(self.rotl(24).and(Self::splat(0xff00_ff00)))
+        }
+    }
+
+    impl Lanes for __m256i {
+        #[inline(always)]
+        fn splat(value: u32) -> Self {
+            // SAFETY (every method): only reached from `avx2` kernels
+            unsafe { _mm256_set1_epi32(value as i32) }
+        }
+
+        #[inline(always)]
+        fn load(words: &[u32]) -> Self {
+            assert_eq!(words.len(), 8);
+            unsafe { _mm256_loadu_si256(words.as_ptr().cast()) }
+        }
+
+        #[inline(always)]
+        fn store(self, words: &mut [u32]) {
+            assert_eq!(words.len(), 8);
+            unsafe { _mm256_storeu_si256(words.as_mut_ptr().cast(), self) }
+        }
+
+        #[inline(always)]
+        fn add(self, other: Self) -> Self {
+            unsafe { _mm256_add_epi32(self, other) }
+        }
+
+        #[inline(always)]
+        fn xor(self, other: Self) -> Self {
+            unsafe { _mm256_xor_si256(self, other) }
+        }
+
+        #[inline(always)]
+        fn and(self, other: Self) -> Self {
+            unsafe { _mm256_and_si256(self, other) }
+        }
+
+        #[inline(always)]
+        fn or(self, other: Self) -> Self {
+            unsafe { _mm256_or_si256(self, other) }
+        }
+
+        #[inline(always)]
+        fn andnot(self, other: Self) -> Self {
+            unsafe { _mm256_andnot_si256(self, other) }
+        }
+
+        #[inline(always)]
+        fn rotl(self, bits: u32) -> Self {
+            unsafe {
+                _mm256_or_si256(
+                    _mm256_sll_epi32(self, _mm_cvtsi32_si128(bits as i32)),
+                    _mm256_srl_epi32(self, _mm_cvtsi32_si128(32 - bits as i32)),
+                )
+            }
+        }
+
+        #[inline(always)]
+        fn shr(self, bits: u32) -> Self {
+            unsafe { _mm256_srl_epi32(self, _mm_cvtsi32_si128(bits as i32)) }
+        }
+    }
+
+    impl Lanes for __m512i {
+        #[inline(always)]
+        fn splat(value: u32) -> Self {
+            // SAFETY (every method): only reached from `avx512f` kernels
+            unsafe { _mm512_set1_epi32(value as i32) }
+        }
+
+        #[inline(always)]
+        fn load(words: &[u32]) -> Self {
+            assert_eq!(words.len(), 16);
+            unsafe { _mm512_loadu_si512(words.as_ptr().cast()) }
+        }
+
+        #[inline(always)]
+        fn store(self, words: &mut [u32]) {
+            assert_eq!(words.len(), 16);
+            unsafe { _mm512_storeu_si512(words.as_mut_ptr().cast(), self) }
+        }
+
+        #[inline(always)]
+        fn add(self, other: Self) -> Self {
+            unsafe { _mm512_add_epi32(self, other) }
+        }
+
+        #[inline(always)]
+        fn xor(self, other: Self) -> Self {
+            unsafe { _mm512_xor_si512(self, other) }
+        }
+
+        #[inline(always)]
+        fn and(self, other: Self) -> Self {
+            unsafe { _mm512_and_si512(self, other) }
+        }
+
+        #[inline(always)]
+        fn or(self, other: Self) -> Self {
+            unsafe { _mm512_or_si512(self, other) }
+        }
+
+        #[inline(always)]
+        fn andnot(self, other: Self) -> Self {
+            unsafe { _mm512_andnot_si512(self, other) }
+        }
+
+        #[inline(always)]
+        fn rotl(self, bits: u32) -> Self {
+            unsafe { _mm512_rolv_epi32(self, _mm512_set1_epi32(bits as i32)) }
+        }
+
+        #[inline(always)]
+        fn shr(self, bits: u32) -> Self {
+            unsafe { _mm512_srl_epi32(self, _mm_cvtsi32_si128(bits as i32)) }
+        }
+    }
+
+    const SHA256_INIT: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    const SHA256_K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+        0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+        0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+        0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+        0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+        0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+        0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+        0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+        0xc67178f2,
+    ];
+
+    const RIPEMD_INIT: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+    const RIPEMD_K_LEFT: [u32; 5] = [0x00000000, 0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xa953fd4e];
+
+    const RIPEMD_K_RIGHT: [u32; 5] = [0x50a28be6, 0x5c4dd124, 0x6d703ef3, 0x7a6d76e9, 0x00000000];
+
+    const RIPEMD_R_LEFT: [usize; 80] = [
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 7, 4, 13, 1, 10, 6, 15, 3, 12, 0, 9,
+        5, 2, 14, 11, 8, 3, 10, 14, 4, 9, 15, 8, 1, 2, 7, 0, 6, 13, 11, 5, 12, 1, 9, 11, 10, 0, 8,
+        12, 4, 13, 3, 7, 15, 14, 5, 6, 2, 4, 0, 5, 9, 7, 12, 2, 10, 14, 1, 3, 8, 11, 6, 15, 13,
+    ];
+
+    const RIPEMD_R_RIGHT: [usize; 80] = [
+        5, 14, 7, 0, 9, 2, 11, 4, 13, 6, 15, 8, 1, 10, 3, 12, 6, 11, 3, 7, 0, 13, 5, 10, 14, 15, 8,
+        12, 4, 9, 1, 2, 15, 5, 1, 3, 7, 14, 6, 9, 11, 8, 12, 2, 10, 0, 4, 13, 8, 6, 4, 1, 3, 11,
+        15, 0, 5, 12, 2, 13, 9, 7, 10, 14, 12, 15, 10, 4, 1, 5, 8, 7, 6, 2, 13, 14, 0, 3, 9, 11,
+    ];
+
+    const RIPEMD_S_LEFT: [u32; 80] = [
+        11, 14, 15, 12, 5, 8, 7, 9, 11, 13, 14, 15, 6, 7, 9, 8, 7, 6, 8, 13, 11, 9, 7, 15, 7, 12,
+        15, 9, 11, 7, 13, 12, 11, 13, 6, 7, 14, 9, 13, 15, 14, 8, 13, 6, 5, 12, 7, 5, 11, 12, 14,
+        15, 14, 15, 9, 8, 9, 14, 5, 6, 8, 6, 5, 12, 9, 15, 5, 11, 6, 8, 13, 12, 5, 12, 13, 14, 11,
+        8, 5, 6,
+    ];
+
+    const RIPEMD_S_RIGHT: [u32; 80] = [
+        8, 9, 9, 11, 13, 15, 15, 5, 7, 7, 8, 11, 14, 14, 12, 6, 9, 13, 15, 7, 12, 8, 9, 11, 7, 7,
+        12, 7, 6, 15, 13, 11, 9, 7, 15, 11, 8, 6, 6, 14, 12, 13, 5, 14, 13, 13, 7, 5, 15, 5, 8, 11,
+        14, 14, 6, 14, 6, 9, 12, 9, 12, 5, 15, 8, 8, 5, 12, 9, 12, 5, 14, 6, 8, 13, 6, 5, 15, 13,
+        11, 11,
+    ];
+
+    /// SHA-256 of `len`-byte messages, one per lane, padded in place
+    #[inline(always)]
+    fn sha256<V: Lanes, const L: usize>(messages: &mut [[u8; 128]; L], len: usize) -> [V; 8] {
+        let blocks = (len + 8) / 64 + 1;
+        let bits = (len as u64 * 8).to_be_bytes();
+        for message in messages.iter_mut() {
+            message[len] = 0x80;
+            message[blocks * 64 - 8..blocks * 64].copy_from_slice(&bits);
+        }
+
+        let mut state = SHA256_INIT.map(V::splat);
+        for block in 0..blocks {
+            let mut w = [V::splat(0); 64];
+            for (t, word) in w.iter_mut().take(16).enumerate() {
+                let at = block * 64 + t * 4;
+                let words: [u32; L] = std::array::from_fn(|lane| {
+                    let bytes = &messages[lane][at..at + 4];
+                    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+                });
+                *word = V::load(&words);
+            }
+            for t in 16..64 {
+                let s0 = w[t - 15]
+                    .rotr(7)
+                    .xor(w[t - 15].rotr(18))
+                    .xor(w[t - 15].shr(3));
+                let s1 = w[t - 2]
+                    .rotr(17)
+                    .xor(w[t - 2].rotr(19))
+                    .xor(w[t - 2].shr(10));
+                w[t] = w[t - 16].add(s0).add(w[t - 7]).add(s1);
+            }
+
+            let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+            for (k, w) in SHA256_K.iter().zip(w) {
+                let s1 = e.rotr(6).xor(e.rotr(11)).xor(e.rotr(25));
+                let ch = e.and(f).xor(e.andnot(g));
+                let t1 = h.add(s1).add(ch).add(V::splat(*k)).add(w);
+                let s0 = a.rotr(2).xor(a.rotr(13)).xor(a.rotr(22));
+                let maj = a.and(b).xor(a.and(c)).xor(b.and(c));
+                let t2 = s0.add(maj);
+                h = g;
+                g = f;
+                f = e;
+                e = d.add(t1);
+                d = c;
+                c = b;
+                b = a;
+                a = t1.add(t2);
+            }
+            for (word, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+                *word = word.add(add);
+            }
+        }
+        state
+    }
+
+    /// The nonlinear function of RIPEMD-160 round `round`, a constant once
+    /// inlined into [`ripemd_round`]
+    #[inline(always)]
+    fn ripemd_f<V: Lanes>(round: usize, x: V, y: V, z: V) -> V {
+        match round {
+            0 => x.xor(y).xor(z),
+            1 => x.and(y).or(x.andnot(z)),
+            2 => x.or(y.not()).xor(z),
+            3 => x.and(z).or(z.andnot(y)),
+            _ => x.xor(y.or(z.not())),
+        }
+    }
+
+    /// The sixteen steps of round `R` on the left and right lines
+    #[inline(always)]
+    fn ripemd_round<V: Lanes, const R: usize>(left: &mut [V; 5], right: &mut [V; 5], x: &[V; 16]) {
+        for i in 0..16 {
+            let j = R * 16 + i;
+            let [a, b, c, d, e] = *left;
+            let t = a
+                .add(ripemd_f(R, b, c, d))
+                .add(x[RIPEMD_R_LEFT[j]])
+                .add(V::splat(RIPEMD_K_LEFT[R]))
+                .rotl(RIPEMD_S_LEFT[j])
+                .add(e);
+            *left = [e, t, b, c.rotl(10), d];
+
+            let [a, b, c, d, e] = *right;
+            let t = a
+                .add(ripemd_f(4 - R, b, c, d))
+                .add(x[RIPEMD_R_RIGHT[j]])
+                .add(V::splat(RIPEMD_K_RIGHT[R]))
+                .rotl(RIPEMD_S_RIGHT[j])
+                .add(e);
+            *right = [e, t, b, c.rotl(10), d];
+        }
+    }
+
+    /// RIPEMD-160 of 32-byte SHA-256 digests, one per lane
+    #[inline(always)]
+    fn ripemd160<V: Lanes>(digest: [V; 8]) -> [V; 5] {
+        // The digest's big-endian words read as little-endian, then the
+        // padding and the 256-bit length
+        let mut x = [V::splat(0); 16];
+        for (word, digest) in x.iter_mut().zip(digest) {
+            *word = digest.swap_bytes();
+        }
+        x[8] = V::splat(0x80);
+        x[14] = V::splat(256);
+
+        let mut left = RIPEMD_INIT.map(V::splat);
+        let mut right = left;
+        ripemd_round::<V, 0>(&mut left, &mut right, &x);
+        ripemd_round::<V, 1>(&mut left, &mut right, &x);
+        ripemd_round::<V, 2>(&mut left, &mut right, &x);
+        ripemd_round::<V, 3>(&mut left, &mut right, &x);
+        ripemd_round::<V, 4>(&mut left, &mut right, &x);
+
+        let [h0, h1, h2, h3, h4] = RIPEMD_INIT.map(V::splat);
+        let ([al, bl, cl, dl, el], [ar, br, cr, dr, er]) = (left, right);
+        [
+            h1.add(cl).add(dr),
+            h2.add(dl).add(er),
+            h3.add(el).add(ar),
+            h4.add(al).add(br),
+            h0.add(bl).add(cr),
+        ]
+    }
+
+    #[inline(always)]
+    fn unpack<V: Lanes, const L: usize>(state: [V; 5]) -> [[u32; L]; 5] {
+        state.map(|word| {
+            let mut words = [0u32; L];
+            word.store(&mut words);
+            words
+        })
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) fn ripemd160_avx2(digests: &[[u32; 8]; 8]) -> [[u32; 8]; 5] {
+        unpack(ripemd160(digests.map(|words| __m256i::load(&words))))
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) fn hash160_avx2(messages: &mut [[u8; 128]; 8], len: usize) -> [[u32; 8]; 5] {
+        unpack(ripemd160(sha256::<__m256i, 8>(messages, len)))
+    }
+
+    #[target_feature(enable = "avx512f")]
+    pub(super) fn hash160_avx512(messages: &mut [[u8; 128]; 16], len: usize) -> [[u32; 16]; 5] {
+        unpack(ripemd160(sha256::<__m512i, 16>(messages, len)))
+    }
+}
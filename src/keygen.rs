@@ -1,27 +1,50 @@
+use crate::error::{Error, Result};
+use crate::permutation::Permutation;
+use crate::range::parse_key_value;
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
 pub struct HexKeyGenerator {
     current: [u8; 32],
     end: [u8; 32],
+    exhausted: bool,
+    /// Keys stay below 2^128, so only the low half is ever incremented
+    narrow: bool,
 }
 
 impl HexKeyGenerator {
-    pub fn new(start_hex: &str, end_hex: &str) -> Self {
-        let mut current = [0u8; 32];
-        let mut end = [0u8; 32];
-        hex::decode_to_slice(start_hex, &mut current).expect("Invalid start hex");
-        hex::decode_to_slice(end_hex, &mut end).expect("Invalid end hex");
-        Self { current, end }
+    pub fn new(start_hex: &str, end_hex: &str) -> Result<Self> {
+        let end = decode_key(end_hex)?;
+        Ok(Self {
+            current: decode_key(start_hex)?,
+            end,
+            exhausted: false,
+            narrow: end[..16] == [0; 16],
+        })
     }
 
     pub fn next_batch(&mut self, batch_size: usize) -> Vec<[u8; 32]> {
         let mut batch = Vec::with_capacity(batch_size);
+        if self.exhausted {
+            return batch;
+        }
         for _ in 0..batch_size {
             batch.push(self.current);
 
             if self.current == self.end {
+                self.exhausted = true;
                 break;
             }
 
-            Self::increment(&mut self.current);
+            if self.narrow {
+                let low = u128::from_be_bytes(self.current[16..].try_into().unwrap_or_default());
+                self.current[16..].copy_from_slice(&low.wrapping_add(1).to_be_bytes());
+            } else {
+                Self::increment(&mut self.current);
+            }
         }
         batch
     }
@@ -41,3 +64,559 @@ impl HexKeyGenerator {
         }
     }
 }
+
+/// Decode a 64-character hex string into a raw 32-byte key
+pub fn decode_key(key_hex: &str) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    hex::decode_to_slice(key_hex, &mut key).map_err(|source| Error::InvalidHex {
+        value: key_hex.to_string(),
+        source,
+    })?;
+    Ok(key)
+}
+
+/// Encode a key below 2^128 as a raw 32-byte key
+pub fn narrow_key_bytes(key: u128) -> [u8; 32] {
+    let mut raw = [0u8; 32];
+    raw[16..].copy_from_slice(&key.to_be_bytes());
+    raw
+}
+
+/// A key as 32 big-endian bytes
+pub fn key_bytes(key: &BigUint) -> [u8; 32] {
+    let bytes = key.to_bytes_be();
+    let mut raw = [0u8; 32];
+    raw[32 - bytes.len()..].copy_from_slice(&bytes);
+    raw
+}
+
+/// The order a chunk's keys are scanned in.
+///
+/// Chunk files store it as a string such as `stride:7`; chunks without one
+/// are walked in ascending order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum KeyWalk {
+    /// Every key, counting up from the first
+    #[default]
+    Ascending,
+    /// Every key, counting down from the last
+    Descending,
+    /// Every `step`-th key from the first
+    Stride(u64),
+    /// Every key, alternating outward from a center key: `c, c+1, c-1, …`.
+    /// Without a center the middle of the chunk is used; a center outside
+    /// the chunk is clamped to its nearest end.
+    CenterOut(Option<BigUint>),
+    /// Only the keys matching a template, counting up
+    Template(KeyTemplate),
+    /// Every key once, in the pseudorandom order of a keyed permutation.
+    /// Without a key one is picked at random when a chunk starts the walk.
+    Permuted(Option<u64>),
+}
+
+impl KeyWalk {
+    /// Whether the keys walked so far always form one range
+    pub fn is_contiguous(&self) -> bool {
+        match self {
+            KeyWalk::Ascending | KeyWalk::Descending | KeyWalk::CenterOut(_) => true,
+            KeyWalk::Stride(step) => *step == 1,
+            KeyWalk::Template(template) => template.is_suffix(),
+            KeyWalk::Permuted(_) => false,
+        }
+    }
+
+    /// Whether the walk visits every key of its range
+    pub fn visits_every_key(&self) -> bool {
+        matches!(self, KeyWalk::Permuted(_)) || self.is_contiguous()
+    }
+
+    /// The walk with a random permutation key picked when it has none
+    pub fn resolved(self) -> Self {
+        match self {
+            KeyWalk::Permuted(None) => KeyWalk::Permuted(Some(rand::random())),
+            walk => walk,
+        }
+    }
+}
+
+impl fmt::Display for KeyWalk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyWalk::Ascending => f.write_str("ascending"),
+            KeyWalk::Descending => f.write_str("descending"),
+            KeyWalk::Stride(step) => write!(f, "stride:{}", step),
+            KeyWalk::CenterOut(None) => f.write_str("center"),
+            KeyWalk::CenterOut(Some(center)) => write!(f, "center:0x{:x}", center),
+            KeyWalk::Template(template) => write!(f, "template:{}", template),
+            KeyWalk::Permuted(None) => f.write_str("permuted"),
+            KeyWalk::Permuted(Some(key)) => write!(f, "permuted:{}", key),
+        }
+    }
+}
+
+impl FromStr for KeyWalk {
+    type Err = String;
+
+    /// Parse `ascending`, `descending`, `stride:N`, `center`, `center:KEY`,
+    /// `template:PATTERN`, `permuted` or `permuted:KEY`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, value) = match s.split_once(':') {
+            Some((name, value)) => (name, Some(value.trim())),
+            None => (s, None),
+        };
+        match (name.to_ascii_lowercase().as_str(), value) {
+            ("ascending", None) => Ok(KeyWalk::Ascending),
+            ("descending", None) => Ok(KeyWalk::Descending),
+            ("stride", Some(step)) => match step.parse::<u64>() {
+                Ok(step) if step > 0 => Ok(KeyWalk::Stride(step)),
+                _ => Err(format!(
+                    "'{}' is not a stride, expected a positive integer",
+                    step
+                )),
+            },
+            ("center", None) => Ok(KeyWalk::CenterOut(None)),
+            ("center", Some(center)) => parse_key_value(center)
+                .map(|center| KeyWalk::CenterOut(Some(center)))
+                .map_err(|e| e.to_string()),
+            ("template", Some(pattern)) => pattern.parse().map(KeyWalk::Template),
+            ("permuted", None) => Ok(KeyWalk::Permuted(None)),
+            ("permuted", Some(key)) => key
+                .parse()
+                .map(|key| KeyWalk::Permuted(Some(key)))
+                .map_err(|_| format!("'{}' is not a permutation key, expected an integer", key)),
+            _ => Err(format!(
+                "unknown walk '{}', expected ascending, descending, stride:N, center[:KEY], template:PATTERN or permuted[:KEY]",
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for KeyWalk {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<KeyWalk> for String {
+    fn from(walk: KeyWalk) -> Self {
+        walk.to_string()
+    }
+}
+
+/// A 64-nibble key pattern where `?` nibbles are enumerated and the others
+/// are fixed, e.g. `4?????ffff`. Shorter patterns are padded with leading
+/// zeros. Matching keys are numbered in ascending order from 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyTemplate {
+    /// Nibbles from the most significant, `None` for a wildcard
+    nibbles: Box<[Option<u8>; 64]>,
+}
+
+impl KeyTemplate {
+    /// Number of wildcard nibbles
+    pub fn wildcards(&self) -> usize {
+        self.nibbles.iter().filter(|n| n.is_none()).count()
+    }
+
+    /// Number of matching keys, `16^wildcards`
+    pub fn count(&self) -> BigUint {
+        BigUint::one() << (4 * self.wildcards())
+    }
+
+    /// Whether only trailing nibbles are wildcards, so the matching keys
+    /// form one range
+    pub fn is_suffix(&self) -> bool {
+        let fixed = 64 - self.wildcards();
+        self.nibbles[..fixed].iter().all(Option::is_some)
+    }
+
+    /// The matching key numbered `index`, which must be below [`Self::count`]
+    pub fn key_at(&self, index: &BigUint) -> BigUint {
+        let mut digits = index.to_radix_le(16).into_iter();
+        let mut nibbles = [0u8; 64];
+        for (nibble, pattern) in nibbles.iter_mut().zip(self.nibbles.iter()).rev() {
+            *nibble = pattern.unwrap_or_else(|| digits.next().unwrap_or(0));
+        }
+        BigUint::from_radix_be(&nibbles, 16).unwrap_or_default()
+    }
+
+    /// The number of a key, or `None` when it does not match
+    pub fn index_of(&self, key: &BigUint) -> Option<BigUint> {
+        let mut nibbles = key.to_radix_le(16);
+        if nibbles.len() > 64 {
+            return None;
+        }
+        nibbles.resize(64, 0);
+        nibbles.reverse();
+        let mut digits = Vec::new();
+        for (nibble, pattern) in nibbles.iter().zip(self.nibbles.iter()) {
+            match pattern {
+                Some(fixed) if fixed != nibble => return None,
+                Some(_) => {}
+                None => digits.push(*nibble),
+            }
+        }
+        if digits.is_empty() {
+            return Some(BigUint::ZERO);
+        }
+        BigUint::from_radix_be(&digits, 16)
+    }
+}
+
+impl fmt::Display for KeyTemplate {
+    /// The pattern without leading zero nibbles, which parsing adds back
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let leading = self.nibbles[..63]
+            .iter()
+            .take_while(|n| **n == Some(0))
+            .count();
+        for nibble in &self.nibbles[leading..] {
+            match nibble {
+                Some(n) => write!(f, "{:x}", n)?,
+                None => f.write_str("?")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for KeyTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let pattern = s.trim();
+        let pattern = pattern.strip_prefix("0x").unwrap_or(pattern);
+        if pattern.is_empty() || pattern.len() > 64 {
+            return Err(format!(
+                "template '{}' must have 1 to 64 hex digits or '?'",
+                s
+            ));
+        }
+        let mut nibbles = [Some(0); 64];
+        for (nibble, c) in nibbles[64 - pattern.len()..]
+            .iter_mut()
+            .zip(pattern.chars())
+        {
+            *nibble = match c {
+                '?' => None,
+                c => Some(c.to_digit(16).ok_or_else(|| {
+                    format!("template '{}' has '{}', expected a hex digit or '?'", s, c)
+                })? as u8),
+            };
+        }
+        Ok(Self {
+            nibbles: Box::new(nibbles),
+        })
+    }
+}
+
+/// A walk over the keys `start..=end` of a chunk, numbering the keys it
+/// visits by position from 0
+#[derive(Debug, Clone)]
+pub struct WalkRange {
+    walk: KeyWalk,
+    start: BigUint,
+    end: BigUint,
+    len: BigUint,
+    /// Template number of `start`, or the clamped center key
+    anchor: BigUint,
+    /// Order of the keys of a permuted walk
+    permutation: Option<Permutation>,
+    /// The walk in native integers, when its keys lie below 2^128
+    narrow: Option<NarrowWalk>,
+}
+
+/// A walk whose keys and positions fit in `u128`, computed without
+/// allocating; templates and permutations always take the general path
+#[derive(Debug, Clone)]
+struct NarrowWalk {
+    start: u128,
+    end: u128,
+    len: u128,
+    anchor: u128,
+}
+
+impl NarrowWalk {
+    fn new(range: &WalkRange) -> Option<Self> {
+        match range.walk {
+            KeyWalk::Template(_) | KeyWalk::Permuted(_) => return None,
+            _ => {}
+        }
+        Some(Self {
+            start: u128::try_from(&range.start).ok()?,
+            end: u128::try_from(&range.end).ok()?,
+            len: u128::try_from(&range.len).ok()?,
+            anchor: u128::try_from(&range.anchor).ok()?,
+        })
+    }
+
+    /// [`WalkRange::key_at`] for a walk of `walk`
+    fn key_at(&self, walk: &KeyWalk, position: u128) -> u128 {
+        match walk {
+            KeyWalk::Descending => self.end - position,
+            KeyWalk::Stride(step) => self.start + position * *step as u128,
+            KeyWalk::CenterOut(_) => {
+                let (below, above) = (self.anchor - self.start, self.end - self.anchor);
+                let paired = below.min(above);
+                if position == 0 {
+                    self.anchor
+                } else if position <= paired * 2 {
+                    let distance = position.div_ceil(2);
+                    if position & 1 == 1 {
+                        self.anchor + distance
+                    } else {
+                        self.anchor - distance
+                    }
+                } else if below <= above {
+                    self.anchor + (position - paired)
+                } else {
+                    self.anchor - (position - paired)
+                }
+            }
+            _ => self.start + position,
+        }
+    }
+}
+
+impl WalkRange {
+    /// The walk over a chunk's keys. Template walks need both ends to match
+    /// the template.
+    pub fn new(walk: &KeyWalk, start: BigUint, end: BigUint) -> Result<Self> {
+        if start > end {
+            return Err(Error::EmptyRange);
+        }
+        let span = &end - &start;
+        let (len, anchor) = match walk {
+            KeyWalk::Ascending | KeyWalk::Descending => (span + 1u32, BigUint::ZERO),
+            KeyWalk::Stride(0) => {
+                return Err(Error::InvalidRange("stride must be positive".to_string()));
+            }
+            KeyWalk::Stride(step) => (span / *step + 1u32, BigUint::ZERO),
+            KeyWalk::CenterOut(center) => {
+                let center = match center {
+                    Some(center) => center.clamp(&start, &end).clone(),
+                    None => (&start + &end) >> 1,
+                };
+                (span + 1u32, center)
+            }
+            KeyWalk::Template(template) => {
+                let index = |key: &BigUint| {
+                    template.index_of(key).ok_or_else(|| {
+                        Error::InvalidRange(format!(
+                            "{:064x} does not match template {}",
+                            key, template
+                        ))
+                    })
+                };
+                let (first, last) = (index(&start)?, index(&end)?);
+                (last - &first + 1u32, first)
+            }
+            KeyWalk::Permuted(_) => (span + 1u32, BigUint::ZERO),
+        };
+        let permutation = match walk {
+            KeyWalk::Permuted(key) => Some(Permutation::new(key.unwrap_or_default(), len.clone())),
+            _ => None,
+        };
+        let mut range = Self {
+            walk: walk.clone(),
+            start,
+            end,
+            len,
+            anchor,
+            permutation,
+            narrow: None,
+        };
+        range.narrow = NarrowWalk::new(&range);
+        Ok(range)
+    }
+
+    pub fn walk(&self) -> &KeyWalk {
+        &self.walk
+    }
+
+    /// Number of keys the walk visits
+    pub fn len(&self) -> &BigUint {
+        &self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len.is_zero()
+    }
+
+    fn permutation(&self) -> &Permutation {
+        self.permutation
+            .as_ref()
+            .expect("permuted walks are built with a permutation")
+    }
+
+    /// Keys below and above the center of a center-out walk
+    fn sides(&self) -> (BigUint, BigUint) {
+        (&self.anchor - &self.start, &self.end - &self.anchor)
+    }
+
+    /// The key at `position`, which must be below [`Self::len`]
+    pub fn key_at(&self, position: &BigUint) -> BigUint {
+        match &self.walk {
+            KeyWalk::Permuted(_) => &self.start + self.permutation().apply(position),
+            KeyWalk::Ascending => &self.start + position,
+            KeyWalk::Descending => &self.end - position,
+            KeyWalk::Stride(step) => &self.start + position * *step,
+            KeyWalk::Template(template) => template.key_at(&(&self.anchor + position)),
+            KeyWalk::CenterOut(_) => {
+                let (below, above) = self.sides();
+                let paired = below.clone().min(above.clone());
+                if position.is_zero() {
+                    self.anchor.clone()
+                } else if *position <= &paired * 2u32 {
+                    let distance = (position + 1u32) >> 1;
+                    if position.bit(0) {
+                        &self.anchor + distance
+                    } else {
+                        &self.anchor - distance
+                    }
+                } else if below <= above {
+                    &self.anchor + (position - &paired)
+                } else {
+                    &self.anchor - (position - &paired)
+                }
+            }
+        }
+    }
+
+    /// The raw key at `position`
+    pub fn raw_key_at(&self, position: u64) -> [u8; 32] {
+        match &self.narrow {
+            Some(narrow) => narrow_key_bytes(narrow.key_at(&self.walk, position.into())),
+            None => key_bytes(&self.key_at(&BigUint::from(position))),
+        }
+    }
+
+    /// The position of `key`, or `None` when the walk does not visit it
+    pub fn position_of(&self, key: &BigUint) -> Option<BigUint> {
+        if *key < self.start || *key > self.end {
+            return None;
+        }
+        match &self.walk {
+            KeyWalk::Permuted(_) => Some(self.permutation().invert(&(key - &self.start))),
+            KeyWalk::Ascending => Some(key - &self.start),
+            KeyWalk::Descending => Some(&self.end - key),
+            KeyWalk::Stride(step) => {
+                let offset = key - &self.start;
+                (&offset % *step).is_zero().then(|| offset / *step)
+            }
+            KeyWalk::Template(template) => Some(template.index_of(key)? - &self.anchor),
+            KeyWalk::CenterOut(_) => {
+                let (below, above) = self.sides();
+                let paired = below.min(above);
+                Some(if *key == self.anchor {
+                    BigUint::ZERO
+                } else if *key > self.anchor {
+                    let distance = key - &self.anchor;
+                    if distance <= paired {
+                        distance * 2u32 - 1u32
+                    } else {
+                        distance + paired
+                    }
+                } else {
+                    let distance = &self.anchor - key;
+                    if distance <= paired {
+                        distance * 2u32
+                    } else {
+                        distance + paired
+                    }
+                })
+            }
+        }
+    }
+
+    /// The range holding the first `done` keys of a contiguous walk, or of
+    /// any walk visiting every key once it is done; `None` when nothing is
+    /// done or the keys done do not form a range
+    pub fn scanned(&self, done: &BigUint) -> Option<(BigUint, BigUint)> {
+        if *done >= self.len && self.walk.visits_every_key() {
+            return Some((self.start.clone(), self.end.clone()));
+        }
+        if done.is_zero() || !self.walk.is_contiguous() {
+            return None;
+        }
+        let done = done.min(&self.len);
+        let last = self.key_at(&(done - 1u32));
+        match &self.walk {
+            KeyWalk::Descending => Some((last, self.end.clone())),
+            KeyWalk::CenterOut(_) => {
+                let (below, above) = self.sides();
+                let paired = below.clone().min(above.clone());
+                let steps = done - 1u32;
+                let (down, up) = if steps <= &paired * 2u32 {
+                    (&steps >> 1, (&steps + 1u32) >> 1)
+                } else if below <= above {
+                    (below.clone(), steps - below)
+                } else {
+                    (&steps - &above, above)
+                };
+                Some((&self.anchor - down, &self.anchor + up))
+            }
+            _ => Some((self.key_at(&BigUint::ZERO), last)),
+        }
+    }
+}
+
+/// Batches of raw keys along a walk, resumable from any position
+pub struct KeyGenerator {
+    range: WalkRange,
+    next: BigUint,
+}
+
+impl KeyGenerator {
+    /// A generator starting at `position` of the walk
+    pub fn new(range: WalkRange, position: BigUint) -> Self {
+        Self {
+            range,
+            next: position,
+        }
+    }
+
+    pub fn next_batch(&mut self, batch_size: usize) -> Vec<[u8; 32]> {
+        let mut batch = Vec::with_capacity(batch_size);
+        if let Some(narrow) = &self.range.narrow
+            && let Ok(next) = u128::try_from(&self.next)
+        {
+            let stop = next
+                .saturating_add(batch_size as u128)
+                .min(narrow.len)
+                .max(next);
+            batch.extend(
+                (next..stop)
+                    .map(|position| narrow_key_bytes(narrow.key_at(&self.range.walk, position))),
+            );
+            self.next = stop.into();
+            return batch;
+        }
+        while batch.len() < batch_size && self.next < *self.range.len() {
+            batch.push(key_bytes(&self.range.key_at(&self.next)));
+            self.next += 1u32;
+        }
+        batch
+    }
+
+    /// Position of the next key to scan
+    pub fn position(&self) -> &BigUint {
+        &self.next
+    }
+
+    /// The next key to scan, or the walk's last key once every key has been
+    /// handed out, like [`HexKeyGenerator::last_key`]
+    pub fn last_key(&self) -> String {
+        let position = if self.next < *self.range.len() {
+            self.next.clone()
+        } else {
+            self.range.len() - 1u32
+        };
+        hex::encode(key_bytes(&self.range.key_at(&position)))
+    }
+}
@@ -1,6 +1,26 @@
 pub mod address;
+pub mod audit;
 pub mod chunk;
 pub mod chunk_manager;
+pub mod codec;
+pub mod config;
+pub mod coordinator;
+pub mod coverage;
+pub mod error;
+pub mod hash;
 pub mod keygen;
+pub mod merge;
+pub mod peer;
+pub mod permutation;
+pub mod proof;
+pub mod protocol;
 pub mod puzzles;
+pub mod range;
+pub mod reference;
+pub mod scanner;
+pub mod script;
 pub mod search;
+pub mod shard;
+pub mod symmetry;
+pub mod validate;
+pub mod worker;
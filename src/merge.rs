@@ -0,0 +1,99 @@
+use crate::chunk::{ChunkMetadata, ChunkStatus};
+use crate::error::{Error, Result};
+use crate::puzzles::PuzzleRange;
+use crate::validate::{check_chunk_file, chunk_files};
+use num_bigint::BigUint;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Outcome of merging chunk folders into one
+#[derive(Debug, Default)]
+pub struct MergeReport {
+    pub copied: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+/// Orders copies of the same chunk by how much work they record
+fn progress_rank(meta: &ChunkMetadata) -> Result<(u8, BigUint)> {
+    Ok(match meta.status {
+        ChunkStatus::Verified => (2, BigUint::ZERO),
+        ChunkStatus::Finished => (1, BigUint::ZERO),
+        _ => (0, meta.keys_done()?),
+    })
+}
+
+/// Merge the chunk files of several folders, e.g. the ledgers of sharded
+/// workers, into `dest`.
+///
+/// When a chunk exists in more than one place, the copy with the most work
+/// wins. Chunks still `processing` in a source are merged as `pending`, since
+/// nobody is scanning the merged copy. Broken files and copies that disagree
+/// on a chunk's key range are skipped and reported.
+pub fn merge_chunk_folders(
+    sources: &[String],
+    dest: &str,
+    puzzle_range: Option<&PuzzleRange>,
+    dry_run: bool,
+) -> Result<MergeReport> {
+    fs::create_dir_all(dest).map_err(|e| Error::io(dest, e))?;
+    let mut report = MergeReport::default();
+    // What has been merged so far, so a dry run compares like a real one
+    let mut merged: HashMap<PathBuf, ChunkMetadata> = HashMap::new();
+
+    for source in sources {
+        for path in chunk_files(source)? {
+            let (loaded, issues) = check_chunk_file(&path, puzzle_range);
+            let mut incoming = match loaded {
+                Some(meta) if issues.is_empty() => meta,
+                _ => {
+                    let reasons: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+                    report.skipped.push((path, reasons.join("; ")));
+                    continue;
+                }
+            };
+            if incoming.status == ChunkStatus::Processing {
+                incoming.status = ChunkStatus::Pending;
+            }
+
+            let target = Path::new(dest).join(path.file_name().unwrap_or_default());
+            let existing = match merged.get(&target) {
+                Some(meta) => Some(meta.clone()),
+                None if target.exists() => match check_chunk_file(&target, puzzle_range) {
+                    (Some(meta), _) => Some(meta),
+                    (None, _) => {
+                        report
+                            .skipped
+                            .push((path, format!("{} is unreadable", target.display())));
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            if let Some(existing) = existing {
+                if existing.start_hex != incoming.start_hex || existing.end_hex != incoming.end_hex
+                {
+                    report.skipped.push((
+                        path,
+                        format!("covers a different key range than {}", target.display()),
+                    ));
+                    continue;
+                }
+                if progress_rank(&incoming)? <= progress_rank(&existing)? {
+                    report.unchanged += 1;
+                    continue;
+                }
+                report.updated += 1;
+            } else {
+                report.copied += 1;
+            }
+            if !dry_run {
+                incoming.save(&target.to_string_lossy())?;
+            }
+            merged.insert(target, incoming);
+        }
+    }
+    Ok(report)
+}
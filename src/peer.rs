@@ -0,0 +1,488 @@
+use crate::coverage::{Coverage, IntervalRecord, IntervalSet};
+use crate::error::{Error, Result};
+use crate::protocol::{exchange, read_message, write_message};
+use crate::script::ScriptTypes;
+use crate::symmetry::{Symmetry, mirror};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Folder below the chunk root holding the coverage learned from each peer
+pub const PEERS_FOLDER: &str = "peers";
+
+/// How long a peer's claim holds without being refreshed by a sync
+pub const CLAIM_TTL: Duration = Duration::from_secs(600);
+
+/// How long to wait for a peer before treating it as down
+pub const PEER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A message between scanner nodes, one JSON object per line over TCP
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PeerRequest {
+    /// Ask for the intervals scanned after entry `since` of the peer's
+    /// ledger, and its active claims. `epoch` is the peer run the caller
+    /// last synced with; after a restart the ledger is sent from the start.
+    Sync {
+        node: String,
+        epoch: Option<u64>,
+        since: usize,
+    },
+    /// Ask to scan a key interval
+    Claim { node: String, span: IntervalRecord },
+}
+
+/// A node's answer to a [`PeerRequest`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PeerResponse {
+    Synced {
+        node: String,
+        epoch: u64,
+        /// Ledger length, the `since` for the next sync
+        seq: usize,
+        finished: Vec<ScannedRecord>,
+        claims: Vec<IntervalRecord>,
+    },
+    Granted,
+    /// Someone already scanned or is scanning the interval
+    Held {
+        by: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// A ledger entry: an interval a node scanned and the script types it
+/// checked there
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScannedRecord {
+    #[serde(flatten)]
+    pub span: IntervalRecord,
+    /// Missing from nodes that predate it, which checked the default types
+    #[serde(default)]
+    pub script_types: ScriptTypes,
+}
+
+/// An interval this node is scanning or about to scan
+#[derive(Debug)]
+struct OwnClaim {
+    start: BigUint,
+    end: BigUint,
+    /// All reachable peers agreed
+    confirmed: bool,
+    /// A peer with priority claimed the interval while we were asking
+    lost: bool,
+}
+
+#[derive(Debug)]
+struct PeerClaim {
+    node: String,
+    start: BigUint,
+    end: BigUint,
+    expires: Instant,
+}
+
+fn overlaps(a: (&BigUint, &BigUint), b: (&BigUint, &BigUint)) -> bool {
+    a.0 <= b.1 && b.0 <= a.1
+}
+
+/// One scanner's view of a peer group that shares coverage without a
+/// coordinator.
+///
+/// Each node keeps a ledger of the intervals it has scanned and hands out
+/// the entries a peer has not seen yet. Before scanning a chunk a node
+/// claims it from every peer; when two nodes claim the same chunk at once,
+/// the node with the smaller name wins. Unreachable peers are skipped, so
+/// the group keeps working while any node is down.
+#[derive(Debug)]
+pub struct PeerNode {
+    name: String,
+    epoch: u64,
+    root: String,
+    /// Script types this node checks; peer coverage of fewer types is
+    /// not trusted
+    script_types: ScriptTypes,
+    ledger: Vec<(BigUint, BigUint, ScriptTypes)>,
+    own_claims: Vec<OwnClaim>,
+    peer_claims: Vec<PeerClaim>,
+    /// Intervals scanned by each peer for each set of script types, also
+    /// saved below the chunk root
+    peer_coverage: HashMap<(String, ScriptTypes), IntervalSet>,
+    /// Ledger position reached for each peer address
+    cursors: HashMap<String, (u64, usize)>,
+}
+
+impl PeerNode {
+    /// Start a node checking `script_types` whose ledger begins with the
+    /// keys it already scanned, loading what earlier runs learned from peers
+    pub fn open(
+        name: &str,
+        chunk_root: &str,
+        scanned: &IntervalSet,
+        script_types: ScriptTypes,
+    ) -> Result<Self> {
+        let mut peer_coverage: HashMap<_, IntervalSet> = HashMap::new();
+        let folder = Path::new(chunk_root).join(PEERS_FOLDER);
+        if folder.exists() {
+            let entries =
+                fs::read_dir(&folder).map_err(|e| Error::io(folder.to_string_lossy(), e))?;
+            for path in entries.flatten().map(|e| e.path()) {
+                if path.extension().is_some_and(|x| x == "json") {
+                    let coverage = Coverage::load(&path.to_string_lossy())?;
+                    peer_coverage
+                        .entry((coverage.source, coverage.script_types))
+                        .or_default()
+                        .extend(&coverage.intervals);
+                }
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            epoch: rand::random(),
+            root: chunk_root.to_string(),
+            script_types,
+            ledger: scanned
+                .intervals()
+                .iter()
+                .map(|(start, end)| (start.clone(), end.clone(), script_types))
+                .collect(),
+            own_claims: Vec::new(),
+            peer_claims: Vec::new(),
+            peer_coverage,
+            cursors: HashMap::new(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Peer coverage that checked at least this node's script types
+    fn trusted_coverage(&self) -> impl Iterator<Item = (&String, &IntervalSet)> {
+        self.peer_coverage
+            .iter()
+            .filter(|((_, types), _)| types.is_superset(self.script_types))
+            .map(|((node, _), set)| (node, set))
+    }
+
+    /// Keys other nodes have scanned or are scanning
+    pub fn excluded(&self) -> IntervalSet {
+        let now = Instant::now();
+        let mut excluded = IntervalSet::from_intervals(
+            self.peer_claims
+                .iter()
+                .filter(|c| c.expires > now)
+                .map(|c| (c.start.clone(), c.end.clone()))
+                .collect::<Vec<_>>(),
+        );
+        for (_, coverage) in self.trusted_coverage() {
+            excluded.extend(coverage);
+        }
+        excluded
+    }
+
+    /// Which other peer, if any, holds an interval, ignoring `asking`'s own claims
+    fn peer_holder(&self, start: &BigUint, end: &BigUint, asking: &str) -> Option<String> {
+        let now = Instant::now();
+        self.peer_claims
+            .iter()
+            .find(|c| {
+                c.node != asking && c.expires > now && overlaps((&c.start, &c.end), (start, end))
+            })
+            .map(|c| c.node.clone())
+            .or_else(|| {
+                self.trusted_coverage()
+                    .find(|(node, set)| *node != asking && set.covers(start, end))
+                    .map(|(node, _)| node.clone())
+            })
+    }
+
+    pub fn handle(&mut self, request: PeerRequest) -> PeerResponse {
+        match request {
+            PeerRequest::Sync { epoch, since, .. } => {
+                let since = if epoch == Some(self.epoch) { since } else { 0 };
+                PeerResponse::Synced {
+                    node: self.name.clone(),
+                    epoch: self.epoch,
+                    seq: self.ledger.len(),
+                    finished: self
+                        .ledger
+                        .iter()
+                        .skip(since)
+                        .map(|(s, e, types)| ScannedRecord {
+                            span: IntervalRecord::new(s, e),
+                            script_types: *types,
+                        })
+                        .collect(),
+                    claims: self
+                        .own_claims
+                        .iter()
+                        .map(|c| IntervalRecord::new(&c.start, &c.end))
+                        .collect(),
+                }
+            }
+            PeerRequest::Claim { node, span } => {
+                let (start, end) = match span.parse("claim") {
+                    Ok(bounds) => bounds,
+                    Err(e) => {
+                        return PeerResponse::Error {
+                            message: e.to_string(),
+                        };
+                    }
+                };
+                for claim in &mut self.own_claims {
+                    if overlaps((&claim.start, &claim.end), (&start, &end)) {
+                        if claim.confirmed || self.name < node {
+                            return PeerResponse::Held {
+                                by: self.name.clone(),
+                            };
+                        }
+                        claim.lost = true;
+                    }
+                }
+                if self.ledger.iter().any(|(s, e, types)| {
+                    types.is_superset(self.script_types) && overlaps((s, e), (&start, &end))
+                }) {
+                    return PeerResponse::Held {
+                        by: self.name.clone(),
+                    };
+                }
+                if let Some(by) = self.peer_holder(&start, &end, &node) {
+                    return PeerResponse::Held { by };
+                }
+                self.peer_claims.push(PeerClaim {
+                    node,
+                    start,
+                    end,
+                    expires: Instant::now() + CLAIM_TTL,
+                });
+                PeerResponse::Granted
+            }
+        }
+    }
+
+    pub fn sync_request(&self, addr: &str) -> PeerRequest {
+        let (epoch, since) = match self.cursors.get(addr) {
+            Some(&(epoch, seq)) => (Some(epoch), seq),
+            None => (None, 0),
+        };
+        PeerRequest::Sync {
+            node: self.name.clone(),
+            epoch,
+            since,
+        }
+    }
+
+    /// Merge a peer's sync answer, saving any new coverage it reported
+    pub fn apply_sync(&mut self, addr: &str, response: PeerResponse) -> Result<()> {
+        let PeerResponse::Synced {
+            node,
+            epoch,
+            seq,
+            finished,
+            claims,
+        } = response
+        else {
+            return Err(Error::Protocol(format!(
+                "{} answered a sync with {:?}",
+                addr, response
+            )));
+        };
+        self.cursors.insert(addr.to_string(), (epoch, seq));
+
+        let expires = Instant::now() + CLAIM_TTL;
+        self.peer_claims.retain(|c| c.node != node);
+        for span in &claims {
+            let (start, end) = span.parse(addr)?;
+            self.peer_claims.push(PeerClaim {
+                node: node.clone(),
+                start,
+                end,
+                expires,
+            });
+        }
+
+        let mut new: HashMap<ScriptTypes, Vec<_>> = HashMap::new();
+        for record in &finished {
+            new.entry(record.script_types)
+                .or_default()
+                .push(record.span.parse(addr)?);
+        }
+        for (script_types, intervals) in new {
+            let coverage = self
+                .peer_coverage
+                .entry((node.clone(), script_types))
+                .or_default();
+            coverage.extend(&IntervalSet::from_intervals(intervals));
+            let folder = Path::new(&self.root).join(PEERS_FOLDER);
+            fs::create_dir_all(&folder).map_err(|e| Error::io(folder.to_string_lossy(), e))?;
+            let file = peer_file_name(&format!("{}_{}", node, script_types));
+            Coverage {
+                puzzle: None,
+                source: node.clone(),
+                exported_at: None,
+                script_types,
+                intervals: coverage.clone(),
+            }
+            .save(&folder.join(file).to_string_lossy())?;
+        }
+        Ok(())
+    }
+
+    /// Start claiming an interval, or `false` when it is already known to
+    /// be taken
+    pub fn begin_claim(&mut self, start: &BigUint, end: &BigUint) -> bool {
+        if self.peer_holder(start, end, &self.name).is_some() {
+            return false;
+        }
+        self.own_claims.push(OwnClaim {
+            start: start.clone(),
+            end: end.clone(),
+            confirmed: false,
+            lost: false,
+        });
+        true
+    }
+
+    /// Settle a claim once every reachable peer answered. A refused claim
+    /// is treated like a peer's claim, so it is not picked again right away.
+    pub fn end_claim(&mut self, start: &BigUint, granted: bool, by: &str) -> bool {
+        let Some(i) = self.own_claims.iter().position(|c| c.start == *start) else {
+            return false;
+        };
+        if granted && !self.own_claims[i].lost {
+            self.own_claims[i].confirmed = true;
+            return true;
+        }
+        let claim = self.own_claims.remove(i);
+        self.peer_claims.push(PeerClaim {
+            node: by.to_string(),
+            start: claim.start,
+            end: claim.end,
+            expires: Instant::now() + CLAIM_TTL,
+        });
+        false
+    }
+
+    /// Add an interval scanned for `script_types` to the ledger and drop
+    /// its claim. With negation symmetry the mirrored keys `n - k` are
+    /// covered as well.
+    pub fn record_scanned(
+        &mut self,
+        start: &BigUint,
+        end: &BigUint,
+        script_types: ScriptTypes,
+        symmetry: Symmetry,
+    ) {
+        self.own_claims.retain(|c| c.start != *start);
+        self.ledger.push((start.clone(), end.clone(), script_types));
+        if symmetry >= Symmetry::Negation
+            && let Some((start, end)) = mirror(start, end)
+        {
+            self.ledger.push((start, end, script_types));
+        }
+    }
+
+    /// Drop a claim without scanning the interval
+    pub fn release(&mut self, start: &BigUint) {
+        self.own_claims.retain(|c| c.start != *start);
+    }
+}
+
+/// File name for a peer's coverage; node names are often `host:port`
+fn peer_file_name(node: &str) -> String {
+    let safe: String = node
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.json", safe)
+}
+
+fn lock(node: &Mutex<PeerNode>) -> std::sync::MutexGuard<'_, PeerNode> {
+    node.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Pull ledger deltas and claims from every peer, returning the peers that
+/// could not be reached
+pub fn sync_with_peers(node: &Mutex<PeerNode>, peers: &[String]) -> Vec<(String, Error)> {
+    let mut unreachable = Vec::new();
+    for addr in peers {
+        let request = lock(node).sync_request(addr);
+        let result = exchange::<_, PeerResponse>(addr, &request, PEER_TIMEOUT)
+            .and_then(|response| lock(node).apply_sync(addr, response));
+        if let Err(e) = result {
+            unreachable.push((addr.clone(), e));
+        }
+    }
+    unreachable
+}
+
+/// Claim an interval from every reachable peer before scanning it
+pub fn claim_with_peers(
+    node: &Mutex<PeerNode>,
+    peers: &[String],
+    start: &BigUint,
+    end: &BigUint,
+) -> bool {
+    let name = {
+        let mut node = lock(node);
+        if !node.begin_claim(start, end) {
+            return false;
+        }
+        node.name().to_string()
+    };
+    let request = PeerRequest::Claim {
+        node: name,
+        span: IntervalRecord::new(start, end),
+    };
+    for addr in peers {
+        // A peer that is down cannot be scanning, so it does not get a say
+        if let Ok(PeerResponse::Held { by }) = exchange(addr, &request, PEER_TIMEOUT) {
+            return lock(node).end_claim(start, false, &by);
+        }
+    }
+    lock(node).end_claim(start, true, "")
+}
+
+/// Answer peer requests until the listener fails
+pub fn serve_peers(listener: TcpListener, node: Arc<Mutex<PeerNode>>) -> Result<()> {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let node = Arc::clone(&node);
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &node) {
+                eprintln!("⚠️  Peer connection error: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, node: &Mutex<PeerNode>) -> Result<()> {
+    let mut writer = stream
+        .try_clone()
+        .map_err(|e| Error::io("peer connection", e))?;
+    let mut reader = BufReader::new(stream);
+    while let Some(request) = read_message::<PeerRequest>(&mut reader)? {
+        let response = lock(node).handle(request);
+        write_message(&mut writer, &response)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use num_bigint::BigUint;
+use num_traits::One;
+use sha2::{Digest, Sha256};
+
+/// Feistel rounds; four make a strong pseudorandom permutation
+const ROUNDS: u8 = 4;
+
+/// A keyed pseudorandom permutation of `0..size`.
+///
+/// A balanced Feistel network permutes the smallest even number of bits
+/// holding `size - 1`, and values falling outside the domain are permuted
+/// again until they land inside it (cycle walking). The network covers less
+/// than four times the domain, so a few rounds of walking are enough.
+#[derive(Debug, Clone)]
+pub struct Permutation {
+    key: u64,
+    size: BigUint,
+    half_bits: u64,
+    mask: BigUint,
+}
+
+impl Permutation {
+    /// The permutation of `0..size` for `key`; `size` must be at least one
+    pub fn new(key: u64, size: BigUint) -> Self {
+        let bits = (&size - BigUint::one()).bits().max(2);
+        let half_bits = bits.div_ceil(2);
+        Self {
+            key,
+            size,
+            half_bits,
+            mask: (BigUint::one() << half_bits) - BigUint::one(),
+        }
+    }
+
+    pub fn size(&self) -> &BigUint {
+        &self.size
+    }
+
+    /// The value `index` is sent to
+    pub fn apply(&self, index: &BigUint) -> BigUint {
+        let mut value = self.encrypt(index);
+        while value >= self.size {
+            value = self.encrypt(&value);
+        }
+        value
+    }
+
+    /// The index sent to `value`
+    pub fn invert(&self, value: &BigUint) -> BigUint {
+        let mut index = self.decrypt(value);
+        while index >= self.size {
+            index = self.decrypt(&index);
+        }
+        index
+    }
+
+    fn round(&self, round: u8, half: &BigUint) -> BigUint {
+        let digest = Sha256::new()
+            .chain_update(self.key.to_le_bytes())
+            .chain_update([round])
+            .chain_update(half.to_bytes_le())
+            .finalize();
+        BigUint::from_bytes_le(&digest) & &self.mask
+    }
+
+    fn encrypt(&self, value: &BigUint) -> BigUint {
+        let (mut left, mut right) = (value >> self.half_bits, value & &self.mask);
+        for round in 0..ROUNDS {
+            let mixed = left ^ self.round(round, &right);
+            left = right;
+            right = mixed;
+        }
+        (left << self.half_bits) | right
+    }
+
+    fn decrypt(&self, value: &BigUint) -> BigUint {
+        let (mut left, mut right) = (value >> self.half_bits, value & &self.mask);
+        for round in (0..ROUNDS).rev() {
+            let mixed = right ^ self.round(round, &left);
+            right = left;
+            left = mixed;
+        }
+        (left << self.half_bits) | right
+    }
+}
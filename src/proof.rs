@@ -0,0 +1,211 @@
+use crate::address::{hash160, public_key};
+use crate::chunk::ChunkMetadata;
+use crate::error::{Error, Result};
+use crate::keygen::WalkRange;
+use num_traits::ToPrimitive;
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Distinguished keys a chunk is expected to yield; sets the difficulty
+const TARGET_DISTINGUISHED: u32 = 256;
+
+/// Standard deviations a proof's distinguished key count may stray from the
+/// expected count before the proof is rejected
+const COUNT_TOLERANCE: f64 = 5.0;
+
+/// Proof that a chunk was scanned.
+///
+/// A key is distinguished when the hash160 of its compressed public key has
+/// at least `bits` leading zero bits. Those keys cannot be found without
+/// deriving every key of the chunk, yet they are cheap to check afterwards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkProof {
+    /// Leading zero bits a hash160 needs to be distinguished
+    pub bits: u32,
+    /// Positions of the distinguished keys along the chunk's walk, which
+    /// are their offsets from `start_hex` in ascending chunks
+    pub distinguished: Vec<u64>,
+    /// Running SHA-256 over the distinguished hash160 values in key order
+    pub fingerprint: String,
+}
+
+impl ChunkProof {
+    pub fn new(bits: u32) -> Self {
+        Self {
+            bits,
+            distinguished: Vec::new(),
+            fingerprint: hex::encode([0u8; 32]),
+        }
+    }
+
+    /// A proof whose difficulty yields about 256 distinguished keys for a
+    /// chunk of `keys` keys
+    pub fn for_chunk_size(keys: u64) -> Self {
+        let key_bits = u64::BITS - keys.leading_zeros();
+        Self::new(key_bits.saturating_sub(TARGET_DISTINGUISHED.ilog2()))
+    }
+
+    pub fn is_distinguished(&self, hash: &[u8]) -> bool {
+        leading_zero_bits(hash) >= self.bits
+    }
+
+    /// Record a distinguished key. Offsets at or before the last recorded one
+    /// are ignored, so keys rescanned after a resume are not counted twice.
+    pub fn record(&mut self, offset: u64, hash: &[u8]) {
+        if self
+            .distinguished
+            .last()
+            .is_some_and(|&last| offset <= last)
+        {
+            return;
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(hex::decode(&self.fingerprint).unwrap_or_default());
+        hasher.update(hash);
+        self.fingerprint = hex::encode(hasher.finalize());
+        self.distinguished.push(offset);
+    }
+}
+
+/// The fewest and most distinguished keys an honest scan of `keys` keys at
+/// `bits` difficulty is expected to list.
+///
+/// The count is binomial with mean `keys >> bits`. The bound is wide enough
+/// that an honest proof almost never fails it, yet a proof missing half of
+/// its keys always does.
+pub fn distinguished_bounds(keys: u64, bits: u32) -> (u64, u64) {
+    let p = 0.5f64.powi(bits as i32);
+    let mean = keys as f64 * p;
+    let slack = COUNT_TOLERANCE * (mean * (1.0 - p)).sqrt();
+    let min = (mean - slack).ceil().max(0.0) as u64;
+    let max = ((mean + slack).floor() as u64).min(keys);
+    (min, max)
+}
+
+/// Number of leading zero bits in a byte string
+pub fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in bytes {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+/// The hash160 of a key's compressed public key, the value proofs are built on
+pub fn proof_hash(raw_key: &[u8; 32]) -> Result<Vec<u8>> {
+    Ok(hash160(&public_key(raw_key)?.serialize()))
+}
+
+/// A chunk's walk and the number of keys on it
+pub fn chunk_bounds(meta: &ChunkMetadata) -> Result<(WalkRange, u64)> {
+    let walk = meta.walk_range()?;
+    let keys = walk
+        .len()
+        .to_u64()
+        .ok_or_else(|| Error::Verification(format!("chunk {} is too large", meta.chunk_id)))?;
+    Ok((walk, keys))
+}
+
+/// Distinguished positions and hashes among `count` keys starting at `from`
+fn distinguished_in(
+    walk: &WalkRange,
+    from: u64,
+    count: u64,
+    proof: &ChunkProof,
+) -> Result<Vec<(u64, Vec<u8>)>> {
+    let hashes: Vec<Vec<u8>> = (from..from + count)
+        .into_par_iter()
+        .map(|position| proof_hash(&walk.raw_key_at(position)))
+        .collect::<Result<_>>()?;
+    Ok((from..)
+        .zip(hashes)
+        .filter(|(_, hash)| proof.is_distinguished(hash))
+        .collect())
+}
+
+/// Build the proof for a whole chunk by deriving every key
+pub fn compute_proof(meta: &ChunkMetadata) -> Result<ChunkProof> {
+    let (walk, keys) = chunk_bounds(meta)?;
+    let mut proof = ChunkProof::for_chunk_size(keys);
+    for (offset, hash) in distinguished_in(&walk, 0, keys, &proof)? {
+        proof.record(offset, &hash);
+    }
+    Ok(proof)
+}
+
+/// Check a finished chunk's proof.
+///
+/// The number of listed keys must be within [`distinguished_bounds`]. Every
+/// listed key is derived again to confirm it is distinguished and to rebuild
+/// the fingerprint. Then `windows` random runs of `window_keys` keys, but at
+/// least the mean gap between distinguished keys, are scanned, and every
+/// distinguished key found there must be listed.
+pub fn verify_proof(meta: &ChunkMetadata, windows: usize, window_keys: u64) -> Result<()> {
+    let fail =
+        |message: String| Error::Verification(format!("chunk {}: {}", meta.chunk_id, message));
+    let proof = meta
+        .proof
+        .as_ref()
+        .ok_or_else(|| fail("no proof of work".to_string()))?;
+    let (walk, keys) = chunk_bounds(meta)?;
+
+    let expected = ChunkProof::for_chunk_size(keys).bits;
+    if proof.bits != expected {
+        return Err(fail(format!(
+            "proof difficulty is {} bits, expected {}",
+            proof.bits, expected
+        )));
+    }
+    if proof.distinguished.windows(2).any(|w| w[0] >= w[1])
+        || proof.distinguished.last().is_some_and(|&last| last >= keys)
+    {
+        return Err(fail(
+            "distinguished offsets are out of order or range".to_string(),
+        ));
+    }
+    let (min, max) = distinguished_bounds(keys, proof.bits);
+    let listed = proof.distinguished.len() as u64;
+    if listed < min || listed > max {
+        return Err(fail(format!(
+            "proof lists {} distinguished keys, expected {} to {}",
+            listed, min, max
+        )));
+    }
+
+    let mut rebuilt = ChunkProof::new(proof.bits);
+    for &offset in &proof.distinguished {
+        let hash = proof_hash(&walk.raw_key_at(offset))?;
+        if !proof.is_distinguished(&hash) {
+            return Err(fail(format!(
+                "key at offset {} is not distinguished",
+                offset
+            )));
+        }
+        rebuilt.record(offset, &hash);
+    }
+    if rebuilt.fingerprint != proof.fingerprint {
+        return Err(fail(
+            "fingerprint does not match the listed keys".to_string(),
+        ));
+    }
+
+    let window_keys = window_keys.max(1u64 << proof.bits).min(keys);
+    let mut rng = rand::thread_rng();
+    for _ in 0..windows {
+        let from = rng.gen_range(0..=keys - window_keys);
+        for (offset, _) in distinguished_in(&walk, from, window_keys, proof)? {
+            if proof.distinguished.binary_search(&offset).is_err() {
+                return Err(fail(format!(
+                    "distinguished key at offset {} is missing from the proof",
+                    offset
+                )));
+            }
+        }
+    }
+    Ok(())
+}
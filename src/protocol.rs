@@ -0,0 +1,130 @@
+use crate::chunk::ChunkMetadata;
+use crate::error::{Error, Result};
+use crate::proof::ChunkProof;
+use crate::scanner::MatchReport;
+use crate::script::ScriptTypes;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// How long a client waits for the coordinator before giving up
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A message from a worker to the coordinator.
+///
+/// Messages are sent as one JSON object per line over TCP.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// Ask for a chunk to scan
+    Lease { worker: String },
+    /// Keep a lease alive and checkpoint its progress
+    Heartbeat {
+        lease_id: u64,
+        last_processed_hex: String,
+        #[serde(default)]
+        proof: Option<ChunkProof>,
+        #[serde(default)]
+        script_types: Option<ScriptTypes>,
+    },
+    /// Give a lease back unfinished, keeping its checkpoint
+    Release {
+        lease_id: u64,
+        last_processed_hex: String,
+        #[serde(default)]
+        proof: Option<ChunkProof>,
+        #[serde(default)]
+        script_types: Option<ScriptTypes>,
+    },
+    /// Report that a leased chunk has been fully scanned, with its proof of work
+    Finish {
+        lease_id: u64,
+        #[serde(default)]
+        proof: Option<ChunkProof>,
+        /// Script types checked; `None` from workers that predate the field
+        #[serde(default)]
+        script_types: Option<ScriptTypes>,
+    },
+    /// Report a key that matched the target database
+    Match { lease_id: u64, report: MatchReport },
+    /// Ask for a summary of the chunk store and leases
+    Status,
+}
+
+/// The coordinator's answer to a [`Request`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Leased {
+        lease_id: u64,
+        chunk: ChunkMetadata,
+        lease_secs: u64,
+    },
+    NoWork,
+    Ok,
+    /// The lease is unknown or has expired and was handed to someone else
+    LeaseLost,
+    Status {
+        active_leases: usize,
+        pending: usize,
+        processing: usize,
+        finished: usize,
+        matches: usize,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Write one message as a JSON line
+pub fn write_message<T: Serialize>(stream: &mut impl Write, message: &T) -> Result<()> {
+    let mut line = serde_json::to_string(message).map_err(|e| Error::Protocol(e.to_string()))?;
+    line.push('\n');
+    stream
+        .write_all(line.as_bytes())
+        .and_then(|_| stream.flush())
+        .map_err(|e| Error::io("coordinator connection", e))
+}
+
+/// Read one JSON-line message, or `None` when the peer closed the connection
+pub fn read_message<T: DeserializeOwned>(reader: &mut impl BufRead) -> Result<Option<T>> {
+    let mut line = String::new();
+    let read = reader
+        .read_line(&mut line)
+        .map_err(|e| Error::io("coordinator connection", e))?;
+    if read == 0 {
+        return Ok(None);
+    }
+    serde_json::from_str(&line)
+        .map(Some)
+        .map_err(|e| Error::Protocol(format!("bad message '{}': {}", line.trim(), e)))
+}
+
+/// Send a single request to a coordinator and wait for its response
+pub fn send_request(addr: &str, request: &Request) -> Result<Response> {
+    exchange(addr, request, CLIENT_TIMEOUT)
+}
+
+/// Send one message and wait up to `timeout` for the reply
+pub fn exchange<Q: Serialize, A: DeserializeOwned>(
+    addr: &str,
+    message: &Q,
+    timeout: Duration,
+) -> Result<A> {
+    let socket = addr
+        .to_socket_addrs()
+        .map_err(|e| Error::io(addr, e))?
+        .next()
+        .ok_or_else(|| Error::Protocol(format!("{} does not resolve", addr)))?;
+    let mut stream =
+        TcpStream::connect_timeout(&socket, timeout).map_err(|e| Error::io(addr, e))?;
+    stream
+        .set_read_timeout(Some(timeout))
+        .map_err(|e| Error::io(addr, e))?;
+    write_message(&mut stream, message)?;
+    let mut reader = BufReader::new(stream);
+    read_message(&mut reader)?
+        .ok_or_else(|| Error::Protocol(format!("{} closed the connection", addr)))
+}
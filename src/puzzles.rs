@@ -1,12 +1,31 @@
 use num_bigint::BigUint;
 use std::collections::HashMap;
 
-#[derive(Clone)]
+/// An inclusive range of private keys
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PuzzleRange {
     pub start: BigUint,
     pub end: BigUint,
 }
 
+impl PuzzleRange {
+    /// Number of keys in the range
+    pub fn size(&self) -> BigUint {
+        if self.end < self.start {
+            return BigUint::ZERO;
+        }
+        &self.end - &self.start + 1u32
+    }
+
+    /// Both ends as native integers, when the range lies below 2^128
+    pub fn narrow(&self) -> Option<(u128, u128)> {
+        Some((
+            u128::try_from(&self.start).ok()?,
+            u128::try_from(&self.end).ok()?,
+        ))
+    }
+}
+
 pub fn get_puzzle_ranges() -> HashMap<u32, PuzzleRange> {
     let mut map = HashMap::new();
 
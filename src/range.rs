@@ -0,0 +1,177 @@
+use crate::chunk::max_private_key;
+use crate::error::{Error, Result};
+use crate::keygen::KeyTemplate;
+use crate::puzzles::{PuzzleRange, get_puzzle_ranges};
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use std::fs;
+
+/// Parse a single key as `0x`-prefixed hex or plain decimal
+pub fn parse_key_value(value: &str) -> Result<BigUint> {
+    let value = value.trim();
+    if value.starts_with("0x") || value.starts_with("0X") {
+        return parse_hex_key(value);
+    }
+    BigUint::parse_bytes(value.as_bytes(), 10)
+        .filter(|_| !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()))
+        .ok_or_else(|| {
+            Error::InvalidRange(format!(
+                "'{}' is not a key, write hex keys with a 0x prefix",
+                value
+            ))
+        })
+}
+
+/// Parse a key written in hex, with or without a `0x` prefix
+pub fn parse_hex_key(value: &str) -> Result<BigUint> {
+    let value = value.trim();
+    let hex = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    BigUint::parse_bytes(hex.as_bytes(), 16)
+        .filter(|_| !hex.is_empty() && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+        .ok_or_else(|| Error::InvalidRange(format!("'{}' is not a hex key", value)))
+}
+
+/// Parse a key count such as `2^24`, or a value accepted by
+/// [`parse_key_value`]; the count must be positive
+pub fn parse_key_count(value: &str) -> Result<BigUint> {
+    let value = value.trim();
+    let count = match value.split_once('^') {
+        Some(("2", exponent)) => {
+            let exponent: u32 = exponent
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&exponent| exponent <= 256)
+                .ok_or_else(|| {
+                    Error::InvalidRange(format!("'{}' is not a power of two up to 2^256", value))
+                })?;
+            BigUint::one() << exponent
+        }
+        _ => parse_key_value(value)?,
+    };
+    if count.is_zero() {
+        return Err(Error::InvalidRange(format!(
+            "'{}' is not a positive count",
+            value
+        )));
+    }
+    Ok(count)
+}
+
+/// Parse a percentage like `40`, `40%` or `12.5%` into a fraction `num / den`
+fn parse_percent(value: &str) -> Result<(BigUint, BigUint)> {
+    let trimmed = value.trim().trim_end_matches('%').trim();
+    let invalid = || Error::InvalidRange(format!("'{}' is not a percentage", value));
+    let (whole, frac) = trimmed.split_once('.').unwrap_or((trimmed, ""));
+    let digits = format!("{}{}", whole, frac);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let num = BigUint::parse_bytes(digits.as_bytes(), 10).ok_or_else(invalid)?;
+    let den = BigUint::from(100u32) * BigUint::from(10u32).pow(frac.len() as u32);
+    if num > den {
+        return Err(invalid());
+    }
+    Ok((num, den))
+}
+
+/// Parse a key range specification.
+///
+/// Accepted forms:
+/// - `START-END` or `START..END`, each side in any form accepted by [`parse_key_value`]
+/// - `puzzle:71` for a whole puzzle range
+/// - `puzzle:71@40%-41%` or `puzzle:71 at 40%–41%` for a slice of a puzzle
+pub fn parse_range_spec(spec: &str) -> Result<PuzzleRange> {
+    let spec = spec.trim();
+    if let Some(rest) = spec.strip_prefix("puzzle:") {
+        return parse_puzzle_spec(spec, rest);
+    }
+
+    let (start, end) = spec
+        .split_once("..")
+        .or_else(|| spec.split_once('-'))
+        .ok_or_else(|| Error::InvalidRange(format!("'{}' has no START-END separator", spec)))?;
+    make_range(parse_key_value(start)?, parse_key_value(end)?)
+}
+
+fn parse_puzzle_spec(spec: &str, rest: &str) -> Result<PuzzleRange> {
+    let (id, slice) = match rest.split_once('@').or_else(|| rest.split_once(" at ")) {
+        Some((id, slice)) => (id.trim(), Some(slice.trim())),
+        None => (rest.trim(), None),
+    };
+    let puzzle_id: u32 = id
+        .parse()
+        .map_err(|_| Error::InvalidRange(format!("'{}' has no puzzle number", spec)))?;
+    let range = get_puzzle_ranges()
+        .remove(&puzzle_id)
+        .ok_or(Error::UnknownPuzzle(puzzle_id))?;
+    let Some(slice) = slice else {
+        return Ok(range);
+    };
+
+    let (from, to) = slice
+        .split_once(['-', '–'])
+        .ok_or_else(|| Error::InvalidRange(format!("'{}' has no FROM%-TO% slice", spec)))?;
+    let (from_num, from_den) = parse_percent(from)?;
+    let (to_num, to_den) = parse_percent(to)?;
+
+    let size = range.size();
+    let start = &range.start + &size * from_num / from_den;
+    let end_exclusive = &range.start + &size * to_num / to_den;
+    if end_exclusive <= start {
+        return Err(Error::InvalidRange(format!("'{}' is an empty slice", spec)));
+    }
+    make_range(start, end_exclusive - BigUint::one())
+}
+
+fn make_range(start: BigUint, end: BigUint) -> Result<PuzzleRange> {
+    if start.is_zero() {
+        return Err(Error::InvalidRange(
+            "key 0 is not a valid private key".to_string(),
+        ));
+    }
+    if end < start {
+        return Err(Error::InvalidRange(format!(
+            "range end {:x} is below start {:x}",
+            end, start
+        )));
+    }
+    if end > max_private_key() {
+        return Err(Error::InvalidRange(format!(
+            "range end {:x} is beyond the curve order",
+            end
+        )));
+    }
+    Ok(PuzzleRange { start, end })
+}
+
+/// Read one range specification per line, skipping blank lines and `#` comments
+pub fn load_ranges_file(path: &str) -> Result<Vec<PuzzleRange>> {
+    let content = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_range_spec)
+        .collect()
+}
+
+/// Chunk folder name for a custom range, e.g. `range_400000_40ffff`
+pub fn range_folder_name(range: &PuzzleRange) -> String {
+    format!("range_{:x}_{:x}", range.start, range.end)
+}
+
+/// Chunk folder name for a template, with `x` for each wildcard, e.g.
+/// `template_4xxxxxffff`
+pub fn template_folder_name(template: &KeyTemplate) -> String {
+    format!("template_{}", template.to_string().replace('?', "x"))
+}
+
+/// Chunk folder name for the sample windows of a puzzle, e.g.
+/// `window_16777216`, kept inside the puzzle's folder
+pub fn window_folder_name(window: &BigUint) -> String {
+    format!("window_{}", window)
+}
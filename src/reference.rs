@@ -0,0 +1,257 @@
+use crate::error::{Error, Result};
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+const FIELD_PRIME: &[u8] = b"fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f";
+const CURVE_ORDER: &[u8] = b"fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+const GENERATOR_X: &[u8] = b"79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+const GENERATOR_Y: &[u8] = b"483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BECH32_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+fn hex_constant(hex: &[u8]) -> BigUint {
+    BigUint::parse_bytes(hex, 16).unwrap_or_default()
+}
+
+/// A point in Jacobian coordinates; `z == 0` is the point at infinity
+#[derive(Clone)]
+struct Point {
+    x: BigUint,
+    y: BigUint,
+    z: BigUint,
+}
+
+struct Curve {
+    p: BigUint,
+}
+
+impl Curve {
+    fn sub(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a + &self.p - b) % &self.p
+    }
+
+    fn mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a * b % &self.p
+    }
+
+    fn infinity() -> Point {
+        Point {
+            x: BigUint::one(),
+            y: BigUint::one(),
+            z: BigUint::zero(),
+        }
+    }
+
+    fn double(&self, pt: &Point) -> Point {
+        if pt.z.is_zero() || pt.y.is_zero() {
+            return Self::infinity();
+        }
+        let y2 = self.mul(&pt.y, &pt.y);
+        let s = self.mul(&(BigUint::from(4u32) * &pt.x), &y2);
+        let m = self.mul(&BigUint::from(3u32), &self.mul(&pt.x, &pt.x));
+        let x = self.sub(&self.mul(&m, &m), &(BigUint::from(2u32) * &s % &self.p));
+        let y = self.sub(
+            &self.mul(&m, &self.sub(&s, &x)),
+            &(BigUint::from(8u32) * self.mul(&y2, &y2) % &self.p),
+        );
+        let z = self.mul(&(BigUint::from(2u32) * &pt.y), &pt.z);
+        Point { x, y, z }
+    }
+
+    fn add(&self, a: &Point, b: &Point) -> Point {
+        if a.z.is_zero() {
+            return b.clone();
+        }
+        if b.z.is_zero() {
+            return a.clone();
+        }
+        let az2 = self.mul(&a.z, &a.z);
+        let bz2 = self.mul(&b.z, &b.z);
+        let u1 = self.mul(&a.x, &bz2);
+        let u2 = self.mul(&b.x, &az2);
+        let s1 = self.mul(&a.y, &self.mul(&bz2, &b.z));
+        let s2 = self.mul(&b.y, &self.mul(&az2, &a.z));
+        if u1 == u2 {
+            return if s1 == s2 {
+                self.double(a)
+            } else {
+                Self::infinity()
+            };
+        }
+        let h = self.sub(&u2, &u1);
+        let r = self.sub(&s2, &s1);
+        let h2 = self.mul(&h, &h);
+        let h3 = self.mul(&h2, &h);
+        let u1h2 = self.mul(&u1, &h2);
+        let x = self.sub(
+            &self.sub(&self.mul(&r, &r), &h3),
+            &(BigUint::from(2u32) * &u1h2 % &self.p),
+        );
+        let y = self.sub(&self.mul(&r, &self.sub(&u1h2, &x)), &self.mul(&s1, &h3));
+        let z = self.mul(&h, &self.mul(&a.z, &b.z));
+        Point { x, y, z }
+    }
+
+    /// Affine coordinates of `k * G` by double-and-add
+    fn multiply_generator(&self, k: &BigUint) -> Option<(BigUint, BigUint)> {
+        let g = Point {
+            x: hex_constant(GENERATOR_X),
+            y: hex_constant(GENERATOR_Y),
+            z: BigUint::one(),
+        };
+        let mut result = Self::infinity();
+        for i in (0..k.bits()).rev() {
+            result = self.double(&result);
+            if k.bit(i) {
+                result = self.add(&result, &g);
+            }
+        }
+        if result.z.is_zero() {
+            return None;
+        }
+        let z_inv = result.z.modpow(&(&self.p - 2u32), &self.p);
+        let z_inv2 = self.mul(&z_inv, &z_inv);
+        Some((
+            self.mul(&result.x, &z_inv2),
+            self.mul(&result.y, &self.mul(&z_inv2, &z_inv)),
+        ))
+    }
+}
+
+fn to_32_bytes(value: &BigUint) -> [u8; 32] {
+    let bytes = value.to_bytes_be();
+    let mut out = [0u8; 32];
+    out[32 - bytes.len()..].copy_from_slice(&bytes);
+    out
+}
+
+/// Compressed and uncompressed SEC1 encodings of a private key's public key
+pub fn reference_public_key(raw_key: &[u8; 32]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let k = BigUint::from_bytes_be(raw_key);
+    if k.is_zero() || k >= hex_constant(CURVE_ORDER) {
+        return Err(Error::InvalidPrivateKey(hex::encode(raw_key)));
+    }
+    let curve = Curve {
+        p: hex_constant(FIELD_PRIME),
+    };
+    let (x, y) = curve
+        .multiply_generator(&k)
+        .ok_or_else(|| Error::InvalidPrivateKey(hex::encode(raw_key)))?;
+
+    let mut compressed = vec![if y.bit(0) { 0x03 } else { 0x02 }];
+    compressed.extend(to_32_bytes(&x));
+    let mut uncompressed = vec![0x04];
+    uncompressed.extend(to_32_bytes(&x));
+    uncompressed.extend(to_32_bytes(&y));
+    Ok((compressed, uncompressed))
+}
+
+pub fn reference_hash160(data: &[u8]) -> Vec<u8> {
+    Ripemd160::digest(Sha256::digest(data)).to_vec()
+}
+
+fn base58check(version: u8, payload: &[u8]) -> String {
+    let mut data = vec![version];
+    data.extend(payload);
+    let checksum = Sha256::digest(Sha256::digest(&data));
+    data.extend(&checksum[..4]);
+
+    let mut value = BigUint::from_bytes_be(&data);
+    let mut encoded = Vec::new();
+    let base = BigUint::from(58u32);
+    while !value.is_zero() {
+        let digit = (&value % &base)
+            .to_u32_digits()
+            .first()
+            .copied()
+            .unwrap_or(0);
+        encoded.push(BASE58_ALPHABET[digit as usize]);
+        value /= &base;
+    }
+    let leading_zeros = data.iter().take_while(|&&b| b == 0).count();
+    encoded.extend(std::iter::repeat_n(b'1', leading_zeros));
+    encoded.reverse();
+    String::from_utf8_lossy(&encoded).into_owned()
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+    let mut checksum = 1u32;
+    for &value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x01ff_ffff) << 5) ^ value as u32;
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= g;
+            }
+        }
+    }
+    checksum
+}
+
+/// Segwit v0 address for a witness program
+fn bech32_segwit_v0(hrp: &str, program: &[u8]) -> String {
+    let mut data = vec![0u8];
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for &byte in program {
+        acc = ((acc << 8) | byte as u32) & 0xfff;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            data.push(((acc >> bits) & 31) as u8);
+        }
+    }
+    if bits > 0 {
+        data.push(((acc << (5 - bits)) & 31) as u8);
+    }
+
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 31));
+    values.extend(&data);
+    values.extend([0u8; 6]);
+    let checksum = bech32_polymod(&values) ^ 1;
+
+    let mut address = format!("{}1", hrp);
+    for value in data {
+        address.push(BECH32_CHARSET[value as usize] as char);
+    }
+    for i in 0..6 {
+        address.push(BECH32_CHARSET[((checksum >> (5 * (5 - i))) & 31) as usize] as char);
+    }
+    address
+}
+
+/// The addresses of a private key for the default script types, in
+/// `derive_addresses` order.
+///
+/// This is a slow reference path: the curve arithmetic and the address
+/// encodings are written out on plain big integers, without the `secp256k1`,
+/// `base58` or `bech32` crates, so it can cross-check the fast path.
+pub fn reference_addresses(raw_key: &[u8; 32]) -> Result<Vec<String>> {
+    let (compressed, uncompressed) = reference_public_key(raw_key)?;
+    Ok(reference_addresses_for(&compressed, &uncompressed))
+}
+
+/// The addresses of an already derived public key, see [`reference_addresses`]
+pub fn reference_addresses_for(compressed: &[u8], uncompressed: &[u8]) -> Vec<String> {
+    let pubkey_hash = reference_hash160(compressed);
+    let mut redeem_script = vec![0x00, 0x14];
+    redeem_script.extend(&pubkey_hash);
+
+    vec![
+        base58check(0x00, &pubkey_hash),
+        base58check(0x00, &reference_hash160(uncompressed)),
+        base58check(0x05, &reference_hash160(&redeem_script)),
+        bech32_segwit_v0("bc", &pubkey_hash),
+    ]
+}
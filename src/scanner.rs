@@ -0,0 +1,425 @@
+use crate::address::{
+    DerivedAddress, KeyHashes, derive, derive_with_hashes, nested_redeem_script,
+    private_key_to_wif, public_key,
+};
+use crate::chunk::{ChunkMetadata, ChunkStatus};
+use crate::error::{Error, Result};
+use crate::hash::{MAX_LANES, hash160_batch};
+use crate::keygen::KeyGenerator;
+use crate::proof::ChunkProof;
+use crate::script::{ScriptType, ScriptTypes};
+use crate::search::{MatchAction, TargetGroup, TargetSet};
+use crate::symmetry::{Symmetry, Variant};
+use num_traits::{ToPrimitive, Zero};
+use rayon::prelude::*;
+use secp256k1::PublicKey;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A private key whose derived address is in the target database
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchReport {
+    pub hex_key: String,
+    pub address: String,
+    pub wif_uncompressed: String,
+    pub wif_compressed: String,
+    pub format: String,
+    /// Labels of the target groups holding the key, highest priority first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sets: Vec<String>,
+    /// The most urgent action asked for by any group holding the key: `stop`
+    /// over `notify`, and `notify` over `continue`
+    #[serde(default)]
+    pub action: MatchAction,
+}
+
+impl MatchReport {
+    /// Header line of `match_found.csv`
+    pub const CSV_HEADER: &str =
+        "hex_key;matched_address;wif_uncompressed;wif_compressed;format;sets";
+
+    pub fn new(raw_key: &[u8; 32], address: &DerivedAddress) -> Self {
+        Self {
+            hex_key: hex::encode(raw_key),
+            address: address.to_string(),
+            wif_uncompressed: private_key_to_wif(raw_key, false, address.network),
+            wif_compressed: private_key_to_wif(raw_key, true, address.network),
+            format: address.script.label().to_string(),
+            sets: Vec::new(),
+            action: MatchAction::Stop,
+        }
+    }
+
+    /// Attribute the match to `groups`, given highest priority first
+    pub fn in_groups<'a>(mut self, groups: impl IntoIterator<Item = &'a TargetGroup>) -> Self {
+        let groups: Vec<_> = groups.into_iter().collect();
+        if let Some(action) = [
+            MatchAction::Stop,
+            MatchAction::Notify,
+            MatchAction::Continue,
+        ]
+        .into_iter()
+        .find(|&action| groups.iter().any(|g| g.action() == action))
+        {
+            self.action = action;
+        }
+        self.sets = groups.iter().map(|g| g.label().to_string()).collect();
+        self
+    }
+
+    /// The `match_found.csv` line for this match
+    pub fn to_csv_line(&self) -> String {
+        format!(
+            "{};{};{};{};{};{}",
+            self.hex_key,
+            self.address,
+            self.wif_uncompressed,
+            self.wif_compressed,
+            self.format,
+            self.sets.join(",")
+        )
+    }
+
+    /// The `match_found.csv` contents for this match alone
+    pub fn to_csv(&self) -> String {
+        format!("{}\n{}\n", Self::CSV_HEADER, self.to_csv_line())
+    }
+
+    /// Append the match to a CSV file, writing the header to a new file
+    pub fn append_csv(&self, path: &str) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| Error::io(path, e))?;
+        let fresh = file.metadata().map_err(|e| Error::io(path, e))?.len() == 0;
+        let content = if fresh {
+            self.to_csv()
+        } else {
+            format!("{}\n", self.to_csv_line())
+        };
+        file.write_all(content.as_bytes())
+            .map_err(|e| Error::io(path, e))
+    }
+}
+
+/// Why a scan stopped before running out of work
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopReason {
+    MaxChunks,
+    MaxKeys,
+    MaxTime,
+    StopFile,
+}
+
+/// Limits for a continuous scan; every limit is optional
+#[derive(Debug, Clone, Default)]
+pub struct ScanLimits {
+    pub max_chunks: Option<u64>,
+    pub max_keys: Option<u64>,
+    pub max_time: Option<Duration>,
+    pub stop_file: Option<PathBuf>,
+}
+
+impl ScanLimits {
+    /// Check the limits that can interrupt a chunk between batches
+    pub fn reached_within_chunk(&self, stats: &ScanStats) -> Option<StopReason> {
+        if self.max_keys.is_some_and(|max| stats.keys >= max) {
+            return Some(StopReason::MaxKeys);
+        }
+        if self
+            .max_time
+            .is_some_and(|max| stats.started.elapsed() >= max)
+        {
+            return Some(StopReason::MaxTime);
+        }
+        if self.stop_file.as_ref().is_some_and(|p| p.exists()) {
+            return Some(StopReason::StopFile);
+        }
+        None
+    }
+
+    /// Check all limits before starting another chunk
+    pub fn reached(&self, stats: &ScanStats) -> Option<StopReason> {
+        if self.max_chunks.is_some_and(|max| stats.chunks >= max) {
+            return Some(StopReason::MaxChunks);
+        }
+        self.reached_within_chunk(stats)
+    }
+}
+
+/// Counters that keep running across chunks
+#[derive(Debug, Clone)]
+pub struct ScanStats {
+    pub chunks: u64,
+    pub batches: u64,
+    pub keys: u64,
+    pub started: Instant,
+}
+
+impl Default for ScanStats {
+    fn default() -> Self {
+        Self {
+            chunks: 0,
+            batches: 0,
+            keys: 0,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl ScanStats {
+    pub fn keys_per_second(&self) -> f64 {
+        self.keys as f64 / self.started.elapsed().as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// How a call to [`scan_chunk`] ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChunkOutcome {
+    Finished,
+    /// Every match of the batch that stopped the scan, in key order; at
+    /// least one of them asks to stop
+    Matched(Vec<MatchReport>),
+    Stopped(StopReason),
+}
+
+/// Progress reported after every checkpointed batch
+pub struct BatchProgress<'a> {
+    pub meta: &'a ChunkMetadata,
+    /// Matches in the batch whose targets do not stop the scan
+    pub matches: &'a [MatchReport],
+    pub batch_start_hex: &'a str,
+    pub batch_keys: usize,
+    pub elapsed: Duration,
+    pub stats: &'a ScanStats,
+}
+
+/// Scan a chunk from its last checkpoint, saving progress after every batch.
+///
+/// The chunk is marked finished when its range is exhausted, or reset to
+/// pending when a limit stops the scan part-way. Distinguished keys are
+/// added to the chunk's proof of work as they are found. Only the script
+/// types of `targets` are derived; the chunk records them, narrowed to the
+/// types it already had when it is resumed with a different set. The
+/// symmetric keys of `targets` are checked and recorded the same way, and a
+/// match on one of them reports that key. A match whose target groups do
+/// not ask to stop is passed on with its batch and the scan goes on; a
+/// match that stops it is returned with the other matches of its batch,
+/// which is left unrecorded.
+pub fn scan_chunk(
+    meta: &mut ChunkMetadata,
+    path: &str,
+    targets: &TargetSet,
+    batch_size: usize,
+    limits: &ScanLimits,
+    stats: &mut ScanStats,
+    mut on_batch: impl FnMut(&BatchProgress),
+) -> Result<ChunkOutcome> {
+    let script_types = targets.script_types();
+    let position = meta.position()?;
+    let fresh = position.is_zero();
+    meta.script_types = Some(if fresh {
+        script_types
+    } else {
+        meta.checked_types().intersection(script_types)
+    });
+    let symmetry = if fresh {
+        targets.symmetry()
+    } else {
+        meta.checked_symmetry().min(targets.symmetry())
+    };
+    meta.symmetry = (symmetry != Symmetry::Off).then_some(symmetry);
+    let mut generator = KeyGenerator::new(meta.walk_range()?, position);
+    let mut proof = match meta.proof.take() {
+        Some(proof) => proof,
+        None => ChunkProof::for_chunk_size(meta.keys_total()?.to_u64().unwrap_or(u64::MAX)),
+    };
+
+    loop {
+        if let Some(reason) = limits.reached_within_chunk(stats) {
+            meta.proof = Some(proof);
+            meta.status = ChunkStatus::Pending;
+            meta.save(path)?;
+            return Ok(ChunkOutcome::Stopped(reason));
+        }
+
+        let batch_size = match limits.max_keys {
+            Some(max) => batch_size.min(max.saturating_sub(stats.keys) as usize),
+            None => batch_size,
+        };
+        let batch_position = generator.position().clone();
+        let batch = generator.next_batch(batch_size);
+        if batch.is_empty() {
+            break;
+        }
+
+        let batch_start_hex = hex::encode(batch[0]);
+        let batch_start = Instant::now();
+        let reports: Mutex<Vec<MatchReport>> = Mutex::new(Vec::new());
+        let distinguished: Mutex<Vec<(usize, Vec<u8>)>> = Mutex::new(Vec::new());
+
+        batch.par_chunks(MAX_LANES).enumerate().try_for_each(
+            |(group, raw_keys)| -> Result<()> {
+                let public_keys = raw_keys
+                    .iter()
+                    .map(public_key)
+                    .collect::<Result<Vec<_>>>()?;
+                let hashes = hash_lanes(&public_keys, targets.script_types());
+
+                let lanes = raw_keys.iter().zip(&public_keys).zip(hashes);
+                for (lane, ((raw_key, public_key), hashes)) in lanes.enumerate() {
+                    if let Some(matched) =
+                        find_match(raw_key, public_key, &hashes, targets, symmetry)?
+                    {
+                        let mut slot = reports.lock().unwrap_or_else(|e| e.into_inner());
+                        slot.push(matched);
+                    }
+                    if proof.is_distinguished(&hashes.compressed) {
+                        let mut keys = distinguished.lock().unwrap_or_else(|e| e.into_inner());
+                        keys.push((group * MAX_LANES + lane, hashes.compressed.to_vec()));
+                    }
+                }
+                Ok(())
+            },
+        )?;
+
+        let mut matches = reports.into_inner().unwrap_or_else(|e| e.into_inner());
+        matches.sort_by(|a, b| a.hex_key.cmp(&b.hex_key));
+        if matches.iter().any(|m| m.action == MatchAction::Stop) {
+            meta.proof = Some(proof);
+            return Ok(ChunkOutcome::Matched(matches));
+        }
+
+        let mut distinguished = distinguished
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner());
+        distinguished.sort();
+        for (index, hash) in distinguished {
+            let position = batch_position.to_u64().unwrap_or(u64::MAX);
+            proof.record(position.saturating_add(index as u64), &hash);
+        }
+
+        stats.batches += 1;
+        stats.keys += batch.len() as u64;
+        meta.last_processed_hex = generator.last_key();
+        meta.proof = Some(proof.clone());
+        meta.save(path)?;
+
+        on_batch(&BatchProgress {
+            meta,
+            matches: &matches,
+            batch_start_hex: &batch_start_hex,
+            batch_keys: batch.len(),
+            elapsed: batch_start.elapsed(),
+            stats,
+        });
+    }
+
+    meta.proof = Some(proof);
+    meta.status = ChunkStatus::Finished;
+    meta.save(path)?;
+    stats.chunks += 1;
+    Ok(ChunkOutcome::Finished)
+}
+
+/// The hashes of a lane group of public keys, batched through the hash
+/// backend; hashes of script types outside `types` are left zeroed
+fn hash_lanes(public_keys: &[PublicKey], types: ScriptTypes) -> [KeyHashes; MAX_LANES] {
+    let count = public_keys.len();
+    let mut hashes = [KeyHashes::default(); MAX_LANES];
+    let mut digests = [[0u8; 20]; MAX_LANES];
+
+    let mut compressed = [[0u8; 33]; MAX_LANES];
+    for (out, public_key) in compressed.iter_mut().zip(public_keys) {
+        *out = public_key.serialize();
+    }
+    hash160_batch(&compressed[..count], &mut digests[..count]);
+    for (hashes, digest) in hashes.iter_mut().zip(&digests) {
+        hashes.compressed = *digest;
+    }
+
+    if types.contains(ScriptType::P2pkhUncompressed) {
+        let mut uncompressed = [[0u8; 65]; MAX_LANES];
+        for (out, public_key) in uncompressed.iter_mut().zip(public_keys) {
+            *out = public_key.serialize_uncompressed();
+        }
+        hash160_batch(&uncompressed[..count], &mut digests[..count]);
+        for (hashes, digest) in hashes.iter_mut().zip(&digests) {
+            hashes.uncompressed = *digest;
+        }
+    }
+
+    if types.contains(ScriptType::P2shP2wpkh) {
+        let mut scripts = [[0u8; 22]; MAX_LANES];
+        for (out, hashes) in scripts.iter_mut().zip(&hashes) {
+            *out = nested_redeem_script(&hashes.compressed);
+        }
+        hash160_batch(&scripts[..count], &mut digests[..count]);
+        for (hashes, digest) in hashes.iter_mut().zip(&digests) {
+            hashes.nested = *digest;
+        }
+    }
+    hashes
+}
+
+/// Check a key, and the symmetric keys of its point, against the targets
+fn find_match(
+    raw_key: &[u8; 32],
+    public_key: &PublicKey,
+    hashes: &KeyHashes,
+    targets: &TargetSet,
+    symmetry: Symmetry,
+) -> Result<Option<MatchReport>> {
+    let (network, types) = (targets.network(), targets.script_types());
+    let derived = derive_with_hashes(public_key, hashes, network, types)?;
+    if let Some(report) = match_in(raw_key, Variant::IDENTITY, &derived, targets) {
+        return Ok(Some(report));
+    }
+    for variant in &symmetry.variants()[1..] {
+        let derived = derive(&variant.point(public_key)?, network, types)?;
+        if let Some(report) = match_in(raw_key, *variant, &derived, targets) {
+            return Ok(Some(report));
+        }
+    }
+    Ok(None)
+}
+
+/// The report for the first target among the addresses of a variant
+fn match_in(
+    raw_key: &[u8; 32],
+    variant: Variant,
+    derived: &[DerivedAddress],
+    targets: &TargetSet,
+) -> Option<MatchReport> {
+    let address = derived.iter().find(|a| targets.contains(a))?;
+    let key = variant.private_key(raw_key);
+    Some(MatchReport::new(&key, address).in_groups(targets.groups_containing(derived)))
+}
+
+/// Parse a duration such as `90`, `90s`, `30m`, `2h` or `1d`
+pub fn parse_duration(value: &str) -> std::result::Result<Duration, String> {
+    let value = value.trim();
+    let (digits, unit) = value
+        .find(|c: char| !c.is_ascii_digit())
+        .map(|i| value.split_at(i))
+        .unwrap_or((value, "s"));
+    let amount: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", value))?;
+    let scale: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => return Err(format!("invalid duration unit in '{}'", value)),
+    };
+    let seconds = amount
+        .checked_mul(scale)
+        .ok_or_else(|| format!("invalid duration '{}'", value))?;
+    Ok(Duration::from_secs(seconds))
+}
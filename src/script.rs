@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// An output type a public key is derived into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ScriptType {
+    P2pkhCompressed,
+    P2pkhUncompressed,
+    P2shP2wpkh,
+    P2wpkh,
+    P2tr,
+    /// Bare public key output with the compressed SEC encoding
+    P2pkCompressed,
+    /// Bare public key output with the uncompressed SEC encoding
+    P2pkUncompressed,
+}
+
+impl ScriptType {
+    /// Every script type, in the order keys are checked
+    pub const ALL: [ScriptType; 7] = [
+        ScriptType::P2pkhCompressed,
+        ScriptType::P2pkhUncompressed,
+        ScriptType::P2shP2wpkh,
+        ScriptType::P2wpkh,
+        ScriptType::P2tr,
+        ScriptType::P2pkCompressed,
+        ScriptType::P2pkUncompressed,
+    ];
+
+    /// Short name used in config files, on the command line and in chunk files
+    pub fn name(self) -> &'static str {
+        match self {
+            ScriptType::P2pkhCompressed => "p2pkh-c",
+            ScriptType::P2pkhUncompressed => "p2pkh-u",
+            ScriptType::P2shP2wpkh => "p2sh-p2wpkh",
+            ScriptType::P2wpkh => "p2wpkh",
+            ScriptType::P2tr => "p2tr",
+            ScriptType::P2pkCompressed => "p2pk-c",
+            ScriptType::P2pkUncompressed => "p2pk-u",
+        }
+    }
+
+    /// Label shown in match reports
+    pub fn label(self) -> &'static str {
+        match self {
+            ScriptType::P2pkhCompressed => "P2PKH compressed",
+            ScriptType::P2pkhUncompressed => "P2PKH uncompressed",
+            ScriptType::P2shP2wpkh => "P2SH",
+            ScriptType::P2wpkh => "Bech32 (P2WPKH)",
+            ScriptType::P2tr => "P2TR",
+            ScriptType::P2pkCompressed => "P2PK compressed",
+            ScriptType::P2pkUncompressed => "P2PK uncompressed",
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+impl fmt::Display for ScriptType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ScriptType {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        ScriptType::ALL
+            .into_iter()
+            .find(|t| t.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<_> = ScriptType::ALL.iter().map(|t| t.name()).collect();
+                format!(
+                    "unknown script type '{}', expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// A set of script types to derive and check.
+///
+/// Chunk and coverage files store it as a list of names. Files written
+/// before the set was recorded checked [`ScriptTypes::DEFAULT`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct ScriptTypes(u8);
+
+impl ScriptTypes {
+    /// The four types derived before the set became selectable
+    pub const DEFAULT: ScriptTypes = ScriptTypes(0b01111);
+
+    pub const ALL: ScriptTypes = ScriptTypes(0b111_1111);
+
+    pub const EMPTY: ScriptTypes = ScriptTypes(0);
+
+    pub fn contains(self, script: ScriptType) -> bool {
+        self.0 & script.bit() != 0
+    }
+
+    pub fn with(self, script: ScriptType) -> Self {
+        Self(self.0 | script.bit())
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether every type of `other` is also in this set
+    pub fn is_superset(self, other: ScriptTypes) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersection(self, other: ScriptTypes) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// The types of the set in checking order
+    pub fn iter(self) -> impl Iterator<Item = ScriptType> {
+        ScriptType::ALL
+            .into_iter()
+            .filter(move |t| self.contains(*t))
+    }
+
+    fn non_empty(self) -> std::result::Result<Self, String> {
+        if self.is_empty() {
+            Err("at least one script type is required".to_string())
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Default for ScriptTypes {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl FromIterator<ScriptType> for ScriptTypes {
+    fn from_iter<I: IntoIterator<Item = ScriptType>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::EMPTY, Self::with)
+    }
+}
+
+impl fmt::Display for ScriptTypes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self.iter().map(ScriptType::name).collect();
+        f.write_str(&names.join(","))
+    }
+}
+
+impl fmt::Debug for ScriptTypes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ScriptTypes({})", self)
+    }
+}
+
+impl FromStr for ScriptTypes {
+    type Err = String;
+
+    /// Parse a comma-separated list such as `p2pkh-c,p2tr`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        s.split(',')
+            .filter(|name| !name.trim().is_empty())
+            .map(str::parse)
+            .collect::<std::result::Result<ScriptTypes, _>>()?
+            .non_empty()
+    }
+}
+
+impl TryFrom<Vec<String>> for ScriptTypes {
+    type Error = String;
+
+    fn try_from(names: Vec<String>) -> std::result::Result<Self, Self::Error> {
+        names
+            .iter()
+            .map(|name| name.parse())
+            .collect::<std::result::Result<ScriptTypes, _>>()?
+            .non_empty()
+    }
+}
+
+impl From<ScriptTypes> for Vec<String> {
+    fn from(types: ScriptTypes) -> Self {
+        types.iter().map(|t| t.name().to_string()).collect()
+    }
+}
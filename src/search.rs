@@ -1,13 +1,450 @@
+use crate::address::DerivedAddress;
+use crate::codec::{Address, Network, P2pk};
+use crate::config::TargetFile;
+use crate::error::{Error, Result};
+use crate::script::{ScriptType, ScriptTypes};
+use crate::symmetry::Symmetry;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::str::FromStr;
 
-pub fn load_sorted_addresses(path: &str) -> Vec<String> {
-    let file =
-        File::open(path).unwrap_or_else(|e| panic!("Cannot open address file '{}': {:?}", path, e));
+/// Label of the group built from a plain address list
+pub const DEFAULT_LABEL: &str = "targets";
+
+pub fn load_sorted_addresses(path: &str) -> Result<Vec<String>> {
+    let file = File::open(path).map_err(|e| Error::io(path, e))?;
     let reader = BufReader::new(file);
-    reader.lines().map(|l| l.unwrap()).collect()
+    reader
+        .lines()
+        .collect::<std::io::Result<Vec<String>>>()
+        .map_err(|e| Error::io(path, e))
 }
 
 pub fn binary_search(sorted: &[String], key: &str) -> bool {
     sorted.binary_search(&key.to_string()).is_ok()
 }
+
+/// What a scan does after a key matches a target set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchAction {
+    /// Report the match and end the scan
+    #[default]
+    Stop,
+    /// Record the match and keep scanning
+    Continue,
+    /// Record and announce the match, and keep scanning
+    Notify,
+}
+
+impl MatchAction {
+    pub fn name(self) -> &'static str {
+        match self {
+            MatchAction::Stop => "stop",
+            MatchAction::Continue => "continue",
+            MatchAction::Notify => "notify",
+        }
+    }
+}
+
+impl fmt::Display for MatchAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for MatchAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        [
+            MatchAction::Stop,
+            MatchAction::Continue,
+            MatchAction::Notify,
+        ]
+        .into_iter()
+        .find(|a| a.name().eq_ignore_ascii_case(s.trim()))
+        .ok_or_else(|| {
+            format!(
+                "unknown match action '{}', expected stop, continue or notify",
+                s
+            )
+        })
+    }
+}
+
+/// Targets indexed by the hash or output key they pay to
+#[derive(Debug, Clone, Default)]
+struct TargetIndex {
+    pubkey_hashes: Vec<[u8; 20]>,
+    script_hashes: Vec<[u8; 20]>,
+    witness_hashes: Vec<[u8; 20]>,
+    taproot_keys: Vec<[u8; 32]>,
+    public_keys: Vec<Vec<u8>>,
+}
+
+impl TargetIndex {
+    /// Index a target, returning false when no derived key can match it
+    fn insert(&mut self, target: Target) -> bool {
+        match target {
+            Target::Address(Address::P2pkh { hash, .. }) => self.pubkey_hashes.push(hash),
+            Target::Address(Address::P2sh { hash, .. }) => self.script_hashes.push(hash),
+            Target::Address(Address::P2wpkh { hash, .. }) => self.witness_hashes.push(hash),
+            Target::Address(Address::P2tr { output_key, .. }) => self.taproot_keys.push(output_key),
+            Target::P2pk(p2pk) => self.public_keys.push(p2pk.key),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Sort and de-duplicate the index once every target is inserted
+    fn finish(&mut self) {
+        for index in [
+            &mut self.pubkey_hashes,
+            &mut self.script_hashes,
+            &mut self.witness_hashes,
+        ] {
+            index.sort_unstable();
+            index.dedup();
+        }
+        self.taproot_keys.sort_unstable();
+        self.taproot_keys.dedup();
+        self.public_keys.sort_unstable();
+        self.public_keys.dedup();
+    }
+
+    fn contains(&self, script: ScriptType, payload: &[u8]) -> bool {
+        let index = match script {
+            ScriptType::P2pkhCompressed | ScriptType::P2pkhUncompressed => &self.pubkey_hashes,
+            ScriptType::P2shP2wpkh => &self.script_hashes,
+            ScriptType::P2wpkh => &self.witness_hashes,
+            ScriptType::P2tr => {
+                return self
+                    .taproot_keys
+                    .binary_search_by(|key| key[..].cmp(payload))
+                    .is_ok();
+            }
+            ScriptType::P2pkCompressed | ScriptType::P2pkUncompressed => {
+                return self
+                    .public_keys
+                    .binary_search_by(|key| key[..].cmp(payload))
+                    .is_ok();
+            }
+        };
+        index.binary_search_by(|hash| hash[..].cmp(payload)).is_ok()
+    }
+}
+
+/// One labelled list of targets within a [`TargetSet`]
+#[derive(Debug, Clone)]
+pub struct TargetGroup {
+    label: String,
+    priority: i32,
+    action: MatchAction,
+    index: TargetIndex,
+    len: usize,
+}
+
+impl TargetGroup {
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Groups with a higher priority are listed first in match reports
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    pub fn action(&self) -> MatchAction {
+        self.action
+    }
+
+    /// Number of addresses loaded into the group
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// The addresses a scan matches against and the script types derived for
+/// them.
+///
+/// Targets are decoded and indexed by the hash or output key they pay to,
+/// so derived addresses are compared as raw bytes and never encoded unless
+/// they match. Bare P2PK targets are hex SEC public keys and are compared
+/// with the derived key directly. Entries no derived key can produce, such
+/// as P2WSH outputs or malformed lines, are counted but not indexed.
+///
+/// Targets come in labelled groups. Every key is checked against one index
+/// of all groups, and only a match is looked up in the groups themselves.
+#[derive(Debug, Clone, Default)]
+pub struct TargetSet {
+    index: TargetIndex,
+    groups: Vec<TargetGroup>,
+    len: usize,
+    unmatchable: usize,
+    script_types: ScriptTypes,
+    network: Network,
+    symmetry: Symmetry,
+}
+
+impl TargetSet {
+    /// Targets from a list of mainnet addresses, checked for the default
+    /// script types
+    pub fn new<S: AsRef<str>>(addresses: impl IntoIterator<Item = S>) -> Self {
+        Self::for_network(Network::Mainnet, addresses)
+    }
+
+    /// Targets from a list of addresses on `network`, in one group labelled
+    /// [`DEFAULT_LABEL`]. Addresses of other networks are counted as
+    /// unmatchable.
+    pub fn for_network<S: AsRef<str>>(
+        network: Network,
+        addresses: impl IntoIterator<Item = S>,
+    ) -> Self {
+        Self::empty(network).with_group(DEFAULT_LABEL, 0, MatchAction::Stop, addresses)
+    }
+
+    /// A set without targets on `network`
+    pub fn empty(network: Network) -> Self {
+        Self {
+            network,
+            ..Self::default()
+        }
+    }
+
+    /// Add a labelled group of addresses on the set's network
+    pub fn with_group<S: AsRef<str>>(
+        mut self,
+        label: &str,
+        priority: i32,
+        action: MatchAction,
+        addresses: impl IntoIterator<Item = S>,
+    ) -> Self {
+        let mut group = TargetGroup {
+            label: label.to_string(),
+            priority,
+            action,
+            index: TargetIndex::default(),
+            len: 0,
+        };
+        for address in addresses {
+            group.len += 1;
+            match parse_target(address.as_ref(), self.network) {
+                Ok(target) if group.index.insert(target.clone()) => {
+                    self.index.insert(target);
+                }
+                _ => self.unmatchable += 1,
+            }
+        }
+        group.index.finish();
+        self.index.finish();
+        self.len += group.len;
+        let at = self.groups.partition_point(|g| g.priority >= priority);
+        self.groups.insert(at, group);
+        self
+    }
+
+    /// Load an address file of `network` addresses, labelled by the file name
+    pub fn load(path: &str, network: Network) -> Result<Self> {
+        let file = TargetFile {
+            label: None,
+            file: path.to_string(),
+            priority: 0,
+            action: MatchAction::Stop,
+        };
+        Self::load_files(&[file], network)
+    }
+
+    /// Load one labelled group per address file
+    pub fn load_files(files: &[TargetFile], network: Network) -> Result<Self> {
+        let mut targets = Self::empty(network);
+        for file in files {
+            targets = targets.with_group(
+                &file.label(),
+                file.priority,
+                file.action,
+                load_sorted_addresses(&file.file)?,
+            );
+        }
+        Ok(targets)
+    }
+
+    /// Derive and check only these script types
+    pub fn with_script_types(mut self, script_types: ScriptTypes) -> Self {
+        self.script_types = script_types;
+        self
+    }
+
+    pub fn script_types(&self) -> ScriptTypes {
+        self.script_types
+    }
+
+    /// Also check the symmetric keys of every scanned key
+    pub fn with_symmetry(mut self, symmetry: Symmetry) -> Self {
+        self.symmetry = symmetry;
+        self
+    }
+
+    pub fn symmetry(&self) -> Symmetry {
+        self.symmetry
+    }
+
+    /// Network keys are derived on
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Number of addresses loaded
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of loaded entries that no derived key can match
+    pub fn unmatchable(&self) -> usize {
+        self.unmatchable
+    }
+
+    /// Indexed targets that no type of `script_types` can match, counted by
+    /// the script type that would
+    pub fn unreachable(&self, script_types: ScriptTypes) -> Vec<(ScriptType, usize)> {
+        let index = &self.index;
+        let compressed = index.public_keys.iter().filter(|k| k.len() == 33).count();
+        let pubkey_hashes = if script_types.contains(ScriptType::P2pkhUncompressed) {
+            0
+        } else {
+            index.pubkey_hashes.len()
+        };
+        [
+            (ScriptType::P2pkhCompressed, pubkey_hashes),
+            (ScriptType::P2shP2wpkh, index.script_hashes.len()),
+            (ScriptType::P2wpkh, index.witness_hashes.len()),
+            (ScriptType::P2tr, index.taproot_keys.len()),
+            (ScriptType::P2pkCompressed, compressed),
+            (
+                ScriptType::P2pkUncompressed,
+                index.public_keys.len() - compressed,
+            ),
+        ]
+        .into_iter()
+        .filter(|&(script, count)| count > 0 && !script_types.contains(script))
+        .collect()
+    }
+
+    /// The groups, highest priority first
+    pub fn groups(&self) -> &[TargetGroup] {
+        &self.groups
+    }
+
+    pub fn contains(&self, derived: &DerivedAddress) -> bool {
+        derived.network == self.network && self.index.contains(derived.script, derived.payload())
+    }
+
+    /// The groups holding any of `derived`, highest priority first
+    pub fn groups_containing<'a>(
+        &'a self,
+        derived: &'a [DerivedAddress],
+    ) -> impl Iterator<Item = &'a TargetGroup> + 'a {
+        self.groups.iter().filter(move |group| {
+            derived.iter().any(|address| {
+                address.network == self.network
+                    && group.index.contains(address.script, address.payload())
+            })
+        })
+    }
+}
+
+/// An entry of the address database
+#[derive(Clone)]
+enum Target {
+    Address(Address),
+    P2pk(P2pk),
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Address(address) => address.fmt(f),
+            Target::P2pk(p2pk) => p2pk.fmt(f),
+        }
+    }
+}
+
+/// Parse an address of `network`, or a hex public key for a P2PK output
+fn parse_target(entry: &str, network: Network) -> Result<Target> {
+    let is_key = matches!(entry.len(), 66 | 130) && entry.bytes().all(|b| b.is_ascii_hexdigit());
+    if is_key {
+        Ok(Target::P2pk(entry.parse()?))
+    } else {
+        Ok(Target::Address(Address::parse_for(entry, network)?))
+    }
+}
+
+/// Index of the first entry that is not strictly greater than its predecessor
+pub fn first_unsorted(sorted: &[String]) -> Option<usize> {
+    sorted.windows(2).position(|w| w[0] >= w[1]).map(|i| i + 1)
+}
+
+/// Outcome of building an address database
+#[derive(Debug, Default)]
+pub struct AddressDbReport {
+    /// Unique addresses written
+    pub written: usize,
+    /// Malformed entries as `(file:line, reason)`
+    pub rejected: Vec<(String, String)>,
+}
+
+/// Build a sorted, de-duplicated address file from raw address lists.
+///
+/// Only the first column of each line is used, so CSV exports can be fed in
+/// directly. Blank lines and `#` comments are skipped. Entries that are
+/// neither an address of `network` nor a hex public key of a P2PK output are
+/// left out and reported. Bech32 addresses and public keys are stored
+/// lowercase so they match derived ones.
+pub fn build_address_database(
+    inputs: &[String],
+    output: &str,
+    network: Network,
+) -> Result<AddressDbReport> {
+    let mut report = AddressDbReport::default();
+    let mut addresses = Vec::new();
+    for input in inputs {
+        let file = File::open(input).map_err(|e| Error::io(input, e))?;
+        for (n, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| Error::io(input, e))?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some(entry) = line.split([',', ';', '\t', ' ']).next() else {
+                continue;
+            };
+            match parse_target(entry, network) {
+                Ok(target) => addresses.push(target.to_string()),
+                Err(e) => report
+                    .rejected
+                    .push((format!("{}:{}", input, n + 1), e.to_string())),
+            }
+        }
+    }
+    addresses.sort_unstable();
+    addresses.dedup();
+
+    let file = File::create(output).map_err(|e| Error::io(output, e))?;
+    let mut writer = BufWriter::new(file);
+    for addr in &addresses {
+        writeln!(writer, "{}", addr).map_err(|e| Error::io(output, e))?;
+    }
+    writer.flush().map_err(|e| Error::io(output, e))?;
+    report.written = addresses.len();
+    Ok(report)
+}
@@ -0,0 +1,156 @@
+use crate::chunk::max_private_key;
+use crate::error::{Error, Result};
+use crate::puzzles::PuzzleRange;
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{ToPrimitive, Zero};
+use rand::thread_rng;
+use std::fmt;
+use std::str::FromStr;
+
+/// How a chunk-id space is divided between workers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShardLayout {
+    /// Worker `i` takes ids `i`, `i + N`, `i + 2N`, ...
+    #[default]
+    Interleaved,
+    /// Worker `i` takes the `i`-th of `N` equal consecutive blocks
+    Contiguous,
+}
+
+impl fmt::Display for ShardLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShardLayout::Interleaved => f.write_str("interleaved"),
+            ShardLayout::Contiguous => f.write_str("contiguous"),
+        }
+    }
+}
+
+impl FromStr for ShardLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "interleaved" => Ok(ShardLayout::Interleaved),
+            "contiguous" => Ok(ShardLayout::Contiguous),
+            _ => Err(format!("unknown shard layout '{}'", s)),
+        }
+    }
+}
+
+/// Worker `index` of `count`, owning a fixed part of the chunk-id space.
+///
+/// The rule only depends on `index`, `count` and the size of the id space,
+/// so workers that never talk to each other still never overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    pub index: u64,
+    pub count: u64,
+    pub layout: ShardLayout,
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{} ({})", self.index, self.count, self.layout)
+    }
+}
+
+impl Shard {
+    /// Parse `i/N` with `0 <= i < N`
+    pub fn parse(spec: &str, layout: ShardLayout) -> Result<Self> {
+        let invalid = || Error::InvalidRange(format!("'{}' is not a worker spec like 0/4", spec));
+        let (index, count) = spec.trim().split_once('/').ok_or_else(invalid)?;
+        let index: u64 = index.trim().parse().map_err(|_| invalid())?;
+        let count: u64 = count.trim().parse().map_err(|_| invalid())?;
+        if count == 0 || index >= count {
+            return Err(Error::InvalidRange(format!(
+                "worker {} is outside 0..{}",
+                index, count
+            )));
+        }
+        Ok(Self {
+            index,
+            count,
+            layout,
+        })
+    }
+
+    /// First id and length of this shard's block of a contiguous layout
+    fn block(&self, total: &BigUint) -> (BigUint, BigUint) {
+        let start = total * self.index / self.count;
+        let end = total * (self.index + 1) / self.count;
+        let len = &end - &start;
+        (start, len)
+    }
+
+    /// Number of ids this shard owns out of `total`
+    pub fn chunk_count(&self, total: &BigUint) -> BigUint {
+        match self.layout {
+            ShardLayout::Interleaved => {
+                if *total <= BigUint::from(self.index) {
+                    BigUint::zero()
+                } else {
+                    (total - self.index - 1u32) / self.count + 1u32
+                }
+            }
+            ShardLayout::Contiguous => self.block(total).1,
+        }
+    }
+
+    /// The `k`-th id owned by this shard, counting from zero
+    pub fn nth_chunk(&self, k: &BigUint, total: &BigUint) -> BigUint {
+        match self.layout {
+            ShardLayout::Interleaved => k * self.count + self.index,
+            ShardLayout::Contiguous => self.block(total).0 + k,
+        }
+    }
+
+    pub fn owns(&self, chunk_id: &BigUint, total: &BigUint) -> bool {
+        if chunk_id >= total {
+            return false;
+        }
+        match self.layout {
+            ShardLayout::Interleaved => (chunk_id % self.count).to_u64() == Some(self.index),
+            ShardLayout::Contiguous => {
+                let (start, len) = self.block(total);
+                *chunk_id >= start && *chunk_id < start + len
+            }
+        }
+    }
+
+    /// Check that an explicitly requested chunk belongs to this shard
+    pub fn check_owns(&self, chunk_id: &BigUint, total: &BigUint) -> Result<()> {
+        if self.owns(chunk_id, total) {
+            Ok(())
+        } else {
+            Err(Error::InvalidRange(format!(
+                "chunk {} belongs to another worker than {}",
+                chunk_id, self
+            )))
+        }
+    }
+}
+
+/// Number of chunk ids `random_chunk_id_within_range` or `random_chunk_id`
+/// can pick from
+pub fn chunk_id_space(chunk_size: &BigUint, puzzle_range: Option<&PuzzleRange>) -> BigUint {
+    match puzzle_range {
+        Some(range) => (&range.end - &range.start) / chunk_size,
+        None => max_private_key() / chunk_size,
+    }
+}
+
+/// Pick a random chunk id owned by `shard`
+pub fn random_chunk_id_in_shard(
+    chunk_size: &BigUint,
+    puzzle_range: Option<&PuzzleRange>,
+    shard: &Shard,
+) -> Result<BigUint> {
+    let total = chunk_id_space(chunk_size, puzzle_range);
+    let owned = shard.chunk_count(&total);
+    if owned.is_zero() {
+        return Err(Error::EmptyRange);
+    }
+    let k = thread_rng().gen_biguint_below(&owned);
+    Ok(shard.nth_chunk(&k, &total))
+}
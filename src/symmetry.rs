@@ -0,0 +1,201 @@
+use crate::error::{Error, Result};
+use num_bigint::BigUint;
+use num_traits::One;
+use secp256k1::{PublicKey, Secp256k1, VerifyOnly};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Order of the secp256k1 group
+const ORDER_HEX: &str = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+
+/// Prime of the secp256k1 field
+const PRIME_HEX: &str = "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f";
+
+/// Cube root of unity modulo the order: λ·(x, y) = (β·x, y)
+const LAMBDA_HEX: &str = "5363ad4cc05c30e0a5261c028812645a122e22ea20816678df02967c1b23bd72";
+
+/// Cube root of unity modulo the field prime matching `LAMBDA_HEX`
+const BETA_HEX: &str = "7ae96a2b657c07106e64479eac3434e99cf0497512f58995c1396c28719501ee";
+
+struct Constants {
+    order: BigUint,
+    prime: BigUint,
+    lambda: BigUint,
+    beta: BigUint,
+}
+
+fn context() -> &'static Secp256k1<VerifyOnly> {
+    static CONTEXT: OnceLock<Secp256k1<VerifyOnly>> = OnceLock::new();
+    CONTEXT.get_or_init(Secp256k1::verification_only)
+}
+
+fn constants() -> &'static Constants {
+    static CONSTANTS: OnceLock<Constants> = OnceLock::new();
+    CONSTANTS.get_or_init(|| {
+        let parse = |hex| BigUint::parse_bytes(hex, 16).unwrap_or_default();
+        Constants {
+            order: parse(ORDER_HEX.as_bytes()),
+            prime: parse(PRIME_HEX.as_bytes()),
+            lambda: parse(LAMBDA_HEX.as_bytes()),
+            beta: parse(BETA_HEX.as_bytes()),
+        }
+    })
+}
+
+/// Order of the secp256k1 group; private keys run from 1 to `order - 1`
+pub fn curve_order() -> &'static BigUint {
+    &constants().order
+}
+
+/// Which keys besides the scanned one are checked for every computed point.
+///
+/// Negating a point gives the key `n - k` and multiplying its x coordinate
+/// by β gives `λ·k`, so `full` checks six keys for one point computation.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Symmetry {
+    /// Only the scanned key
+    #[default]
+    Off,
+    /// `k` and `n - k`
+    Negation,
+    /// `k`, `λk` and `λ²k`, each with its negation
+    Full,
+}
+
+impl Symmetry {
+    pub fn name(self) -> &'static str {
+        match self {
+            Symmetry::Off => "off",
+            Symmetry::Negation => "negation",
+            Symmetry::Full => "full",
+        }
+    }
+
+    /// The variants checked for every scanned key, the key itself first
+    pub fn variants(self) -> &'static [Variant] {
+        const ALL: [Variant; 6] = [
+            Variant::new(0, false),
+            Variant::new(0, true),
+            Variant::new(1, false),
+            Variant::new(1, true),
+            Variant::new(2, false),
+            Variant::new(2, true),
+        ];
+        match self {
+            Symmetry::Off => &ALL[..1],
+            Symmetry::Negation => &ALL[..2],
+            Symmetry::Full => &ALL,
+        }
+    }
+
+    /// Keys checked per computed point
+    pub fn keys_per_point(self) -> usize {
+        self.variants().len()
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        [Symmetry::Off, Symmetry::Negation, Symmetry::Full]
+            .into_iter()
+            .find(|m| m.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("unknown symmetry '{}', expected off, negation or full", s))
+    }
+}
+
+/// A key related to a scanned key `k`: `λ^power·k`, negated when `negated`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Variant {
+    pub power: u8,
+    pub negated: bool,
+}
+
+impl Variant {
+    /// The scanned key itself
+    pub const IDENTITY: Variant = Variant::new(0, false);
+
+    pub const fn new(power: u8, negated: bool) -> Self {
+        Self {
+            power: power % 3,
+            negated,
+        }
+    }
+
+    /// The private key of this variant of `key`, modulo the group order
+    pub fn apply(self, key: &BigUint) -> BigUint {
+        let order = curve_order();
+        let mut value = key % order;
+        for _ in 0..self.power {
+            value = value * &constants().lambda % order;
+        }
+        if self.negated && value != BigUint::default() {
+            value = order - value;
+        }
+        value
+    }
+
+    /// The private key of this variant of a raw key
+    pub fn private_key(self, raw_key: &[u8; 32]) -> [u8; 32] {
+        let value = self.apply(&BigUint::from_bytes_be(raw_key)).to_bytes_be();
+        let mut key = [0u8; 32];
+        key[32 - value.len()..].copy_from_slice(&value);
+        key
+    }
+
+    /// The point of this variant, from the scanned key's point, using only
+    /// field operations
+    pub fn point(self, public_key: &PublicKey) -> Result<PublicKey> {
+        let mut point = *public_key;
+        if self.power > 0 {
+            let Constants { prime, beta, .. } = constants();
+            let mut serialized = public_key.serialize_uncompressed();
+            let mut x = BigUint::from_bytes_be(&serialized[1..33]);
+            for _ in 0..self.power {
+                x = x * beta % prime;
+            }
+            let x = x.to_bytes_be();
+            serialized[1..33].fill(0);
+            serialized[33 - x.len()..33].copy_from_slice(&x);
+            point = PublicKey::from_slice(&serialized)
+                .map_err(|e| Error::Encoding(format!("symmetric point: {}", e)))?;
+        }
+        if self.negated {
+            // -P keeps x and flips the parity of y, the 0x02/0x03 prefix
+            point = point.negate(context());
+        }
+        Ok(point)
+    }
+}
+
+/// The points of every variant a symmetry checks, the scanned key first
+pub fn symmetric_points(
+    public_key: &PublicKey,
+    symmetry: Symmetry,
+) -> Result<Vec<(Variant, PublicKey)>> {
+    symmetry
+        .variants()
+        .iter()
+        .map(|variant| Ok((*variant, variant.point(public_key)?)))
+        .collect()
+}
+
+/// The keys `n - k` of the valid keys `k` in `start..=end`, as a range
+pub fn mirror(start: &BigUint, end: &BigUint) -> Option<(BigUint, BigUint)> {
+    let order = curve_order();
+    let start = start.max(&BigUint::one()).clone();
+    let end = end.min(&(order - BigUint::one())).clone();
+    (start <= end).then(|| (order - end, order - start))
+}
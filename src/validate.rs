@@ -0,0 +1,246 @@
+use crate::chunk::{ChunkMetadata, chunk_id_from_filename, parse_chunk_id};
+use crate::error::{Error, Result};
+use crate::puzzles::PuzzleRange;
+use num_bigint::BigUint;
+use serde::Serialize;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const QUARANTINE_FOLDER: &str = "quarantine";
+pub const QUARANTINE_REPORT: &str = "report.jsonl";
+
+/// A problem found in a chunk metadata file
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChunkIssue {
+    Unreadable(String),
+    InvalidJson(String),
+    InvalidChunkId(String),
+    ChunkIdMismatch {
+        file_id: BigUint,
+        meta_id: String,
+    },
+    BadHexWidth {
+        field: &'static str,
+        len: usize,
+    },
+    InvalidHex {
+        field: &'static str,
+    },
+    StartAfterEnd,
+    LastProcessedOutOfRange,
+    /// The walk does not fit the range, or `last_processed_hex` is not on it
+    InvalidWalk(String),
+    OutsidePuzzleRange,
+}
+
+impl fmt::Display for ChunkIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkIssue::Unreadable(e) => write!(f, "unreadable file: {}", e),
+            ChunkIssue::InvalidJson(e) => write!(f, "invalid JSON: {}", e),
+            ChunkIssue::InvalidChunkId(id) => write!(f, "invalid chunk id '{}'", id),
+            ChunkIssue::ChunkIdMismatch { file_id, meta_id } => write!(
+                f,
+                "file name says chunk {} but metadata says '{}'",
+                file_id, meta_id
+            ),
+            ChunkIssue::BadHexWidth { field, len } => {
+                write!(f, "{} has {} hex digits, expected 64", field, len)
+            }
+            ChunkIssue::InvalidHex { field } => write!(f, "{} is not valid hex", field),
+            ChunkIssue::StartAfterEnd => write!(f, "start_hex is greater than end_hex"),
+            ChunkIssue::LastProcessedOutOfRange => {
+                write!(f, "last_processed_hex is outside start_hex..=end_hex")
+            }
+            ChunkIssue::InvalidWalk(e) => write!(f, "invalid walk: {}", e),
+            ChunkIssue::OutsidePuzzleRange => {
+                write!(f, "chunk range lies outside the puzzle range")
+            }
+        }
+    }
+}
+
+/// Outcome of checking a chunk folder
+#[derive(Debug, Default)]
+pub struct FsckReport {
+    pub checked: usize,
+    pub broken: Vec<(PathBuf, Vec<ChunkIssue>)>,
+    pub quarantined: Vec<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct QuarantineEntry<'a> {
+    file: &'a str,
+    quarantined_at: u64,
+    issues: Vec<String>,
+}
+
+fn parse_key(field: &'static str, value: &str, issues: &mut Vec<ChunkIssue>) -> Option<BigUint> {
+    if value.len() != 64 {
+        issues.push(ChunkIssue::BadHexWidth {
+            field,
+            len: value.len(),
+        });
+        return None;
+    }
+    let parsed = BigUint::parse_bytes(value.as_bytes(), 16)
+        .filter(|_| value.bytes().all(|b| b.is_ascii_hexdigit()));
+    if parsed.is_none() {
+        issues.push(ChunkIssue::InvalidHex { field });
+    }
+    parsed
+}
+
+/// Check a chunk for internal consistency and, if given, against its puzzle range
+pub fn validate_chunk(
+    meta: &ChunkMetadata,
+    file_id: Option<&BigUint>,
+    puzzle_range: Option<&PuzzleRange>,
+) -> Vec<ChunkIssue> {
+    let mut issues = Vec::new();
+
+    match parse_chunk_id(&meta.chunk_id) {
+        Ok(id) => {
+            if let Some(file_id) = file_id.filter(|f| **f != id) {
+                issues.push(ChunkIssue::ChunkIdMismatch {
+                    file_id: file_id.clone(),
+                    meta_id: meta.chunk_id.clone(),
+                });
+            }
+        }
+        Err(_) => issues.push(ChunkIssue::InvalidChunkId(meta.chunk_id.clone())),
+    }
+
+    let start = parse_key("start_hex", &meta.start_hex, &mut issues);
+    let end = parse_key("end_hex", &meta.end_hex, &mut issues);
+    let last = parse_key("last_processed_hex", &meta.last_processed_hex, &mut issues);
+
+    if let (Some(start), Some(end)) = (&start, &end) {
+        if start > end {
+            issues.push(ChunkIssue::StartAfterEnd);
+        } else if let Some(last) = &last
+            && (last < start || last > end)
+        {
+            issues.push(ChunkIssue::LastProcessedOutOfRange);
+        } else if last.is_some()
+            && let Err(e) = meta.position()
+        {
+            issues.push(ChunkIssue::InvalidWalk(e.to_string()));
+        }
+        if let Some(range) = puzzle_range
+            && (*start < range.start || *end > range.end)
+        {
+            issues.push(ChunkIssue::OutsidePuzzleRange);
+        }
+    }
+
+    issues
+}
+
+/// Load and validate a chunk file, reporting parse failures as issues
+pub fn check_chunk_file(
+    path: &Path,
+    puzzle_range: Option<&PuzzleRange>,
+) -> (Option<ChunkMetadata>, Vec<ChunkIssue>) {
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => return (None, vec![ChunkIssue::Unreadable(e.to_string())]),
+    };
+    let meta = match serde_json::from_str::<ChunkMetadata>(&content) {
+        Ok(m) => m,
+        Err(e) => return (None, vec![ChunkIssue::InvalidJson(e.to_string())]),
+    };
+    let file_id = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .and_then(chunk_id_from_filename);
+    let issues = validate_chunk(&meta, file_id.as_ref(), puzzle_range);
+    (Some(meta), issues)
+}
+
+/// Move a broken chunk file into `<base>/quarantine` and append to the report.
+///
+/// A file quarantined earlier under the same name is kept, and the new one
+/// gets a `-N` suffix before its extension.
+pub fn quarantine_file(base_path: &str, path: &Path, issues: &[ChunkIssue]) -> Result<PathBuf> {
+    let folder = Path::new(base_path).join(QUARANTINE_FOLDER);
+    fs::create_dir_all(&folder).map_err(|e| Error::io(folder.to_string_lossy(), e))?;
+
+    let original = Path::new(path.file_name().unwrap_or_default());
+    let mut file_name = original.to_string_lossy().into_owned();
+    let stem = original.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = original
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut copy = 0;
+    while folder.join(&file_name).exists() {
+        copy += 1;
+        file_name = format!("{}-{}{}", stem, copy, extension);
+    }
+    let target = folder.join(&file_name);
+    fs::rename(path, &target).map_err(|e| Error::io(path.to_string_lossy(), e))?;
+
+    let report_path = folder.join(QUARANTINE_REPORT);
+    let entry = QuarantineEntry {
+        file: &file_name,
+        quarantined_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        issues: issues.iter().map(|i| i.to_string()).collect(),
+    };
+    let line =
+        serde_json::to_string(&entry).map_err(|e| Error::json(report_path.to_string_lossy(), e))?;
+    let mut report = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&report_path)
+        .map_err(|e| Error::io(report_path.to_string_lossy(), e))?;
+    writeln!(report, "{}", line).map_err(|e| Error::io(report_path.to_string_lossy(), e))?;
+
+    Ok(target)
+}
+
+/// List the `chunk_*.json` files directly inside a chunk folder
+pub fn chunk_files(base_path: &str) -> Result<Vec<PathBuf>> {
+    let entries = fs::read_dir(base_path).map_err(|e| Error::io(base_path, e))?;
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("chunk_") && n.ends_with(".json"))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Validate every chunk in a folder, quarantining broken files unless `dry_run`
+pub fn fsck(
+    base_path: &str,
+    puzzle_range: Option<&PuzzleRange>,
+    dry_run: bool,
+) -> Result<FsckReport> {
+    let mut report = FsckReport::default();
+    for path in chunk_files(base_path)? {
+        report.checked += 1;
+        let (_, issues) = check_chunk_file(&path, puzzle_range);
+        if issues.is_empty() {
+            continue;
+        }
+        if !dry_run {
+            report
+                .quarantined
+                .push(quarantine_file(base_path, &path, &issues)?);
+        }
+        report.broken.push((path, issues));
+    }
+    Ok(report)
+}
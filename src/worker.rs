@@ -0,0 +1,286 @@
+use crate::chunk::{ChunkMetadata, ChunkStatus, parse_chunk_id};
+use crate::error::{Error, Result};
+use crate::protocol::{Request, Response, send_request};
+use crate::scanner::MatchReport;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Requests waiting for the coordinator to come back, one JSON line each
+pub const OUTBOX_FILE: &str = "outbox.jsonl";
+
+/// Lease ids of the chunks held in the cache, keyed by chunk id
+pub const LEASES_FILE: &str = "leases.json";
+
+/// What the coordinator answered when asked for work
+#[derive(Debug)]
+pub enum LeaseOutcome {
+    Leased {
+        lease_id: u64,
+        chunk: ChunkMetadata,
+        path: String,
+    },
+    NoWork,
+    /// The coordinator could not be reached and no cached chunk is left
+    Offline,
+}
+
+/// Local copy of a worker's leased chunks and of the reports it could not
+/// deliver yet.
+///
+/// Leased chunks are stored as ordinary chunk files, so a worker scans and
+/// checkpoints them exactly like a local run does.
+pub struct WorkerCache {
+    folder: String,
+}
+
+impl WorkerCache {
+    pub fn open(folder: &str) -> Result<Self> {
+        fs::create_dir_all(folder).map_err(|e| Error::io(folder, e))?;
+        Ok(Self {
+            folder: folder.to_string(),
+        })
+    }
+
+    pub fn folder(&self) -> &str {
+        &self.folder
+    }
+
+    fn file(&self, name: &str) -> String {
+        format!("{}/{}", self.folder, name)
+    }
+
+    fn leases(&self) -> Result<BTreeMap<String, u64>> {
+        let path = self.file(LEASES_FILE);
+        if !Path::new(&path).exists() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(&path).map_err(|e| Error::io(&path, e))?;
+        serde_json::from_str(&content).map_err(|e| Error::json(&path, e))
+    }
+
+    fn save_leases(&self, leases: &BTreeMap<String, u64>) -> Result<()> {
+        let path = self.file(LEASES_FILE);
+        let content = serde_json::to_string_pretty(leases).map_err(|e| Error::json(&path, e))?;
+        fs::write(&path, content).map_err(|e| Error::io(&path, e))
+    }
+
+    /// Store a freshly leased chunk and return the path of its cached file
+    pub fn store_lease(&self, lease_id: u64, chunk: &ChunkMetadata) -> Result<String> {
+        let path = ChunkMetadata::path(&parse_chunk_id(&chunk.chunk_id)?, &self.folder);
+        chunk.save(&path)?;
+        let mut leases = self.leases()?;
+        leases.insert(chunk.chunk_id.clone(), lease_id);
+        self.save_leases(&leases)?;
+        Ok(path)
+    }
+
+    /// Drop a chunk once its final report is delivered or queued
+    pub fn forget(&self, chunk_id: &str) -> Result<()> {
+        let mut leases = self.leases()?;
+        leases.remove(chunk_id);
+        self.save_leases(&leases)?;
+        let path = ChunkMetadata::path(&parse_chunk_id(chunk_id)?, &self.folder);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Error::io(&path, e)),
+            _ => Ok(()),
+        }
+    }
+
+    /// A cached chunk left unfinished by an interrupted run, marked `processing`
+    pub fn resume(&self) -> Result<Option<(u64, ChunkMetadata, String)>> {
+        for (chunk_id, lease_id) in self.leases()? {
+            let path = ChunkMetadata::path(&parse_chunk_id(&chunk_id)?, &self.folder);
+            let mut chunk = ChunkMetadata::load(&path)?;
+            if !chunk.status.is_done() {
+                chunk.status = ChunkStatus::Processing;
+                chunk.save(&path)?;
+                return Ok(Some((lease_id, chunk, path)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Requests queued while the coordinator was unreachable, oldest first
+    pub fn outbox(&self) -> Result<Vec<Request>> {
+        let path = self.file(OUTBOX_FILE);
+        if !Path::new(&path).exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&path).map_err(|e| Error::io(&path, e))?;
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|e| Error::json(&path, e)))
+            .collect()
+    }
+
+    fn save_outbox(&self, requests: &[Request]) -> Result<()> {
+        let path = self.file(OUTBOX_FILE);
+        let mut content = String::new();
+        for request in requests {
+            content += &serde_json::to_string(request).map_err(|e| Error::json(&path, e))?;
+            content.push('\n');
+        }
+        fs::write(&path, content).map_err(|e| Error::io(&path, e))
+    }
+
+    /// Queue a request for later delivery. A heartbeat replaces any earlier
+    /// queued heartbeat of the same lease, since only the newest checkpoint
+    /// matters.
+    pub fn queue(&self, request: &Request) -> Result<()> {
+        let mut requests = self.outbox()?;
+        if let Request::Heartbeat { lease_id, .. } = request {
+            requests.retain(
+                |queued| !matches!(queued, Request::Heartbeat { lease_id: id, .. } if id == lease_id),
+            );
+        }
+        requests.push(request.clone());
+        self.save_outbox(&requests)
+    }
+
+    /// Send queued requests in order until the coordinator stops answering.
+    ///
+    /// Returns the number of requests delivered; undelivered ones stay queued.
+    pub fn flush(&self, addr: &str) -> Result<usize> {
+        let requests = self.outbox()?;
+        let mut delivered = 0;
+        for request in &requests {
+            match send_request(addr, request) {
+                Ok(Response::Error { message }) => {
+                    eprintln!("⚠️  Coordinator rejected a queued report: {}", message);
+                }
+                Ok(_) => {}
+                Err(e) if is_link_error(&e) => break,
+                Err(e) => return Err(e),
+            }
+            delivered += 1;
+        }
+        if delivered > 0 {
+            self.save_outbox(&requests[delivered..])?;
+        }
+        Ok(delivered)
+    }
+}
+
+/// Errors that mean the coordinator is unreachable rather than wrong
+fn is_link_error(err: &Error) -> bool {
+    matches!(err, Error::Io { .. } | Error::Protocol(_))
+}
+
+/// Talks to a coordinator, falling back to the cache when the link is down
+pub struct WorkerClient {
+    addr: String,
+    name: String,
+    cache: WorkerCache,
+}
+
+impl WorkerClient {
+    pub fn new(addr: &str, name: &str, cache: WorkerCache) -> Self {
+        Self {
+            addr: addr.to_string(),
+            name: name.to_string(),
+            cache,
+        }
+    }
+
+    pub fn cache(&self) -> &WorkerCache {
+        &self.cache
+    }
+
+    /// Resume a cached chunk if there is one, otherwise lease a new chunk
+    pub fn lease(&self) -> Result<LeaseOutcome> {
+        if let Some((lease_id, chunk, path)) = self.cache.resume()? {
+            return Ok(LeaseOutcome::Leased {
+                lease_id,
+                chunk,
+                path,
+            });
+        }
+
+        self.cache.flush(&self.addr)?;
+        let request = Request::Lease {
+            worker: self.name.clone(),
+        };
+        match send_request(&self.addr, &request) {
+            Ok(Response::Leased {
+                lease_id, chunk, ..
+            }) => {
+                let path = self.cache.store_lease(lease_id, &chunk)?;
+                Ok(LeaseOutcome::Leased {
+                    lease_id,
+                    chunk,
+                    path,
+                })
+            }
+            Ok(Response::NoWork) => Ok(LeaseOutcome::NoWork),
+            Ok(Response::Error { message }) => Err(Error::Protocol(message)),
+            Ok(other) => Err(Error::Protocol(format!(
+                "unexpected answer to a lease request: {:?}",
+                other
+            ))),
+            Err(e) if is_link_error(&e) => Ok(LeaseOutcome::Offline),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Deliver a report, or queue it when the coordinator is unreachable.
+    ///
+    /// Reports are never sent ahead of older queued ones, so the coordinator
+    /// sees them in the order they were made. Returns `None` when queued.
+    pub fn report(&self, request: Request) -> Result<Option<Response>> {
+        self.cache.flush(&self.addr)?;
+        if !self.cache.outbox()?.is_empty() {
+            self.cache.queue(&request)?;
+            return Ok(None);
+        }
+        match send_request(&self.addr, &request) {
+            Ok(response) => Ok(Some(response)),
+            Err(e) if is_link_error(&e) => {
+                self.cache.queue(&request)?;
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Checkpoint a leased chunk and extend its lease
+    pub fn heartbeat(&self, lease_id: u64, chunk: &ChunkMetadata) -> Result<Option<Response>> {
+        self.report(Request::Heartbeat {
+            lease_id,
+            last_processed_hex: chunk.last_processed_hex.clone(),
+            proof: chunk.proof.clone(),
+            script_types: chunk.script_types,
+        })
+    }
+
+    /// Report a finished chunk with its proof and drop it from the cache
+    pub fn finish(&self, lease_id: u64, chunk: &ChunkMetadata) -> Result<Option<Response>> {
+        let response = self.report(Request::Finish {
+            lease_id,
+            proof: chunk.proof.clone(),
+            script_types: chunk.script_types,
+        })?;
+        self.cache.forget(&chunk.chunk_id)?;
+        Ok(response)
+    }
+
+    /// Hand an unfinished chunk back at its checkpoint and drop it from the cache
+    pub fn release(&self, lease_id: u64, chunk: &ChunkMetadata) -> Result<Option<Response>> {
+        let response = self.report(Request::Release {
+            lease_id,
+            last_processed_hex: chunk.last_processed_hex.clone(),
+            proof: chunk.proof.clone(),
+            script_types: chunk.script_types,
+        })?;
+        self.cache.forget(&chunk.chunk_id)?;
+        Ok(response)
+    }
+
+    pub fn report_match(&self, lease_id: u64, report: &MatchReport) -> Result<Option<Response>> {
+        self.report(Request::Match {
+            lease_id,
+            report: report.clone(),
+        })
+    }
+}
@@ -0,0 +1,47 @@
+//! Fixtures shared by the integration tests
+#![allow(dead_code)]
+
+use btc_key_matcher::chunk::ChunkMetadata;
+use btc_key_matcher::coverage::IntervalSet;
+use btc_key_matcher::scanner::{ScanLimits, ScanStats, scan_chunk};
+use btc_key_matcher::search::TargetSet;
+use num_bigint::BigUint;
+use std::fs;
+
+/// A fresh chunk 1 over `start..=end`, saved to `path`
+pub fn saved_chunk(path: &str, start: u64, end: u64) -> ChunkMetadata {
+    let meta = ChunkMetadata::with_bounds(&1u32.into(), &start.into(), &end.into());
+    meta.save(path).unwrap();
+    meta
+}
+
+/// Scan a fresh chunk to completion and return its finished metadata
+pub fn finished_chunk(base: &str, id: u32, start: u64, end: u64) -> (ChunkMetadata, String) {
+    let path = format!("{}/chunk_{}.json", base, id);
+    let mut meta = ChunkMetadata::with_bounds(&id.into(), &start.into(), &end.into());
+    fs::create_dir_all(base).unwrap();
+    scan_chunk(
+        &mut meta,
+        &path,
+        &TargetSet::default(),
+        64,
+        &ScanLimits::default(),
+        &mut ScanStats::default(),
+        |_| {},
+    )
+    .unwrap();
+    (meta, path)
+}
+
+/// An interval set from inclusive `(start, end)` pairs
+pub fn set(intervals: &[(u64, u64)]) -> IntervalSet {
+    IntervalSet::from_intervals(
+        intervals
+            .iter()
+            .map(|&(s, e)| (BigUint::from(s), BigUint::from(e))),
+    )
+}
+
+pub fn big(v: u64) -> BigUint {
+    BigUint::from(v)
+}
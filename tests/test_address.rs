@@ -0,0 +1,92 @@
+use btc_key_matcher::address::{DerivedAddress, derive, public_key, taproot_output_key};
+use btc_key_matcher::codec::Network;
+use btc_key_matcher::script::{ScriptType, ScriptTypes};
+use secp256k1::PublicKey;
+
+/// Public key with the even-y point of an x-only key
+fn even_key(x_only: &str) -> PublicKey {
+    PublicKey::from_slice(&hex::decode(format!("02{}", x_only)).unwrap()).unwrap()
+}
+
+#[test]
+fn test_taproot_key_path_vectors() {
+    // BIP-341 wallet vector without a script tree, and the first BIP-86
+    // receive address of the all-`abandon` mnemonic
+    for (internal, output, address) in [
+        (
+            "d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d",
+            "53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343",
+            "bc1p2wsldez5mud2yam29q22wgfh9439spgduvct83k3pm50fcxa5dps59h4z5",
+        ),
+        (
+            "cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115",
+            "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+        ),
+    ] {
+        let output_key = taproot_output_key(&even_key(internal)).unwrap();
+        assert_eq!(hex::encode(output_key), output);
+        assert_eq!(
+            DerivedAddress::new(ScriptType::P2tr, Network::Mainnet, &output_key).to_string(),
+            address
+        );
+    }
+}
+
+#[test]
+fn test_taproot_ignores_internal_key_parity() {
+    let mut key = [0u8; 32];
+    key[31] = 1;
+    let public = public_key(&key).unwrap();
+    let taproot = derive(&public, Network::Mainnet, "p2tr".parse().unwrap()).unwrap();
+    assert_eq!(
+        taproot[0].as_str(),
+        "bc1pmfr3p9j00pfxjh0zmgp99y8zftmd3s5pmedqhyptwy6lm87hf5sspknck9"
+    );
+
+    // k and n - k share the x coordinate, so they share the output key
+    let negated = public.negate(&secp256k1::Secp256k1::new());
+    assert_eq!(
+        taproot_output_key(&negated).unwrap(),
+        taproot_output_key(&public).unwrap()
+    );
+}
+
+#[test]
+fn test_derived_addresses_are_typed() {
+    let mut key = [0u8; 32];
+    key[31] = 1;
+    let derived = derive(
+        &public_key(&key).unwrap(),
+        Network::Mainnet,
+        ScriptTypes::ALL,
+    )
+    .unwrap();
+    assert_eq!(
+        derived.iter().map(|a| a.script).collect::<Vec<_>>(),
+        ScriptType::ALL
+    );
+    assert!(derived.iter().all(|a| a.network == Network::Mainnet));
+    assert_eq!(
+        derived.iter().map(|a| a.compressed).collect::<Vec<_>>(),
+        [true, false, true, true, true, true, false]
+    );
+
+    // Compressed P2PKH and P2WPKH pay to the same hash
+    assert_eq!(derived[0].payload(), derived[3].payload());
+    assert_eq!(derived[4].payload().len(), 32);
+    assert_eq!(derived[0].as_str(), "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH");
+    assert_eq!(
+        derived[3].to_string(),
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+    );
+    assert_ne!(derived[0], derived[3]);
+
+    // Bare P2PK outputs have no address and show the SEC key instead
+    assert_eq!(derived[5].to_address(), None);
+    assert_eq!(
+        derived[5].as_str(),
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+    );
+    assert_eq!(derived[6].payload().len(), 65);
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use btc_key_matcher::address::derive_addresses;
+use btc_key_matcher::audit::{AuditIssue, AuditOptions, audit_chunk, audit_folder};
+use btc_key_matcher::chunk::{ChunkMetadata, ChunkStatus};
+use btc_key_matcher::proof::{ChunkProof, proof_hash};
+use btc_key_matcher::search::TargetSet;
+use common::finished_chunk;
+use std::fs;
+
+fn options(dry_run: bool) -> AuditOptions {
+    AuditOptions {
+        chunks: None,
+        keys: 16,
+        recheck: false,
+        dry_run,
+    }
+}
+
+#[test]
+fn test_audit_marks_clean_chunks_verified() {
+    let base = "resources/tests/tmp_audit_clean";
+    let _ = fs::remove_dir_all(base);
+    let (_, path) = finished_chunk(base, 1, 0x1000, 0x10ff);
+
+    let report = audit_folder(base, &TargetSet::default(), &options(false)).unwrap();
+    assert_eq!(report.checked, 1);
+    assert_eq!(report.verified, vec!["00001".to_string()]);
+    assert!(report.flagged.is_empty());
+    let saved = ChunkMetadata::load(&path).unwrap();
+    assert_eq!(saved.status, ChunkStatus::Verified);
+    assert!(saved.status.is_done());
+
+    // Verified chunks are skipped unless rechecked
+    assert_eq!(
+        audit_folder(base, &TargetSet::default(), &options(false))
+            .unwrap()
+            .checked,
+        0
+    );
+
+    fs::remove_dir_all(base).unwrap();
+}
+
+#[test]
+fn test_audit_flags_bad_proof_for_rescan() {
+    let base = "resources/tests/tmp_audit_flag";
+    let _ = fs::remove_dir_all(base);
+    let (mut meta, path) = finished_chunk(base, 2, 0x2000, 0x200f);
+    let proof = meta.proof.as_mut().unwrap();
+    assert_eq!(proof.distinguished.len(), 16);
+    proof.distinguished.remove(3);
+    meta.save(&path).unwrap();
+
+    let dry = audit_folder(base, &TargetSet::default(), &options(true)).unwrap();
+    assert_eq!(dry.flagged.len(), 1);
+    assert_eq!(
+        ChunkMetadata::load(&path).unwrap().status,
+        ChunkStatus::Finished
+    );
+
+    let report = audit_folder(base, &TargetSet::default(), &options(false)).unwrap();
+    let (chunk_id, issues) = &report.flagged[0];
+    assert_eq!(chunk_id, "00002");
+    assert!(issues.iter().any(|i| matches!(i, AuditIssue::Proof(_))));
+    let saved = ChunkMetadata::load(&path).unwrap();
+    assert_eq!(saved.status, ChunkStatus::Pending);
+    assert_eq!(saved.last_processed_hex, saved.start_hex);
+    assert_eq!(saved.proof, None);
+
+    fs::remove_dir_all(base).unwrap();
+}
+
+#[test]
+fn test_audit_rejects_hand_finished_half_scan() {
+    let base = "resources/tests/tmp_audit_half";
+    let _ = fs::remove_dir_all(base);
+    let (mut meta, path) = finished_chunk(base, 4, 0x4000, 0x43ff);
+
+    // Keep a self-consistent proof of only the first half of the chunk, as a
+    // file edited to look finished after a partial scan would have
+    let proof = meta.proof.take().unwrap();
+    let walk = meta.walk_range().unwrap();
+    let mut half = ChunkProof::new(proof.bits);
+    for &offset in proof.distinguished.iter().filter(|&&o| o < 512) {
+        half.record(offset, &proof_hash(&walk.raw_key_at(offset)).unwrap());
+    }
+    meta.proof = Some(half);
+    meta.save(&path).unwrap();
+
+    let report = audit_folder(base, &TargetSet::default(), &options(false)).unwrap();
+    assert!(report.verified.is_empty());
+    let (_, issues) = &report.flagged[0];
+    assert!(issues.iter().any(|i| matches!(i, AuditIssue::Proof(_))));
+    assert_eq!(
+        ChunkMetadata::load(&path).unwrap().status,
+        ChunkStatus::Pending
+    );
+
+    fs::remove_dir_all(base).unwrap();
+}
+
+#[test]
+fn test_audit_catches_missed_match_and_unfinished_chunk() {
+    let base = "resources/tests/tmp_audit_match";
+    let _ = fs::remove_dir_all(base);
+    let (mut meta, _) = finished_chunk(base, 3, 0x3000, 0x300f);
+
+    // With 16 keys every key is distinguished, so all of them are sampled
+    let mut key = [0u8; 32];
+    key[30..].copy_from_slice(&[0x30, 0x07]);
+    let target = derive_addresses(&key).unwrap()[0].to_string();
+    let issues = audit_chunk(&meta, &TargetSet::new([&target]), 16).unwrap();
+    assert!(
+        issues
+            .iter()
+            .any(|i| matches!(i, AuditIssue::MissedMatch { address, .. } if *address == target))
+    );
+
+    meta.status = ChunkStatus::Pending;
+    meta.last_processed_hex = meta.start_hex.clone();
+    let issues = audit_chunk(&meta, &TargetSet::default(), 4).unwrap();
+    assert!(issues.contains(&AuditIssue::NotFinished(ChunkStatus::Pending)));
+    assert!(issues.contains(&AuditIssue::Incomplete));
+
+    fs::remove_dir_all(base).unwrap();
+}
@@ -1,4 +1,10 @@
-use btc_key_matcher::chunk::{ChunkMetadata, ChunkStatus, calculate_chunk_range};
+use btc_key_matcher::chunk::{
+    ChunkMetadata, calculate_chunk_range, chunk_key_range, range_chunk_key_range,
+    template_chunk_count,
+};
+use btc_key_matcher::error::Error;
+use btc_key_matcher::keygen::{KeyTemplate, KeyWalk};
+use btc_key_matcher::puzzles::PuzzleRange;
 use num_bigint::BigUint;
 use num_traits::{FromPrimitive, One}; // <== Use FromPrimitive instead of manual from()
 use std::fs;
@@ -53,16 +59,88 @@ fn test_chunk_metadata_file_persistence() {
     let path = "resources/tests/tmp_chunk_99999.json";
 
     let meta = ChunkMetadata {
-        chunk_id: "99999".to_string(),
-        start_hex: "0f".repeat(32),
-        end_hex: "ff".repeat(32),
         last_processed_hex: "1f".repeat(32),
-        status: ChunkStatus::Processing,
+        ..ChunkMetadata::with_bounds(
+            &99999u32.into(),
+            &BigUint::from_bytes_be(&[0x0f; 32]),
+            &BigUint::from_bytes_be(&[0xff; 32]),
+        )
     };
 
-    meta.save(path);
-    let loaded = ChunkMetadata::load(path);
+    meta.save(path).unwrap();
+    let loaded = ChunkMetadata::load(path).unwrap();
     fs::remove_file(path).unwrap();
 
     assert_eq!(meta, loaded);
 }
+
+#[test]
+fn test_chunk_metadata_load_errors() {
+    assert!(matches!(
+        ChunkMetadata::load("resources/tests/does_not_exist.json"),
+        Err(Error::Io { .. })
+    ));
+    assert!(matches!(
+        ChunkMetadata::load("resources/tests/test_addresses_sorted.txt"),
+        Err(Error::Json { .. })
+    ));
+}
+
+#[test]
+fn test_template_chunks_tile_matching_keys() {
+    let base = "resources/tests/tmp_template_chunks";
+    let _ = fs::remove_dir_all(base);
+    let template: KeyTemplate = "4??f".parse().unwrap();
+    let size = BigUint::from(100u32);
+    assert_eq!(template_chunk_count(&size, &template), BigUint::from(3u32));
+
+    let last =
+        ChunkMetadata::create_on_template(&BigUint::from(2u32), &size, base, &template).unwrap();
+    let saved = ChunkMetadata::load(&ChunkMetadata::path(&BigUint::from(2u32), base)).unwrap();
+    fs::remove_dir_all(base).unwrap();
+
+    assert_eq!(saved, last);
+    assert_eq!(saved.walk(), KeyWalk::Template(template.clone()));
+    assert_eq!(saved.start_hex, format!("{:064x}", 0x4c8f));
+    assert_eq!(saved.end_hex, format!("{:064x}", 0x4fff));
+    assert_eq!(saved.last_processed_hex, saved.start_hex);
+    assert_eq!(saved.keys_total().unwrap(), BigUint::from(56u32));
+    assert!(
+        ChunkMetadata::create_on_template(&BigUint::from(3u32), &size, base, &template).is_err()
+    );
+}
+
+#[test]
+fn test_narrow_chunk_bounds_match_wide_ones() {
+    // The same layout shifted above 2^128 takes the general path
+    let shift = BigUint::one() << 192u32;
+    let top = (BigUint::one() << 128u32) - 1u32;
+    for (start, end) in [
+        (BigUint::from(0x1000u32), BigUint::from(0x1fffu32)),
+        (&top - 1000u32, top.clone()),
+    ] {
+        let narrow = PuzzleRange {
+            start: start.clone(),
+            end: end.clone(),
+        };
+        let wide = PuzzleRange {
+            start: &start + &shift,
+            end: &end + &shift,
+        };
+        let chunk_size = BigUint::from(300u32);
+        for id in 0..5u32 {
+            let id = BigUint::from(id);
+            let shifted = |bounds: Result<(BigUint, BigUint), Error>| {
+                bounds.map(|(first, last)| (first + &shift, last + &shift))
+            };
+            assert_eq!(
+                shifted(chunk_key_range(&id, &chunk_size, Some(&narrow))).ok(),
+                chunk_key_range(&id, &chunk_size, Some(&wide)).ok()
+            );
+            assert_eq!(
+                shifted(range_chunk_key_range(&id, &chunk_size, &narrow)).ok(),
+                range_chunk_key_range(&id, &chunk_size, &wide).ok()
+            );
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use btc_key_matcher::address::{derive_addresses, hash160, private_key_to_wif, public_key};
+use btc_key_matcher::codec::{
+    Address, Network, P2pk, Wif, base58check_decode, base58check_encode, segwit_decode,
+};
+
+fn key_one() -> [u8; 32] {
+    let mut key = [0u8; 32];
+    key[31] = 1;
+    key
+}
+
+#[test]
+fn test_base58check_round_trip_and_checksum() {
+    assert_eq!(
+        base58check_encode(0x00, &[0u8; 20]),
+        "1111111111111111111114oLvT2"
+    );
+    assert_eq!(
+        base58check_decode("1111111111111111111114oLvT2").unwrap(),
+        (0x00, vec![0u8; 20])
+    );
+    assert!(base58check_decode("1111111111111111111114oLvT3").is_err());
+    assert!(base58check_decode("10Lv").is_err());
+    assert!(base58check_decode("1").is_err());
+}
+
+#[test]
+fn test_parse_base58_addresses() {
+    let pubkey = public_key(&key_one()).unwrap().serialize();
+    let address: Address = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH".parse().unwrap();
+    assert_eq!(
+        address,
+        Address::P2pkh {
+            network: Network::Mainnet,
+            hash: hash160(&pubkey).try_into().unwrap(),
+        }
+    );
+    assert_eq!(address.script_type(), "p2pkh");
+    assert_eq!(address.to_string(), "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH");
+
+    let testnet: Address = "mrCDrCybB6J1vRfbwM5hemdJz73FwDBC8r".parse().unwrap();
+    assert_eq!(testnet.network(), Network::Testnet);
+    assert_eq!(testnet.payload(), address.payload());
+
+    for derived in derive_addresses(&key_one()).unwrap() {
+        let parsed: Address = derived.as_str().parse().unwrap();
+        assert_eq!(derived.to_address().as_ref(), Some(&parsed));
+        assert_eq!(parsed.to_string(), derived.as_str());
+    }
+    assert!(
+        "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMh"
+            .parse::<Address>()
+            .is_err()
+    );
+}
+
+#[test]
+fn test_parse_segwit_addresses() {
+    let address: Address = "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4"
+        .parse()
+        .unwrap();
+    assert_eq!(address.script_type(), "p2wpkh");
+    assert_eq!(
+        address.to_string(),
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+    );
+
+    let wsh: Address = "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7"
+        .parse()
+        .unwrap();
+    assert_eq!(wsh.network(), Network::Testnet);
+    assert_eq!(wsh.script_type(), "p2wsh");
+
+    let taproot = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0";
+    let parsed: Address = taproot.parse().unwrap();
+    assert_eq!(parsed.script_type(), "p2tr");
+    assert_eq!(parsed.to_string(), taproot);
+
+    let (hrp, version, program) = segwit_decode("BC1SW50QGDZ25J").unwrap();
+    assert_eq!((hrp.as_str(), version, program.len()), ("bc", 16, 2));
+
+    // Version 0 with a bech32m checksum, version 1 with a bech32 checksum,
+    // a bad checksum and an invalid program length
+    for invalid in [
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+        "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5",
+        "bc1zw508d6qejxtdg4y5r3zarqfsj7t2",
+        "bc1gmk9yu",
+    ] {
+        assert!(invalid.parse::<Address>().is_err(), "{}", invalid);
+    }
+
+    // A hand-built program of an invalid length still formats
+    let broken = Address::Witness {
+        network: Network::Mainnet,
+        version: 2,
+        program: vec![0u8; 1],
+    };
+    assert!(broken.to_string().starts_with("<invalid address: "));
+}
+
+#[test]
+fn test_wif_decode_reads_flags() {
+    let compressed = Wif::decode("KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn").unwrap();
+    assert_eq!(
+        compressed,
+        Wif {
+            key: key_one(),
+            compressed: true,
+            network: Network::Mainnet,
+        }
+    );
+    let uncompressed = Wif::decode("5HpHagT65TZzG1PH3CSu63k8DbpvD8s5ip4nEB3kEsreAnchuDf").unwrap();
+    assert!(!uncompressed.compressed);
+    assert_eq!(uncompressed.key, key_one());
+    assert_eq!(
+        uncompressed.encode(),
+        private_key_to_wif(&key_one(), false, Network::Mainnet)
+    );
+
+    let testnet = Wif {
+        network: Network::Testnet,
+        ..compressed
+    };
+    assert_eq!(Wif::decode(&testnet.encode()).unwrap(), testnet);
+
+    // An address has the wrong version and length for a WIF key
+    assert!(Wif::decode("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH").is_err());
+    assert!(Wif::decode("KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWo").is_err());
+}
+
+#[test]
+fn test_network_params_and_parse_for() {
+    assert_eq!("Signet".parse::<Network>().unwrap(), Network::Signet);
+    assert!("testnet3".parse::<Network>().is_err());
+    assert_eq!(Network::Regtest.hrp(), "bcrt");
+    assert_eq!(
+        Network::Signet.p2pkh_version(),
+        Network::Testnet.p2pkh_version()
+    );
+
+    // Regtest has its own segwit prefix but testnet's version bytes, so
+    // only the expected network tells a regtest P2PKH address apart
+    let regtest: Address = "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080"
+        .parse()
+        .unwrap();
+    assert_eq!(regtest.network(), Network::Regtest);
+    let legacy = "mrCDrCybB6J1vRfbwM5hemdJz73FwDBC8r";
+    assert_eq!(
+        legacy.parse::<Address>().unwrap().network(),
+        Network::Testnet
+    );
+    let on_regtest = Address::parse_for(legacy, Network::Regtest).unwrap();
+    assert_eq!(on_regtest.network(), Network::Regtest);
+    assert_eq!(on_regtest.to_string(), legacy);
+
+    assert!(Address::parse_for(legacy, Network::Mainnet).is_err());
+    assert!(
+        Address::parse_for(
+            "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx",
+            Network::Regtest
+        )
+        .is_err()
+    );
+    assert_eq!(
+        private_key_to_wif(&key_one(), true, Network::Signet),
+        "cMahea7zqjxrtgAbB7LSGbcQUr1uX1ojuat9jZodMN87JcbXMTcA"
+    );
+}
+
+#[test]
+fn test_parse_p2pk_keys() {
+    let compressed = public_key(&key_one()).unwrap().serialize();
+    let p2pk: P2pk = hex::encode(compressed).to_uppercase().parse().unwrap();
+    assert!(p2pk.compressed());
+    assert_eq!(p2pk.key, compressed);
+    assert_eq!(p2pk.to_string(), hex::encode(compressed));
+
+    let uncompressed = public_key(&key_one()).unwrap().serialize_uncompressed();
+    let p2pk: P2pk = hex::encode(uncompressed).parse().unwrap();
+    assert!(!p2pk.compressed());
+
+    // A compressed key with the uncompressed prefix, a point off the curve
+    // and a truncated key
+    let mut wrong_prefix = compressed;
+    wrong_prefix[0] = 0x04;
+    let mut off_curve = uncompressed;
+    off_curve[64] ^= 1;
+    for invalid in [
+        hex::encode(wrong_prefix),
+        hex::encode(off_curve),
+        hex::encode(&compressed[..32]),
+        "zz".repeat(33),
+    ] {
+        assert!(invalid.parse::<P2pk>().is_err(), "{}", invalid);
+    }
+}
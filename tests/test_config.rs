@@ -0,0 +1,108 @@
+use btc_key_matcher::config::{Config, TargetFile};
+use btc_key_matcher::error::Error;
+use btc_key_matcher::search::MatchAction;
+
+#[test]
+fn test_config_defaults_and_overrides() {
+    let config = Config::parse("").unwrap();
+    assert_eq!(config, Config::default());
+    assert_eq!(config.chunk_size, 100_000_000_000);
+
+    let config = Config::parse(
+        r#"
+        addr_file = "targets.txt"
+        chunk_folder = "/data/chunks"
+        chunk_size = 1000
+        batch_size = 50
+        threads = 4
+        script_types = ["p2pkh-c", "p2tr"]
+        network = "regtest"
+        symmetry = "negation"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.addr_file, "targets.txt");
+    assert_eq!(config.chunk_size, 1000);
+    assert_eq!(config.batch_size, 50);
+    assert_eq!(config.threads, Some(4));
+    assert_eq!(config.script_types.to_string(), "p2pkh-c,p2tr");
+    assert_eq!(config.network.to_string(), "regtest");
+    assert_eq!(config.symmetry.to_string(), "negation");
+    assert_eq!(config.chunk_folder_for(Some(71)), "/data/chunks/puzzle_071");
+    assert_eq!(config.chunk_folder_for(None), "/data/chunks");
+}
+
+#[test]
+fn test_config_rejects_invalid_values() {
+    assert!(matches!(
+        Config::parse("batch_size = 0"),
+        Err(Error::Config { .. })
+    ));
+    assert!(matches!(
+        Config::parse("unknown_key = 1"),
+        Err(Error::Config { .. })
+    ));
+    assert!(matches!(
+        Config::parse("script_types = [\"p2pk\"]"),
+        Err(Error::Config { .. })
+    ));
+    assert!(matches!(
+        Config::parse("script_types = []"),
+        Err(Error::Config { .. })
+    ));
+    assert!(matches!(
+        Config::parse("network = \"testnet3\""),
+        Err(Error::Config { .. })
+    ));
+    assert!(matches!(
+        Config::load("resources/tests/missing.toml"),
+        Err(Error::Io { .. })
+    ));
+}
+
+#[test]
+fn test_config_target_files() {
+    let config = Config::parse(r#"addr_file = "all.txt""#).unwrap();
+    let files = config.target_files();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].label(), "all");
+
+    let config = Config::parse(
+        r#"
+        [[targets]]
+        label = "puzzles"
+        file = "puzzles.txt"
+        priority = 10
+
+        [[targets]]
+        file = "lists/canary.txt"
+        action = "continue"
+        "#,
+    )
+    .unwrap();
+    let files = config.target_files();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].action, MatchAction::Stop);
+    assert_eq!(files[1].label(), "canary");
+    assert_eq!(files[1].action, MatchAction::Continue);
+
+    let spec: TargetFile = "watch=watch.txt,priority=-2,action=notify".parse().unwrap();
+    assert_eq!(spec.label(), "watch");
+    assert_eq!(spec.file, "watch.txt");
+    assert_eq!(spec.priority, -2);
+    assert_eq!(spec.action, MatchAction::Notify);
+    assert!("watch.txt,speed=3".parse::<TargetFile>().is_err());
+    assert!("watch=".parse::<TargetFile>().is_err());
+
+    assert!(matches!(
+        Config::parse(
+            r#"
+            [[targets]]
+            file = "a/list.txt"
+            [[targets]]
+            file = "b/list.txt"
+            "#
+        ),
+        Err(Error::Config { .. })
+    ));
+}
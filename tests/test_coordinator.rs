@@ -0,0 +1,362 @@
+use btc_key_matcher::chunk::{ChunkMetadata, ChunkStatus, parse_chunk_id};
+use btc_key_matcher::coordinator::{Coordinator, MATCHES_FILE};
+use btc_key_matcher::keygen::KeyWalk;
+use btc_key_matcher::proof::compute_proof;
+use btc_key_matcher::protocol::{Request, Response, send_request};
+use btc_key_matcher::puzzles::PuzzleRange;
+use btc_key_matcher::scanner::MatchReport;
+use btc_key_matcher::search::MatchAction;
+use num_bigint::BigUint;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+fn small_range() -> PuzzleRange {
+    PuzzleRange {
+        start: BigUint::from(0x1000u32),
+        end: BigUint::from(0x1fffu32),
+    }
+}
+
+fn lease(coordinator: &mut Coordinator, worker: &str) -> (u64, ChunkMetadata) {
+    match coordinator.handle(Request::Lease {
+        worker: worker.to_string(),
+    }) {
+        Response::Leased {
+            lease_id, chunk, ..
+        } => (lease_id, chunk),
+        other => panic!("expected a lease, got {:?}", other),
+    }
+}
+
+fn stored(base: &str, chunk: &ChunkMetadata) -> ChunkMetadata {
+    let id = parse_chunk_id(&chunk.chunk_id).unwrap();
+    ChunkMetadata::load(&ChunkMetadata::path(&id, base)).unwrap()
+}
+
+#[test]
+fn test_coordinator_lease_checkpoint_finish() {
+    let base = "resources/tests/tmp_coordinator_lifecycle";
+    let _ = fs::remove_dir_all(base);
+    let mut coordinator = Coordinator::open(
+        base,
+        BigUint::from(0x100u32),
+        Some(small_range()),
+        Duration::from_secs(60),
+    )
+    .unwrap();
+
+    let (lease_a, chunk_a) = lease(&mut coordinator, "worker-a");
+    let (lease_b, chunk_b) = lease(&mut coordinator, "worker-b");
+    assert_ne!(lease_a, lease_b);
+    assert_ne!(chunk_a.chunk_id, chunk_b.chunk_id);
+
+    // Checkpoints must move forward inside the chunk
+    let start = BigUint::parse_bytes(chunk_a.start_hex.as_bytes(), 16).unwrap();
+    let checkpoint = format!("{:064x}", &start + 0x10u32);
+    assert_eq!(
+        coordinator.handle(Request::Heartbeat {
+            lease_id: lease_a,
+            last_processed_hex: checkpoint.clone(),
+            proof: None,
+            script_types: None,
+        }),
+        Response::Ok
+    );
+    assert_eq!(stored(base, &chunk_a).last_processed_hex, checkpoint);
+    assert!(matches!(
+        coordinator.handle(Request::Heartbeat {
+            lease_id: lease_a,
+            last_processed_hex: chunk_a.start_hex.clone(),
+            proof: None,
+            script_types: None,
+        }),
+        Response::Error { .. }
+    ));
+
+    let report = MatchReport {
+        hex_key: checkpoint.clone(),
+        address: "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH".to_string(),
+        wif_uncompressed: String::new(),
+        wif_compressed: String::new(),
+        format: "P2PKH compressed".to_string(),
+        sets: vec!["puzzles".to_string()],
+        action: MatchAction::Stop,
+    };
+    assert_eq!(
+        coordinator.handle(Request::Match {
+            lease_id: lease_a,
+            report,
+        }),
+        Response::Ok
+    );
+    assert!(
+        fs::read_to_string(format!("{}/{}", base, MATCHES_FILE))
+            .unwrap()
+            .contains("worker-a")
+    );
+
+    let proof = compute_proof(&chunk_a).unwrap();
+    assert_eq!(
+        coordinator.handle(Request::Finish {
+            lease_id: lease_a,
+            proof: Some(proof.clone()),
+            script_types: None,
+        }),
+        Response::Ok
+    );
+    let finished = stored(base, &chunk_a);
+    assert_eq!(finished.status, ChunkStatus::Finished);
+    assert_eq!(finished.proof, Some(proof));
+    assert_eq!(
+        coordinator.handle(Request::Finish {
+            lease_id: lease_a,
+            proof: None,
+            script_types: None,
+        }),
+        Response::LeaseLost
+    );
+
+    assert_eq!(
+        coordinator.handle(Request::Release {
+            lease_id: lease_b,
+            last_processed_hex: chunk_b.start_hex.clone(),
+            proof: None,
+            script_types: None,
+        }),
+        Response::Ok
+    );
+    assert_eq!(stored(base, &chunk_b).status, ChunkStatus::Pending);
+    assert!(coordinator.active_leases().is_empty());
+
+    fs::remove_dir_all(base).unwrap();
+}
+
+#[test]
+fn test_coordinator_reassigns_expired_lease() {
+    let base = "resources/tests/tmp_coordinator_expiry";
+    let _ = fs::remove_dir_all(base);
+    let mut coordinator = Coordinator::open(
+        base,
+        BigUint::from(0x100u32),
+        Some(small_range()),
+        Duration::from_millis(50),
+    )
+    .unwrap();
+
+    let (lease_a, chunk_a) = lease(&mut coordinator, "worker-a");
+    let start = BigUint::parse_bytes(chunk_a.start_hex.as_bytes(), 16).unwrap();
+    let checkpoint = format!("{:064x}", &start + 0x20u32);
+    assert_eq!(
+        coordinator.handle(Request::Heartbeat {
+            lease_id: lease_a,
+            last_processed_hex: checkpoint.clone(),
+            proof: None,
+            script_types: None,
+        }),
+        Response::Ok
+    );
+
+    thread::sleep(Duration::from_millis(120));
+
+    // The expired chunk is pending again and is picked up, checkpoint intact
+    let (_, chunk_b) = lease(&mut coordinator, "worker-b");
+    assert_eq!(chunk_b.chunk_id, chunk_a.chunk_id);
+    assert_eq!(chunk_b.last_processed_hex, checkpoint);
+    assert_eq!(
+        coordinator.handle(Request::Finish {
+            lease_id: lease_a,
+            proof: None,
+            script_types: None,
+        }),
+        Response::LeaseLost
+    );
+
+    fs::remove_dir_all(base).unwrap();
+}
+
+#[test]
+fn test_coordinator_rejects_finish_without_valid_proof() {
+    let base = "resources/tests/tmp_coordinator_proof";
+    let _ = fs::remove_dir_all(base);
+    let mut coordinator = Coordinator::open(
+        base,
+        BigUint::from(0x100u32),
+        Some(small_range()),
+        Duration::from_secs(60),
+    )
+    .unwrap();
+
+    let (lease_id, chunk) = lease(&mut coordinator, "worker-a");
+    let mut proof = compute_proof(&chunk).unwrap();
+    proof.distinguished.pop();
+    assert!(matches!(
+        coordinator.handle(Request::Finish {
+            lease_id,
+            proof: Some(proof),
+            script_types: None,
+        }),
+        Response::Error { .. }
+    ));
+
+    // The chunk goes back to pending from its start for another worker
+    let rejected = stored(base, &chunk);
+    assert_eq!(rejected.status, ChunkStatus::Pending);
+    assert_eq!(rejected.last_processed_hex, chunk.start_hex);
+    assert_eq!(rejected.proof, None);
+
+    let (lease_id, chunk) = lease(&mut coordinator, "worker-b");
+    assert!(matches!(
+        coordinator.handle(Request::Finish {
+            lease_id,
+            proof: None,
+            script_types: None,
+        }),
+        Response::Error { .. }
+    ));
+    assert_eq!(stored(base, &chunk).status, ChunkStatus::Pending);
+
+    fs::remove_dir_all(base).unwrap();
+}
+
+#[test]
+fn test_coordinator_follows_descending_walks() {
+    let base = "resources/tests/tmp_coordinator_descending";
+    let _ = fs::remove_dir_all(base);
+    let mut coordinator = Coordinator::open(
+        base,
+        BigUint::from(0x100u32),
+        Some(small_range()),
+        Duration::from_secs(60),
+    )
+    .unwrap();
+
+    let (lease_id, chunk) = lease(&mut coordinator, "worker-a");
+    let id = parse_chunk_id(&chunk.chunk_id).unwrap();
+    let mut chunk = stored(base, &chunk);
+    chunk.set_walk(KeyWalk::Descending).unwrap();
+    chunk.save(&ChunkMetadata::path(&id, base)).unwrap();
+    let heartbeat = |coordinator: &mut Coordinator, key: &BigUint| {
+        coordinator.handle(Request::Heartbeat {
+            lease_id,
+            last_processed_hex: format!("{:064x}", key),
+            proof: None,
+            script_types: None,
+        })
+    };
+
+    // Walking down from the end is progress, going back up is not
+    let (start, end) = chunk.key_bounds().unwrap();
+    assert_eq!(heartbeat(&mut coordinator, &(&end - 0x10u32)), Response::Ok);
+    assert!(matches!(
+        heartbeat(&mut coordinator, &(&end - 0x08u32)),
+        Response::Error { .. }
+    ));
+
+    let mut bad = compute_proof(&chunk).unwrap();
+    bad.distinguished.pop();
+    assert!(matches!(
+        coordinator.handle(Request::Finish {
+            lease_id,
+            proof: Some(bad),
+            script_types: None,
+        }),
+        Response::Error { .. }
+    ));
+    let rejected = stored(base, &chunk);
+    assert_eq!(rejected.last_processed_hex, chunk.end_hex);
+    assert_eq!(rejected.keys_done().unwrap(), BigUint::ZERO);
+
+    let (lease_id, _) = lease(&mut coordinator, "worker-b");
+    assert_eq!(
+        coordinator.handle(Request::Finish {
+            lease_id,
+            proof: Some(compute_proof(&chunk).unwrap()),
+            script_types: None,
+        }),
+        Response::Ok
+    );
+    let finished = stored(base, &chunk);
+    assert_eq!(finished.last_processed_hex, format!("{:064x}", start));
+    assert_eq!(
+        finished.last_processed_hex,
+        finished.final_key_hex().unwrap()
+    );
+
+    fs::remove_dir_all(base).unwrap();
+}
+
+#[test]
+fn test_coordinator_process_serves_workers() {
+    let base = "resources/tests/tmp_coordinator_process";
+    let _ = fs::remove_dir_all(base);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_btc_key_matcher"))
+        .args([
+            "coordinator",
+            "--listen",
+            "127.0.0.1:0",
+            "--puzzle-id",
+            "71",
+            "--chunk-folder",
+            base,
+            "--chunk-size",
+            "256",
+        ])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Keep the pipe open so the coordinator can keep printing
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut first_line = String::new();
+    stdout.read_line(&mut first_line).unwrap();
+    let addr = first_line.trim().rsplit(' ').next().unwrap().to_string();
+
+    let leases: Vec<(u64, ChunkMetadata)> = (0..2)
+        .map(|i| {
+            match send_request(
+                &addr,
+                &Request::Lease {
+                    worker: format!("worker-{}", i),
+                },
+            )
+            .unwrap()
+            {
+                Response::Leased {
+                    lease_id, chunk, ..
+                } => (lease_id, chunk),
+                other => panic!("expected a lease, got {:?}", other),
+            }
+        })
+        .collect();
+    assert_eq!(
+        send_request(
+            &addr,
+            &Request::Finish {
+                lease_id: leases[0].0,
+                proof: Some(compute_proof(&leases[0].1).unwrap()),
+                script_types: None,
+            }
+        )
+        .unwrap(),
+        Response::Ok
+    );
+    let status = send_request(&addr, &Request::Status).unwrap();
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    fs::remove_dir_all(base).unwrap();
+
+    assert_eq!(
+        status,
+        Response::Status {
+            active_leases: 1,
+            pending: 0,
+            processing: 1,
+            finished: 1,
+            matches: 0,
+        }
+    );
+}
@@ -0,0 +1,207 @@
+mod common;
+
+use btc_key_matcher::chunk::{ChunkMetadata, ChunkStatus};
+use btc_key_matcher::chunk_manager::{ChunkFilter, acquire_range_chunk};
+use btc_key_matcher::coverage::{
+    Coverage, IntervalSet, folder_coverage, load_imported, store_imported,
+};
+use btc_key_matcher::puzzles::PuzzleRange;
+use btc_key_matcher::script::{ScriptType, ScriptTypes};
+use btc_key_matcher::symmetry::{Symmetry, curve_order};
+use common::{big, set};
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_interval_set_merges_and_covers() {
+    let intervals = set(&[(50, 60), (10, 20), (21, 30), (15, 18), (70, 69)]);
+    assert_eq!(intervals, set(&[(10, 30), (50, 60)]));
+    assert_eq!(intervals.key_count(), big(32));
+
+    assert!(intervals.covers(&big(10), &big(30)));
+    assert!(intervals.covers(&big(55), &big(55)));
+    assert!(!intervals.covers(&big(25), &big(50)));
+    assert!(!intervals.covers(&big(5), &big(12)));
+    assert!(!intervals.covers(&big(61), &big(61)));
+
+    let mut joined = intervals.clone();
+    joined.extend(&set(&[(31, 49)]));
+    assert_eq!(joined, set(&[(10, 60)]));
+}
+
+#[test]
+fn test_coverage_round_trips_csv_and_json() {
+    let coverage = Coverage {
+        puzzle: Some(20),
+        source: "pool-a".to_string(),
+        exported_at: Some(1_700_000_000),
+        script_types: "p2pkh-c,p2wpkh".parse().unwrap(),
+        intervals: set(&[(0x80000, 0x80fff), (0x90000, 0x900ff)]),
+    };
+
+    let csv = coverage.to_csv();
+    assert!(csv.starts_with("# puzzle: 20\n# source: pool-a\n"));
+    assert!(csv.contains("# script_types: p2pkh-c,p2wpkh\n"));
+    assert!(csv.contains("start,end\n0x80000,0x80fff\n"));
+    assert_eq!(Coverage::from_csv(&csv, "test.csv").unwrap(), coverage);
+    assert_eq!(
+        Coverage::from_json(&coverage.to_json(), "test.json").unwrap(),
+        coverage
+    );
+
+    let foreign = "{\"intervals\": [{\"start\": \"0x20\", \"end\": \"0x2f\"}, {\"start\": \"10\", \"end\": \"1f\"}]}";
+    let parsed = Coverage::from_json(foreign, "foreign.json").unwrap();
+    assert_eq!(parsed.puzzle, None);
+    assert_eq!(parsed.script_types, ScriptTypes::DEFAULT);
+    assert_eq!(parsed.intervals, set(&[(16, 47)]));
+
+    // Bare bounds are hex even when they contain only decimal digits
+    let bare = Coverage::from_csv("start,end\n20000,20fff\n", "bare.csv").unwrap();
+    assert_eq!(bare.intervals, set(&[(0x20000, 0x20fff)]));
+
+    assert!(Coverage::from_csv("start,end\n0x30,0x20\n", "bad.csv").is_err());
+    assert!(Coverage::from_csv("0x10\n", "bad.csv").is_err());
+    assert!(Coverage::from_csv("# puzzle: x\n", "bad.csv").is_err());
+
+    let puzzle = PuzzleRange {
+        start: big(0x80000),
+        end: big(0xfffff),
+    };
+    assert!(coverage.check_within(&puzzle).is_ok());
+    let outside = Coverage {
+        intervals: set(&[(0x7ffff, 0x80010)]),
+        ..coverage
+    };
+    assert!(outside.check_within(&puzzle).is_err());
+}
+
+#[test]
+fn test_folder_coverage_counts_scanned_keys() {
+    let base = "resources/tests/tmp_coverage_folder";
+    let _ = fs::remove_dir_all(base);
+    fs::create_dir_all(base).unwrap();
+    let chunk = |id: u64, last: u64, status: ChunkStatus| ChunkMetadata {
+        last_processed_hex: format!("{:064x}", 0x100 * id + last),
+        status,
+        ..ChunkMetadata::with_bounds(&id.into(), &big(0x100 * id), &big(0x100 * id + 0xff))
+    };
+    for (id, last, status) in [
+        (1, 0xff, ChunkStatus::Finished),
+        (2, 0xff, ChunkStatus::Verified),
+        (3, 0x40, ChunkStatus::Pending),
+        (5, 0x00, ChunkStatus::Processing),
+    ] {
+        chunk(id, last, status)
+            .save(&format!("{}/chunk_{}.json", base, id))
+            .unwrap();
+    }
+    let compressed_only: ScriptTypes = "p2pkh-c".parse().unwrap();
+    ChunkMetadata {
+        script_types: Some(compressed_only),
+        ..chunk(6, 0xff, ChunkStatus::Finished)
+    }
+    .save(&format!("{}/chunk_6.json", base))
+    .unwrap();
+
+    let covered = folder_coverage(base, ScriptTypes::DEFAULT).unwrap();
+    let compressed = folder_coverage(base, compressed_only).unwrap();
+    fs::remove_dir_all(base).unwrap();
+    assert_eq!(covered, set(&[(0x100, 0x33f)]));
+    assert_eq!(compressed, set(&[(0x100, 0x33f), (0x600, 0x6ff)]));
+}
+
+#[test]
+fn test_folder_coverage_follows_walks() {
+    let base = "resources/tests/tmp_coverage_walks";
+    let _ = fs::remove_dir_all(base);
+    fs::create_dir_all(base).unwrap();
+    let chunk = |id: u64, walk: &str, last: u64| ChunkMetadata {
+        last_processed_hex: format!("{:064x}", 0x100 * id + last),
+        status: ChunkStatus::Pending,
+        walk: Some(Box::new(walk.parse().unwrap())),
+        ..ChunkMetadata::with_bounds(&id.into(), &big(0x100 * id), &big(0x100 * id + 0xff))
+    };
+    for meta in [
+        chunk(1, "descending", 0x7f),
+        chunk(2, "center", 0x70),
+        chunk(3, "stride:2", 0x40),
+    ] {
+        meta.save(&format!(
+            "{}/chunk_{}.json",
+            base,
+            meta.chunk_id.trim_start_matches('0')
+        ))
+        .unwrap();
+    }
+
+    let covered = folder_coverage(base, ScriptTypes::DEFAULT).unwrap();
+    fs::remove_dir_all(base).unwrap();
+    assert_eq!(covered, set(&[(0x180, 0x1ff), (0x271, 0x28e)]));
+}
+
+#[test]
+fn test_folder_coverage_mirrors_negation_symmetry() {
+    let base = "resources/tests/tmp_coverage_symmetry";
+    let _ = fs::remove_dir_all(base);
+    fs::create_dir_all(base).unwrap();
+    // Key 0 is not a private key, so only 1..=0xff is mirrored
+    ChunkMetadata {
+        last_processed_hex: format!("{:064x}", 0xff),
+        status: ChunkStatus::Finished,
+        symmetry: Some(Symmetry::Full),
+        ..ChunkMetadata::with_bounds(&big(0), &big(0), &big(0xff))
+    }
+    .save(&format!("{}/chunk_0.json", base))
+    .unwrap();
+
+    let covered = folder_coverage(base, ScriptTypes::DEFAULT).unwrap();
+    fs::remove_dir_all(base).unwrap();
+    let n = curve_order();
+    assert_eq!(
+        covered,
+        IntervalSet::from_intervals([(big(0), big(0xff)), (n - big(0xff), n - big(1))])
+    );
+}
+
+#[test]
+fn test_imported_coverage_excludes_range_chunks() {
+    let root = "resources/tests/tmp_coverage_import";
+    let _ = fs::remove_dir_all(root);
+    let theirs = Coverage {
+        puzzle: None,
+        source: "pool-b".to_string(),
+        exported_at: None,
+        script_types: ScriptTypes::DEFAULT,
+        intervals: set(&[(0x1000, 0x11ff), (0x1380, 0x14ff)]),
+    };
+    let stored = store_imported(root, "pool-b", &theirs).unwrap();
+    assert_eq!(stored, Path::new(root).join("imported/pool-b.json"));
+    let escaped = store_imported(root, "../../pool-c", &theirs).unwrap();
+    assert_eq!(escaped.parent(), stored.parent());
+    fs::remove_file(escaped).unwrap();
+    let excluded = load_imported(root, ScriptTypes::DEFAULT).unwrap();
+    assert_eq!(excluded, theirs.intervals);
+    let taproot = ScriptTypes::DEFAULT.with(ScriptType::P2tr);
+    assert!(load_imported(root, taproot).unwrap().is_empty());
+
+    // Chunks 0 and 1 are fully covered, chunk 3 only partly
+    let custom = PuzzleRange {
+        start: big(0x1000),
+        end: big(0x14ff),
+    };
+    let filter = ChunkFilter {
+        excluded: Some(excluded),
+        ..Default::default()
+    };
+    let folder = format!("{}/range", root);
+    let mut starts = Vec::new();
+    while let Some((mut meta, id)) =
+        acquire_range_chunk(&folder, &big(0x100), &custom, &filter).unwrap()
+    {
+        meta.status = ChunkStatus::Finished;
+        meta.save(&ChunkMetadata::path(&id, &folder)).unwrap();
+        starts.push(meta.start_hex[60..].to_string());
+    }
+    fs::remove_dir_all(root).unwrap();
+    assert_eq!(starts, vec!["1200", "1300"]);
+}
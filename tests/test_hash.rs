@@ -0,0 +1,57 @@
+use btc_key_matcher::address::{hash160, public_key};
+use btc_key_matcher::hash::{HashBackend, hash160_batch, hash160_batch_with, hash160_into};
+
+fn keys(count: usize) -> (Vec<[u8; 33]>, Vec<[u8; 65]>) {
+    (1..=count as u64)
+        .map(|n| {
+            let mut raw = [0u8; 32];
+            raw[24..].copy_from_slice(&n.wrapping_mul(0x9e37_79b9_7f4a_7c15).to_be_bytes());
+            let pk = public_key(&raw).unwrap();
+            (pk.serialize(), pk.serialize_uncompressed())
+        })
+        .unzip()
+}
+
+#[test]
+fn test_every_available_backend_matches_scalar_hash160() {
+    let (compressed, uncompressed) = keys(37);
+    for backend in HashBackend::ALL.into_iter().filter(|b| b.is_available()) {
+        for count in [0, 1, 7, 8, 16, 17, 37] {
+            let mut out = vec![[0u8; 20]; count];
+            hash160_batch_with(backend, &compressed[..count], &mut out);
+            for (key, hash) in compressed.iter().zip(&out) {
+                assert_eq!(hash.to_vec(), hash160(key), "{} compressed", backend);
+            }
+
+            hash160_batch_with(backend, &uncompressed[..count], &mut out);
+            for (key, hash) in uncompressed.iter().zip(&out) {
+                assert_eq!(hash.to_vec(), hash160(key), "{} uncompressed", backend);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_other_input_lengths_and_detection() {
+    let redeem: Vec<[u8; 22]> = (0..20u8).map(|n| [n; 22]).collect();
+    let mut out = vec![[0u8; 20]; redeem.len()];
+    hash160_batch(&redeem, &mut out);
+    for (input, hash) in redeem.iter().zip(&out) {
+        assert_eq!(hash.to_vec(), hash160(input));
+    }
+
+    let mut single = [0u8; 20];
+    hash160_into(b"", &mut single);
+    assert_eq!(
+        hex::encode(single),
+        "b472a266d0bd89c13706a4132ccfb16f7c3b9fcb"
+    );
+
+    assert!(HashBackend::detect().is_available());
+    assert!(HashBackend::Portable.is_available());
+    assert_eq!(
+        "AVX512".parse::<HashBackend>().unwrap(),
+        HashBackend::Avx512
+    );
+    assert!("neon".parse::<HashBackend>().is_err());
+}
@@ -1,11 +1,12 @@
-use btc_key_matcher::keygen::HexKeyGenerator;
+use btc_key_matcher::keygen::{HexKeyGenerator, KeyGenerator, KeyTemplate, KeyWalk, WalkRange};
+use num_bigint::BigUint;
 
 #[test]
 fn test_keygen_batch_exact_size() {
     let start = "0000000000000000000000000000000000000000000000000000000000000000";
     let end = "0000000000000000000000000000000000000000000000000000000000000005";
 
-    let mut generator = HexKeyGenerator::new(start, end);
+    let mut generator = HexKeyGenerator::new(start, end).unwrap();
     let batch = generator.next_batch(5);
 
     assert_eq!(batch.len(), 5);
@@ -21,11 +22,13 @@ fn test_keygen_stops_at_end() {
     let start = "fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff0";
     let end = "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff";
 
-    let mut generator = HexKeyGenerator::new(start, end);
+    let mut generator = HexKeyGenerator::new(start, end).unwrap();
     let batch = generator.next_batch(20); // request more than available
     assert_eq!(batch.len(), 16); // ✅ Fix: 16 keys from f0 to ff inclusive
     assert_eq!(hex::encode(batch.first().unwrap()), start);
     assert_eq!(hex::encode(batch.last().unwrap()), end);
+    assert!(generator.next_batch(20).is_empty());
+    assert_eq!(generator.last_key(), end);
 }
 
 #[test]
@@ -33,7 +36,7 @@ fn test_keygen_increment_logic() {
     let start = "00000000000000000000000000000000000000000000000000000000fffffffe";
     let end = "0000000000000000000000000000000000000000000000000000000100000005";
 
-    let mut generator = HexKeyGenerator::new(start, end);
+    let mut generator = HexKeyGenerator::new(start, end).unwrap();
     let batch = generator.next_batch(10);
 
     assert_eq!(batch.len(), 8);
@@ -57,9 +60,196 @@ fn test_keygen_single_key_range() {
     let start = "deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef";
     let end = "deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef";
 
-    let mut generator = HexKeyGenerator::new(start, end);
+    let mut generator = HexKeyGenerator::new(start, end).unwrap();
     let batch = generator.next_batch(5);
 
     assert_eq!(batch.len(), 1);
     assert_eq!(hex::encode(batch[0]), start);
 }
+
+#[test]
+fn test_keygen_rejects_invalid_hex() {
+    let bad = "zz00000000000000000000000000000000000000000000000000000000000000";
+    let end = "0000000000000000000000000000000000000000000000000000000000000005";
+
+    assert!(HexKeyGenerator::new(bad, end).is_err());
+    assert!(HexKeyGenerator::new(end, "05").is_err());
+}
+
+fn walk_keys(walk: &str, start: u64, end: u64) -> Vec<u64> {
+    let range = WalkRange::new(&walk.parse().unwrap(), start.into(), end.into()).unwrap();
+    let len: u64 = range.len().try_into().unwrap();
+    (0..len)
+        .map(|position| {
+            let key = range.key_at(&position.into());
+            assert_eq!(range.position_of(&key), Some(position.into()), "{}", walk);
+            key.try_into().unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn test_walk_orders() {
+    assert_eq!(walk_keys("ascending", 3, 6), vec![3, 4, 5, 6]);
+    assert_eq!(walk_keys("descending", 3, 6), vec![6, 5, 4, 3]);
+    assert_eq!(walk_keys("stride:3", 3, 12), vec![3, 6, 9, 12]);
+    assert_eq!(walk_keys("stride:4", 3, 12), vec![3, 7, 11]);
+    assert_eq!(walk_keys("center", 3, 8), vec![5, 6, 4, 7, 3, 8]);
+    assert_eq!(walk_keys("center:0x7", 3, 8), vec![7, 8, 6, 5, 4, 3]);
+    assert_eq!(walk_keys("center:4", 3, 8), vec![4, 5, 3, 6, 7, 8]);
+    assert_eq!(walk_keys("center:100", 3, 5), vec![5, 4, 3]);
+    assert_eq!(
+        walk_keys("template:1?f", 0x12f, 0x15f),
+        vec![0x12f, 0x13f, 0x14f, 0x15f]
+    );
+
+    let range = WalkRange::new(&"stride:3".parse().unwrap(), 3u32.into(), 12u32.into()).unwrap();
+    assert_eq!(range.position_of(&4u32.into()), None);
+    assert_eq!(range.position_of(&15u32.into()), None);
+    assert!(
+        WalkRange::new(
+            &"template:1?f".parse().unwrap(),
+            0x120u32.into(),
+            0x15fu32.into()
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_walk_scanned_ranges() {
+    let scanned = |walk: &str, done: u64| {
+        WalkRange::new(&walk.parse().unwrap(), 3u32.into(), 8u32.into())
+            .unwrap()
+            .scanned(&done.into())
+            .map(|(a, b)| (u64::try_from(a).unwrap(), u64::try_from(b).unwrap()))
+    };
+    assert_eq!(scanned("ascending", 0), None);
+    assert_eq!(scanned("ascending", 2), Some((3, 4)));
+    assert_eq!(scanned("descending", 2), Some((7, 8)));
+    assert_eq!(scanned("center", 4), Some((4, 7)));
+    assert_eq!(scanned("center:7", 4), Some((5, 8)));
+    assert_eq!(scanned("center:7", 6), Some((3, 8)));
+    assert_eq!(scanned("stride:1", 6), Some((3, 8)));
+    assert_eq!(scanned("stride:2", 2), None);
+    assert_eq!(scanned("permuted:5", 5), None);
+    assert_eq!(scanned("permuted:5", 6), Some((3, 8)));
+}
+
+#[test]
+fn test_permuted_walk_visits_every_key_once() {
+    let mut keys = walk_keys("permuted:9", 0x100, 0x1ff);
+    assert_ne!(keys, walk_keys("ascending", 0x100, 0x1ff));
+    assert_eq!(keys, walk_keys("permuted:9", 0x100, 0x1ff));
+    keys.sort();
+    assert_eq!(keys, walk_keys("ascending", 0x100, 0x1ff));
+
+    let walk = KeyWalk::Permuted(None).resolved();
+    assert!(matches!(walk, KeyWalk::Permuted(Some(_))));
+    assert!(walk.visits_every_key());
+    assert!(!walk.is_contiguous());
+}
+
+#[test]
+fn test_walk_and_template_parsing() {
+    for walk in [
+        "ascending",
+        "descending",
+        "stride:7",
+        "center",
+        "center:0x1f",
+        "permuted",
+        "permuted:42",
+    ] {
+        assert_eq!(walk.parse::<KeyWalk>().unwrap().to_string(), walk);
+    }
+    assert_eq!(
+        serde_json::to_string(&KeyWalk::Stride(7)).unwrap(),
+        r#""stride:7""#
+    );
+    assert!("stride:0".parse::<KeyWalk>().is_err());
+    assert!("sideways".parse::<KeyWalk>().is_err());
+    assert!("permuted:-1".parse::<KeyWalk>().is_err());
+
+    let template: KeyTemplate = "4??f".parse().unwrap();
+    assert_eq!(template.to_string(), "4??f");
+    assert_eq!("0x000".parse::<KeyTemplate>().unwrap().to_string(), "0");
+    assert_eq!(template.wildcards(), 2);
+    assert_eq!(template.count(), BigUint::from(256u32));
+    assert_eq!(template.key_at(&0x12u32.into()), BigUint::from(0x412fu32));
+    assert_eq!(template.index_of(&0x412fu32.into()), Some(0x12u32.into()));
+    assert_eq!(template.index_of(&0x4120u32.into()), None);
+    assert!(!template.is_suffix());
+    assert!("4f??".parse::<KeyTemplate>().unwrap().is_suffix());
+    assert!("4?g".parse::<KeyTemplate>().is_err());
+    assert!("?".repeat(65).parse::<KeyTemplate>().is_err());
+}
+
+#[test]
+fn test_key_generator_resumes_from_position() {
+    let range = WalkRange::new(&KeyWalk::Descending, 0x10u32.into(), 0x1fu32.into()).unwrap();
+    let mut generator = KeyGenerator::new(range.clone(), BigUint::ZERO);
+    let first = generator.next_batch(5);
+    assert_eq!(first[0][31], 0x1f);
+    assert_eq!(first[4][31], 0x1b);
+    assert_eq!(generator.position(), &BigUint::from(5u32));
+    assert_eq!(generator.last_key(), format!("{:064x}", 0x1a));
+
+    let mut resumed = KeyGenerator::new(range, generator.position().clone());
+    let rest = resumed.next_batch(20);
+    assert_eq!(rest.len(), 11);
+    assert_eq!(rest[0][31], 0x1a);
+    assert!(resumed.next_batch(20).is_empty());
+    assert_eq!(resumed.last_key(), format!("{:064x}", 0x10));
+}
+
+#[test]
+fn test_narrow_key_iteration_matches_general_path() {
+    let top = (BigUint::from(1u32) << 128u32) - 1u32;
+    for (start, end) in [
+        (BigUint::from(0x10f0u32), BigUint::from(0x11ffu32)),
+        (&top - 299u32, top.clone()),
+        (&top - 99u32, &top + 100u32),
+    ] {
+        for walk in [
+            "ascending",
+            "descending",
+            "stride:7",
+            "center",
+            "center:0x1100",
+        ] {
+            let range = WalkRange::new(&walk.parse().unwrap(), start.clone(), end.clone()).unwrap();
+            let mut generator = KeyGenerator::new(range.clone(), BigUint::ZERO);
+            let mut position = 0u64;
+            loop {
+                let batch = generator.next_batch(64);
+                if batch.is_empty() {
+                    break;
+                }
+                for key in batch {
+                    let expected = range.key_at(&position.into());
+                    assert_eq!(BigUint::from_bytes_be(&key), expected, "{}", walk);
+                    assert_eq!(range.raw_key_at(position), key, "{}", walk);
+                    position += 1;
+                }
+            }
+            assert_eq!(BigUint::from(position), *range.len(), "{}", walk);
+        }
+    }
+
+    // Carries across byte boundaries of the low half, with and without the
+    // top half set
+    let keys = |high: &str| {
+        let start = format!("{}{}", high, "0".repeat(28) + "fffe");
+        let end = format!("{}{}", high, "0".repeat(27) + "10101");
+        let mut generator = HexKeyGenerator::new(&start, &end).unwrap();
+        generator
+            .next_batch(1000)
+            .into_iter()
+            .map(|key| key[16..].to_vec())
+            .collect::<Vec<_>>()
+    };
+    let narrow = keys(&"0".repeat(32));
+    assert_eq!(narrow.len(), 260);
+    assert_eq!(narrow, keys(&format!("{}1", "0".repeat(31))));
+}
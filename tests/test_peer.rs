@@ -0,0 +1,212 @@
+mod common;
+
+use btc_key_matcher::chunk::ChunkMetadata;
+use btc_key_matcher::coverage::{Coverage, IntervalRecord, IntervalSet};
+use btc_key_matcher::peer::{PeerNode, PeerRequest, PeerResponse};
+use btc_key_matcher::script::ScriptTypes;
+use btc_key_matcher::symmetry::Symmetry;
+use btc_key_matcher::validate::chunk_files;
+use common::{big, set};
+use std::collections::BTreeSet;
+use std::fs;
+use std::net::TcpListener;
+use std::process::{Command, Stdio};
+
+fn sync(from: &mut PeerNode, to: &mut PeerNode, addr: &str) -> PeerResponse {
+    let response = from.handle(to.sync_request(addr));
+    to.apply_sync(addr, response.clone()).unwrap();
+    response
+}
+
+fn finished_count(response: &PeerResponse) -> usize {
+    match response {
+        PeerResponse::Synced { finished, .. } => finished.len(),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_sync_sends_ledger_deltas() {
+    let root = "resources/tests/tmp_peer_sync";
+    let _ = fs::remove_dir_all(root);
+    let mut a = PeerNode::open(
+        "a",
+        &format!("{}/a", root),
+        &set(&[(0x100, 0x1ff)]),
+        ScriptTypes::DEFAULT,
+    )
+    .unwrap();
+    let mut b = PeerNode::open(
+        "b",
+        &format!("{}/b", root),
+        &IntervalSet::default(),
+        ScriptTypes::DEFAULT,
+    )
+    .unwrap();
+
+    assert_eq!(finished_count(&sync(&mut a, &mut b, "a:1")), 1);
+    assert!(b.excluded().covers(&big(0x100), &big(0x1ff)));
+    assert_eq!(finished_count(&sync(&mut a, &mut b, "a:1")), 0);
+
+    a.record_scanned(
+        &big(0x300),
+        &big(0x3ff),
+        ScriptTypes::DEFAULT,
+        Symmetry::Off,
+    );
+    assert_eq!(finished_count(&sync(&mut a, &mut b, "a:1")), 1);
+    assert_eq!(b.excluded(), set(&[(0x100, 0x1ff), (0x300, 0x3ff)]));
+
+    // A restarted node starts a new epoch and resends its whole ledger
+    let mut a = PeerNode::open(
+        "a",
+        &format!("{}/a", root),
+        &set(&[(0x100, 0x1ff)]),
+        ScriptTypes::DEFAULT,
+    )
+    .unwrap();
+    assert_eq!(finished_count(&sync(&mut a, &mut b, "a:1")), 1);
+
+    // What b learned survives a restart of b
+    let b = PeerNode::open(
+        "b",
+        &format!("{}/b", root),
+        &IntervalSet::default(),
+        ScriptTypes::DEFAULT,
+    )
+    .unwrap();
+    assert!(b.excluded().covers(&big(0x300), &big(0x3ff)));
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_peer_coverage_keeps_its_script_types() {
+    let root = "resources/tests/tmp_peer_types";
+    let _ = fs::remove_dir_all(root);
+    let compressed: ScriptTypes = "p2pkh-c".parse().unwrap();
+    let mut a = PeerNode::open("a", &format!("{}/a", root), &set(&[]), compressed).unwrap();
+    a.record_scanned(&big(0x100), &big(0x1ff), compressed, Symmetry::Off);
+    a.record_scanned(
+        &big(0x300),
+        &big(0x3ff),
+        ScriptTypes::DEFAULT,
+        Symmetry::Off,
+    );
+
+    // A node checking the default types only skips keys checked for them
+    let mut b =
+        PeerNode::open("b", &format!("{}/b", root), &set(&[]), ScriptTypes::DEFAULT).unwrap();
+    sync(&mut a, &mut b, "a:1");
+    assert_eq!(b.excluded(), set(&[(0x300, 0x3ff)]));
+    assert_eq!(
+        a.handle(PeerRequest::Claim {
+            node: "b".into(),
+            span: IntervalRecord::new(&big(0x100), &big(0x1ff)),
+        }),
+        PeerResponse::Held { by: "a".into() }
+    );
+
+    // The narrower coverage is saved with its own types
+    let saved = Coverage::load(&format!("{}/b/peers/a_p2pkh-c.json", root)).unwrap();
+    assert_eq!(saved.script_types, compressed);
+    assert_eq!(saved.intervals, set(&[(0x100, 0x1ff)]));
+    let c = PeerNode::open("c", &format!("{}/b", root), &set(&[]), compressed).unwrap();
+    assert_eq!(c.excluded(), set(&[(0x100, 0x1ff), (0x300, 0x3ff)]));
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_concurrent_claims_go_to_smaller_name() {
+    let root = "resources/tests/tmp_peer_claims";
+    let _ = fs::remove_dir_all(root);
+    let mut a = PeerNode::open("a", root, &IntervalSet::default(), ScriptTypes::DEFAULT).unwrap();
+    let mut b = PeerNode::open("b", root, &set(&[(0x500, 0x5ff)]), ScriptTypes::DEFAULT).unwrap();
+    let (start, end) = (big(0x200), big(0x2ff));
+    let claim = |node: &str| PeerRequest::Claim {
+        node: node.to_string(),
+        span: IntervalRecord::new(&start, &end),
+    };
+
+    assert!(a.begin_claim(&start, &end));
+    assert!(b.begin_claim(&start, &end));
+    assert_eq!(b.handle(claim("a")), PeerResponse::Granted);
+    assert_eq!(a.handle(claim("b")), PeerResponse::Held { by: "a".into() });
+    assert!(a.end_claim(&start, true, ""));
+    assert!(!b.end_claim(&start, true, ""));
+    assert!(b.excluded().covers(&start, &end));
+
+    // Confirmed claims and scanned keys are held against everyone
+    assert_eq!(
+        a.handle(PeerRequest::Claim {
+            node: "0".into(),
+            span: IntervalRecord::new(&start, &end),
+        }),
+        PeerResponse::Held { by: "a".into() }
+    );
+    assert_eq!(
+        b.handle(PeerRequest::Claim {
+            node: "c".into(),
+            span: IntervalRecord::new(&big(0x580), &big(0x67f)),
+        }),
+        PeerResponse::Held { by: "b".into() }
+    );
+    let _ = fs::remove_dir_all(root);
+}
+
+fn free_port() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+#[test]
+fn test_peer_processes_split_a_range() {
+    let base = "resources/tests/tmp_peer_process";
+    let _ = fs::remove_dir_all(base);
+    let addrs = [free_port(), free_port()];
+    let dead = free_port();
+
+    let nodes: Vec<_> = (0..2)
+        .map(|i| {
+            Command::new(env!("CARGO_BIN_EXE_btc_key_matcher"))
+                .args([
+                    "scan",
+                    "--range",
+                    "0x1000-0x1fff",
+                    "--continuous",
+                    "--chunk-folder",
+                    &format!("{}/node{}", base, i),
+                    "--addr-file",
+                    "resources/tests/test_addresses_sorted.txt",
+                    "--chunk-size",
+                    "256",
+                    "--batch-size",
+                    "128",
+                    "--peer-listen",
+                    &addrs[i],
+                    "--peer",
+                    &addrs[1 - i],
+                    "--peer",
+                    &dead,
+                ])
+                .stdout(Stdio::null())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+    for mut node in nodes {
+        assert!(node.wait().unwrap().success());
+    }
+
+    let mut scanned = BTreeSet::new();
+    for i in 0..2 {
+        let folder = format!("{}/node{}/range_1000_1fff", base, i);
+        for path in chunk_files(&folder).unwrap() {
+            let meta = ChunkMetadata::load(&path.to_string_lossy()).unwrap();
+            if meta.status.is_done() {
+                assert!(scanned.insert(meta.start_hex), "chunk scanned twice");
+            }
+        }
+    }
+    fs::remove_dir_all(base).unwrap();
+    assert_eq!(scanned.len(), 16);
+}
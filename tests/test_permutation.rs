@@ -0,0 +1,45 @@
+use btc_key_matcher::permutation::Permutation;
+use num_bigint::BigUint;
+use std::collections::HashSet;
+
+#[test]
+fn test_permutation_is_a_bijection() {
+    for size in [1u32, 2, 3, 4, 5, 17, 255, 256, 1000] {
+        let permutation = Permutation::new(7, size.into());
+        let mut seen = HashSet::new();
+        for index in 0..size {
+            let value = permutation.apply(&index.into());
+            assert!(value < BigUint::from(size), "size {}", size);
+            assert_eq!(permutation.invert(&value), BigUint::from(index));
+            seen.insert(value);
+        }
+        assert_eq!(seen.len(), size as usize);
+    }
+}
+
+#[test]
+fn test_permutation_depends_on_key() {
+    let order = |key: u64| {
+        let permutation = Permutation::new(key, 1000u32.into());
+        (0..1000u32)
+            .map(|i| permutation.apply(&i.into()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(order(1), order(1));
+    assert_ne!(order(1), order(2));
+    assert_ne!(
+        order(1),
+        (0..1000u32).map(BigUint::from).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_permutation_inverts_over_wide_domains() {
+    let size = (BigUint::from(1u32) << 255u32) + 12345u32;
+    let permutation = Permutation::new(42, size.clone());
+    for index in [BigUint::ZERO, BigUint::from(99u32), &size - 1u32] {
+        let value = permutation.apply(&index);
+        assert!(value < size);
+        assert_eq!(permutation.invert(&value), index);
+    }
+}
@@ -0,0 +1,213 @@
+mod common;
+
+use btc_key_matcher::chunk::ChunkMetadata;
+use btc_key_matcher::keygen::KeyWalk;
+use btc_key_matcher::proof::{
+    ChunkProof, compute_proof, distinguished_bounds, leading_zero_bits, proof_hash, verify_proof,
+};
+use btc_key_matcher::scanner::{ChunkOutcome, ScanLimits, ScanStats, scan_chunk};
+use btc_key_matcher::search::TargetSet;
+use common::saved_chunk;
+use std::fs;
+
+#[test]
+fn test_proof_difficulty_and_bits() {
+    assert_eq!(ChunkProof::for_chunk_size(200).bits, 0);
+    assert_eq!(ChunkProof::for_chunk_size(1024).bits, 3);
+    assert_eq!(ChunkProof::for_chunk_size(100_000_000_000).bits, 29);
+    assert_eq!(leading_zero_bits(&[0x00, 0x1f, 0xff]), 11);
+    assert_eq!(leading_zero_bits(&[0x80]), 0);
+}
+
+#[test]
+fn test_scan_builds_same_proof_across_resume() {
+    let path = "resources/tests/tmp_proof_resume.json";
+    let mut meta = saved_chunk(path, 0x1000, 0x13ff);
+    let expected = compute_proof(&meta).unwrap();
+    assert!(!expected.distinguished.is_empty());
+
+    // Stop part-way, then resume from the checkpoint
+    let mut stats = ScanStats::default();
+    let limits = ScanLimits {
+        max_keys: Some(300),
+        ..ScanLimits::default()
+    };
+    scan_chunk(
+        &mut meta,
+        path,
+        &TargetSet::default(),
+        100,
+        &limits,
+        &mut stats,
+        |_| {},
+    )
+    .unwrap();
+    let mut meta = ChunkMetadata::load(path).unwrap();
+    assert!(meta.proof.is_some());
+
+    let outcome = scan_chunk(
+        &mut meta,
+        path,
+        &TargetSet::default(),
+        100,
+        &ScanLimits::default(),
+        &mut ScanStats::default(),
+        |_| {},
+    )
+    .unwrap();
+    let saved = ChunkMetadata::load(path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(outcome, ChunkOutcome::Finished);
+    assert_eq!(saved.proof, Some(expected));
+    verify_proof(&saved, 4, 128).unwrap();
+}
+
+#[test]
+fn test_walked_chunk_proof_survives_resume() {
+    let path = "resources/tests/tmp_proof_walk.json";
+    let mut meta = saved_chunk(path, 0x1000, 0x13ff);
+    meta.set_walk(KeyWalk::Descending).unwrap();
+    assert_eq!(meta.last_processed_hex, format!("{:064x}", 0x13ff));
+    let expected = compute_proof(&meta).unwrap();
+
+    let limits = ScanLimits {
+        max_keys: Some(300),
+        ..ScanLimits::default()
+    };
+    let mut stats = ScanStats::default();
+    scan_chunk(
+        &mut meta,
+        path,
+        &TargetSet::default(),
+        100,
+        &limits,
+        &mut stats,
+        |_| {},
+    )
+    .unwrap();
+    let mut meta = ChunkMetadata::load(path).unwrap();
+    assert_eq!(meta.keys_done().unwrap(), 300u32.into());
+
+    scan_chunk(
+        &mut meta,
+        path,
+        &TargetSet::default(),
+        100,
+        &ScanLimits::default(),
+        &mut ScanStats::default(),
+        |_| {},
+    )
+    .unwrap();
+    let saved = ChunkMetadata::load(path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(saved.last_processed_hex, saved.final_key_hex().unwrap());
+    assert_eq!(saved.last_processed_hex, format!("{:064x}", 0x1000));
+    assert_eq!(saved.proof, Some(expected));
+    verify_proof(&saved, 4, 128).unwrap();
+}
+
+#[test]
+fn test_permuted_chunk_resumes_by_position() {
+    let path = "resources/tests/tmp_proof_permuted.json";
+    let mut meta = saved_chunk(path, 0x1000, 0x13ff);
+    meta.set_walk(KeyWalk::Permuted(None)).unwrap();
+    assert!(matches!(meta.walk(), KeyWalk::Permuted(Some(_))));
+    let expected = compute_proof(&meta).unwrap();
+
+    let limits = ScanLimits {
+        max_keys: Some(300),
+        ..ScanLimits::default()
+    };
+    let scan = |meta: &mut ChunkMetadata, limits: &ScanLimits| {
+        scan_chunk(
+            meta,
+            path,
+            &TargetSet::default(),
+            100,
+            limits,
+            &mut ScanStats::default(),
+            |_| {},
+        )
+        .unwrap()
+    };
+    scan(&mut meta, &limits);
+    let mut meta = ChunkMetadata::load(path).unwrap();
+    assert_eq!(meta.keys_done().unwrap(), 300u32.into());
+
+    let outcome = scan(&mut meta, &ScanLimits::default());
+    let saved = ChunkMetadata::load(path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(outcome, ChunkOutcome::Finished);
+    assert_eq!(saved.keys_done().unwrap(), 1024u32.into());
+    assert_eq!(saved.proof, Some(expected));
+    verify_proof(&saved, 4, 128).unwrap();
+}
+
+#[test]
+fn test_verify_proof_catches_tampering() {
+    let path = "resources/tests/tmp_proof_tamper.json";
+    let mut meta = saved_chunk(path, 0x1000, 0x13ff);
+    fs::remove_file(path).unwrap();
+    let proof = compute_proof(&meta).unwrap();
+
+    meta.proof = None;
+    assert!(verify_proof(&meta, 1, 1024).is_err());
+
+    // A skipped key is found by a spot check that covers it
+    let mut skipped = proof.clone();
+    skipped.distinguished.remove(0);
+    meta.proof = Some(skipped);
+    assert!(verify_proof(&meta, 1, 1024).is_err());
+
+    let mut forged = proof.clone();
+    forged.fingerprint = hex::encode([1u8; 32]);
+    meta.proof = Some(forged);
+    assert!(verify_proof(&meta, 0, 0).is_err());
+
+    let mut easier = proof.clone();
+    easier.bits = 0;
+    meta.proof = Some(easier);
+    assert!(verify_proof(&meta, 0, 0).is_err());
+
+    meta.proof = Some(proof);
+    verify_proof(&meta, 1, 1024).unwrap();
+}
+
+#[test]
+fn test_verify_proof_rejects_short_proofs() {
+    assert_eq!(distinguished_bounds(200, 0), (200, 200));
+    let (min, max) = distinguished_bounds(1024, 3);
+    assert!(min > 64 && min < 128 && max > 128 && max < 256);
+
+    // A real-size chunk whose proof lists no keys, or half of them, fails
+    // on the count alone before any key is derived
+    let path = "resources/tests/tmp_proof_short.json";
+    let mut meta = saved_chunk(path, 1 << 40, (1 << 40) + 100_000_000_000 - 1);
+    fs::remove_file(path).unwrap();
+    let mut proof = ChunkProof::for_chunk_size(100_000_000_000);
+    meta.proof = Some(proof.clone());
+    assert!(verify_proof(&meta, 0, 0).is_err());
+
+    let expected = 100_000_000_000u64 >> proof.bits;
+    proof.distinguished = (0..expected / 2).map(|i| i << proof.bits).collect();
+    meta.proof = Some(proof);
+    let error = verify_proof(&meta, 0, 0).unwrap_err().to_string();
+    assert!(error.contains("distinguished keys, expected"), "{}", error);
+
+    // The same holds for a small chunk with half of its keys dropped
+    let path = "resources/tests/tmp_proof_half.json";
+    let mut meta = saved_chunk(path, 0x1000, 0x13ff);
+    fs::remove_file(path).unwrap();
+    let proof = compute_proof(&meta).unwrap();
+    let walk = meta.walk_range().unwrap();
+    let mut half = ChunkProof::new(proof.bits);
+    for &offset in &proof.distinguished[..proof.distinguished.len() / 2] {
+        half.record(offset, &proof_hash(&walk.raw_key_at(offset)).unwrap());
+    }
+    meta.proof = Some(half);
+    let error = verify_proof(&meta, 0, 0).unwrap_err().to_string();
+    assert!(error.contains("distinguished keys, expected"), "{}", error);
+}
@@ -0,0 +1,158 @@
+use btc_key_matcher::chunk::ChunkStatus;
+use btc_key_matcher::chunk_manager::{ChunkFilter, acquire_chunk, acquire_range_chunk};
+use btc_key_matcher::coverage::IntervalSet;
+use btc_key_matcher::puzzles::{PuzzleRange, get_puzzle_ranges};
+use btc_key_matcher::range::{
+    load_ranges_file, parse_key_count, parse_key_value, parse_range_spec, range_folder_name,
+    window_folder_name,
+};
+use num_bigint::BigUint;
+use std::collections::HashSet;
+use std::fs;
+
+fn range(start: u64, end: u64) -> PuzzleRange {
+    PuzzleRange {
+        start: BigUint::from(start),
+        end: BigUint::from(end),
+    }
+}
+
+#[test]
+fn test_parse_key_values() {
+    assert_eq!(parse_key_value("0x1f").unwrap(), BigUint::from(31u32));
+    assert_eq!(parse_key_value("4096").unwrap(), BigUint::from(4096u32));
+    assert_eq!(parse_key_value("1000").unwrap(), BigUint::from(1000u32));
+    assert!(parse_key_value("100a").is_err());
+    assert!(parse_key_value("ff").is_err());
+    assert!(parse_key_value("0x").is_err());
+    assert!(parse_key_value("12g").is_err());
+
+    assert_eq!(parse_key_count("2^24").unwrap(), BigUint::from(1u32 << 24));
+    assert_eq!(parse_key_count("0x100").unwrap(), BigUint::from(256u32));
+    assert!(parse_key_count("0").is_err());
+    assert!(parse_key_count("3^4").is_err());
+    assert_eq!(
+        parse_key_count("2^256").unwrap(),
+        BigUint::from(1u32) << 256u32
+    );
+    assert!(parse_key_count("2^4000000000").is_err());
+    assert_eq!(window_folder_name(&BigUint::from(256u32)), "window_256");
+}
+
+#[test]
+fn test_parse_range_specs() {
+    assert_eq!(
+        parse_range_spec("0x1000-0x1fff").unwrap(),
+        range(0x1000, 0x1fff)
+    );
+    assert_eq!(parse_range_spec("100..200").unwrap(), range(100, 200));
+    assert!(parse_range_spec("0x20-0x10").is_err());
+    assert!(parse_range_spec("0-10").is_err());
+    assert!(parse_range_spec("0x10").is_err());
+
+    let puzzle = get_puzzle_ranges().remove(&71).unwrap();
+    assert_eq!(parse_range_spec("puzzle:71").unwrap(), puzzle);
+
+    let slice = parse_range_spec("puzzle:71 at 40%–41%").unwrap();
+    assert_eq!(slice, parse_range_spec("puzzle:71@40%-41%").unwrap());
+    assert_eq!(slice.start, &puzzle.start + puzzle.size() * 40u32 / 100u32);
+    assert_eq!(
+        slice.end,
+        &puzzle.start + puzzle.size() * 41u32 / 100u32 - 1u32
+    );
+
+    let tail = parse_range_spec("puzzle:71@99.5%-100%").unwrap();
+    assert_eq!(tail.end, puzzle.end);
+    assert!(parse_range_spec("puzzle:71@50%-40%").is_err());
+    assert!(parse_range_spec("puzzle:99").is_err());
+}
+
+#[test]
+fn test_load_ranges_file() {
+    let path = "resources/tests/tmp_ranges.txt";
+    fs::write(
+        path,
+        "# suspicious subranges\n0x1000-0x1fff\n\n8192..9000\n",
+    )
+    .unwrap();
+    let ranges = load_ranges_file(path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(ranges, vec![range(0x1000, 0x1fff), range(8192, 9000)]);
+    assert_eq!(range_folder_name(&ranges[0]), "range_1000_1fff");
+}
+
+#[test]
+fn test_acquire_range_chunk_tiles_range() {
+    let base = "resources/tests/tmp_range_chunks";
+    let _ = fs::remove_dir_all(base);
+    let custom = range(0x1000, 0x10f9);
+    let chunk_size = BigUint::from(100u32);
+
+    let mut chunks = Vec::new();
+    while let Some((mut meta, id)) =
+        acquire_range_chunk(base, &chunk_size, &custom, &ChunkFilter::default()).unwrap()
+    {
+        meta.status = ChunkStatus::Finished;
+        meta.save(&format!("{}/chunk_{}.json", base, id)).unwrap();
+        chunks.push(meta);
+    }
+    fs::remove_dir_all(base).unwrap();
+
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(chunks[0].start_hex, format!("{:064x}", 0x1000));
+    assert_eq!(chunks[0].end_hex, format!("{:064x}", 0x1063));
+    assert_eq!(chunks[1].start_hex, format!("{:064x}", 0x1064));
+    assert_eq!(chunks[2].end_hex, format!("{:064x}", 0x10f9));
+}
+
+#[test]
+fn test_sample_windows_are_never_picked_twice() {
+    let base = "resources/tests/tmp_windows";
+    let _ = fs::remove_dir_all(base);
+    let puzzle = range(0x1000, 0x1fff);
+    let window = BigUint::from(0x100u32);
+    // The first two windows were already scanned as whole chunks
+    let filter = ChunkFilter {
+        excluded: Some(IntervalSet::from_intervals([(
+            BigUint::from(0x1001u32),
+            BigUint::from(0x1200u32),
+        )])),
+        ..ChunkFilter::default()
+    };
+
+    let mut starts = HashSet::new();
+    while let Ok((mut meta, id)) = acquire_chunk(base, &window, None, Some(&puzzle), &filter) {
+        meta.status = ChunkStatus::Finished;
+        meta.save(&format!("{}/chunk_{}.json", base, id)).unwrap();
+        assert!(starts.insert(meta.start_hex));
+    }
+    fs::remove_dir_all(base).unwrap();
+
+    assert_eq!(starts.len(), 13);
+    assert!(!starts.contains(&format!("{:064x}", 0x1001)));
+    assert!(!starts.contains(&format!("{:064x}", 0x1101)));
+}
+
+#[test]
+fn test_range_chunks_jump_past_excluded_intervals() {
+    let base = "resources/tests/tmp_range_jump";
+    let _ = fs::remove_dir_all(base);
+    // 2^40 chunks of one key, all but the last two already scanned
+    let puzzle = range(1 << 40, (1 << 41) - 1);
+    let filter = ChunkFilter {
+        excluded: Some(IntervalSet::from_intervals([(
+            BigUint::from(1u64 << 40),
+            BigUint::from((1u64 << 41) - 3),
+        )])),
+        ..ChunkFilter::default()
+    };
+
+    let (meta, id) = acquire_range_chunk(base, &BigUint::from(1u32), &puzzle, &filter)
+        .unwrap()
+        .unwrap();
+    fs::remove_dir_all(base).unwrap();
+
+    assert_eq!(id, BigUint::from((1u64 << 40) - 2));
+    assert_eq!(meta.start_hex, format!("{:064x}", (1u64 << 41) - 2));
+}
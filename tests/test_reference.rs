@@ -0,0 +1,50 @@
+use btc_key_matcher::address::derive_addresses;
+use btc_key_matcher::reference::reference_addresses;
+use rand::RngCore;
+
+#[test]
+fn test_reference_matches_fast_path() {
+    let mut keys = vec![[0u8; 32]; 3];
+    keys[0][31] = 1;
+    keys[1][31] = 0xff;
+    keys[2] = [0xff; 32];
+    keys[2][..16].copy_from_slice(&[0x7f; 16]);
+    for _ in 0..3 {
+        let mut key = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut key[1..]);
+        keys.push(key);
+    }
+
+    for key in &keys {
+        assert_eq!(
+            reference_addresses(key).unwrap(),
+            derive_addresses(key)
+                .unwrap()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            "key {}",
+            hex::encode(key)
+        );
+    }
+}
+
+#[test]
+fn test_reference_known_answer_and_invalid_keys() {
+    let mut key_one = [0u8; 32];
+    key_one[31] = 1;
+    assert_eq!(
+        reference_addresses(&key_one).unwrap(),
+        [
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+            "1EHNa6Q4Jz2uvNExL497mE43ikXhwF6kZm",
+            "3JvL6Ymt8MVWiCNHC7oWU6nLeHNJKLZGLN",
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+        ]
+    );
+
+    assert!(reference_addresses(&[0u8; 32]).is_err());
+    let order =
+        hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141").unwrap();
+    assert!(reference_addresses(&order.try_into().unwrap()).is_err());
+}
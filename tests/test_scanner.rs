@@ -0,0 +1,433 @@
+mod common;
+
+use btc_key_matcher::address::derive_addresses;
+use btc_key_matcher::chunk::{ChunkMetadata, ChunkStatus};
+use btc_key_matcher::codec::Network;
+use btc_key_matcher::scanner::{
+    ChunkOutcome, ScanLimits, ScanStats, StopReason, parse_duration, scan_chunk,
+};
+use btc_key_matcher::script::{ScriptType, ScriptTypes};
+use btc_key_matcher::search::{MatchAction, TargetSet};
+use btc_key_matcher::symmetry::{Symmetry, Variant};
+use common::saved_chunk;
+use std::fs;
+use std::time::Duration;
+
+#[test]
+fn test_scan_chunk_finishes_and_counts_keys() {
+    let path = "resources/tests/tmp_scan_finish.json";
+    let mut meta = saved_chunk(path, 0x20, 0x3f);
+    let mut stats = ScanStats::default();
+    let mut batches = 0;
+
+    let outcome = scan_chunk(
+        &mut meta,
+        path,
+        &TargetSet::default(),
+        10,
+        &ScanLimits::default(),
+        &mut stats,
+        |_| batches += 1,
+    )
+    .unwrap();
+    let saved = ChunkMetadata::load(path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(outcome, ChunkOutcome::Finished);
+    assert_eq!(batches, 4);
+    assert_eq!(stats.keys, 32);
+    assert_eq!(stats.chunks, 1);
+    assert_eq!(saved.status, ChunkStatus::Finished);
+    assert_eq!(saved.last_processed_hex, meta.end_hex);
+}
+
+#[test]
+fn test_scan_chunk_stops_at_key_limit() {
+    let path = "resources/tests/tmp_scan_limit.json";
+    let mut meta = saved_chunk(path, 0x20, 0x3f);
+    let mut stats = ScanStats::default();
+    let limits = ScanLimits {
+        max_keys: Some(15),
+        ..ScanLimits::default()
+    };
+
+    let outcome = scan_chunk(
+        &mut meta,
+        path,
+        &TargetSet::default(),
+        10,
+        &limits,
+        &mut stats,
+        |_| {},
+    )
+    .unwrap();
+    let saved = ChunkMetadata::load(path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(outcome, ChunkOutcome::Stopped(StopReason::MaxKeys));
+    assert_eq!(stats.keys, 15);
+    assert_eq!(saved.status, ChunkStatus::Pending);
+    assert_eq!(saved.last_processed_hex, format!("{:064x}", 0x2f));
+}
+
+#[test]
+fn test_scan_chunk_reports_match() {
+    let path = "resources/tests/tmp_scan_match.json";
+    let mut meta = saved_chunk(path, 0x01, 0x10);
+    let addresses = vec!["1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH".to_string()];
+    let mut stats = ScanStats::default();
+
+    let outcome = scan_chunk(
+        &mut meta,
+        path,
+        &TargetSet::new(addresses),
+        8,
+        &ScanLimits::default(),
+        &mut stats,
+        |_| {},
+    )
+    .unwrap();
+    fs::remove_file(path).unwrap();
+
+    let ChunkOutcome::Matched(reports) = outcome else {
+        panic!("expected a match, got {:?}", outcome);
+    };
+    let report = &reports[0];
+    assert_eq!(report.hex_key, format!("{:064x}", 1));
+    assert_eq!(report.format, "P2PKH compressed");
+    assert_eq!(
+        report.wif_compressed,
+        "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn"
+    );
+}
+
+#[test]
+fn test_scan_chunk_matches_taproot_when_selected() {
+    let path = "resources/tests/tmp_scan_taproot.json";
+    let targets = TargetSet::new(vec![
+        "bc1pmfr3p9j00pfxjh0zmgp99y8zftmd3s5pmedqhyptwy6lm87hf5sspknck9".to_string(),
+    ]);
+    let mut stats = ScanStats::default();
+    let limits = ScanLimits::default();
+
+    let mut meta = saved_chunk(path, 0x01, 0x10);
+    let outcome = scan_chunk(&mut meta, path, &targets, 8, &limits, &mut stats, |_| {}).unwrap();
+    assert_eq!(outcome, ChunkOutcome::Finished);
+
+    assert_eq!(
+        targets.unreachable(targets.script_types()),
+        vec![(ScriptType::P2tr, 1)]
+    );
+    let targets = targets.with_script_types(ScriptTypes::DEFAULT.with(ScriptType::P2tr));
+    let mut meta = saved_chunk(path, 0x01, 0x10);
+    let outcome = scan_chunk(&mut meta, path, &targets, 8, &limits, &mut stats, |_| {}).unwrap();
+    fs::remove_file(path).unwrap();
+
+    let ChunkOutcome::Matched(reports) = outcome else {
+        panic!("expected a match, got {:?}", outcome);
+    };
+    let report = &reports[0];
+    assert_eq!(report.hex_key, format!("{:064x}", 1));
+    assert_eq!(report.format, "P2TR");
+    assert_eq!(
+        report.address,
+        "bc1pmfr3p9j00pfxjh0zmgp99y8zftmd3s5pmedqhyptwy6lm87hf5sspknck9"
+    );
+}
+
+#[test]
+fn test_scan_chunk_derives_and_records_selected_types() {
+    let path = "resources/tests/tmp_scan_types.json";
+    let compressed: ScriptTypes = "p2pkh-c,p2wpkh".parse().unwrap();
+    // The uncompressed P2PKH address of key 1
+    let targets = TargetSet::new(vec!["1EHNa6Q4Jz2uvNExL497mE43ikXhwF6kZm".to_string()])
+        .with_script_types(compressed);
+    let mut stats = ScanStats::default();
+
+    let mut meta = saved_chunk(path, 0x01, 0x20);
+    let limits = ScanLimits {
+        max_keys: Some(8),
+        ..ScanLimits::default()
+    };
+    let outcome = scan_chunk(&mut meta, path, &targets, 8, &limits, &mut stats, |_| {}).unwrap();
+    assert_eq!(outcome, ChunkOutcome::Stopped(StopReason::MaxKeys));
+    assert_eq!(
+        ChunkMetadata::load(path).unwrap().script_types,
+        Some(compressed)
+    );
+
+    // Resuming with another set keeps only the types checked all along
+    let targets = targets.with_script_types("p2pkh-c,p2pkh-u".parse().unwrap());
+    let outcome = scan_chunk(
+        &mut meta,
+        path,
+        &targets,
+        8,
+        &ScanLimits::default(),
+        &mut ScanStats::default(),
+        |_| {},
+    )
+    .unwrap();
+    let saved = ChunkMetadata::load(path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(outcome, ChunkOutcome::Finished);
+    assert_eq!(saved.checked_types().to_string(), "p2pkh-c");
+}
+
+#[test]
+fn test_scan_chunk_batches_uncompressed_and_nested_hashes() {
+    let path = "resources/tests/tmp_scan_batched.json";
+    let address_of = |key: u8, script: ScriptType| {
+        let mut raw = [0u8; 32];
+        raw[31] = key;
+        let addresses = derive_addresses(&raw).unwrap();
+        let address = addresses.iter().find(|a| a.script == script).unwrap();
+        address.to_string()
+    };
+    // Keys in the first and second lane group of one batch
+    let targets = TargetSet::new(vec![
+        address_of(3, ScriptType::P2pkhUncompressed),
+        address_of(18, ScriptType::P2shP2wpkh),
+    ]);
+
+    let mut meta = saved_chunk(path, 0x01, 0x20);
+    let mut stats = ScanStats::default();
+    let outcome = scan_chunk(
+        &mut meta,
+        path,
+        &targets,
+        32,
+        &ScanLimits::default(),
+        &mut stats,
+        |_| {},
+    )
+    .unwrap();
+    fs::remove_file(path).unwrap();
+
+    let ChunkOutcome::Matched(reports) = outcome else {
+        panic!("expected a match, got {:?}", outcome);
+    };
+    let keys: Vec<_> = reports.iter().map(|r| r.hex_key.clone()).collect();
+    assert_eq!(keys, [format!("{:064x}", 3), format!("{:064x}", 18)]);
+}
+
+#[test]
+fn test_scan_limits_and_durations() {
+    let stats = ScanStats {
+        chunks: 2,
+        ..ScanStats::default()
+    };
+    let limits = ScanLimits {
+        max_chunks: Some(2),
+        ..ScanLimits::default()
+    };
+    assert_eq!(limits.reached(&stats), Some(StopReason::MaxChunks));
+    assert_eq!(limits.reached_within_chunk(&stats), None);
+
+    assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+    assert_eq!(parse_duration("30m").unwrap(), Duration::from_secs(1800));
+    assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+    assert!(parse_duration("2w").is_err());
+    assert!(parse_duration("h").is_err());
+    assert!(parse_duration("300000000000000000d").is_err());
+}
+
+#[test]
+fn test_scan_chunk_matches_regtest_targets() {
+    let path = "resources/tests/tmp_scan_regtest.json";
+    // The P2WPKH address of key 1 on regtest, next to its mainnet form
+    let targets = TargetSet::for_network(
+        Network::Regtest,
+        [
+            "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080",
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+        ],
+    );
+    assert_eq!(targets.unmatchable(), 1);
+    let mut meta = saved_chunk(path, 0x01, 0x10);
+    let outcome = scan_chunk(
+        &mut meta,
+        path,
+        &targets,
+        8,
+        &ScanLimits::default(),
+        &mut ScanStats::default(),
+        |_| {},
+    )
+    .unwrap();
+    fs::remove_file(path).unwrap();
+
+    let ChunkOutcome::Matched(reports) = outcome else {
+        panic!("expected a match, got {:?}", outcome);
+    };
+    let report = &reports[0];
+    assert_eq!(
+        report.address,
+        "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080"
+    );
+    assert_eq!(
+        report.wif_compressed,
+        "cMahea7zqjxrtgAbB7LSGbcQUr1uX1ojuat9jZodMN87JcbXMTcA"
+    );
+}
+
+#[test]
+fn test_scan_chunk_reports_p2pk_matches() {
+    let path = "resources/tests/tmp_scan_p2pk.json";
+    let targets =
+        TargetSet::new(["0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"])
+            .with_script_types("p2pk-c".parse().unwrap());
+    let mut meta = saved_chunk(path, 0x01, 0x10);
+    let outcome = scan_chunk(
+        &mut meta,
+        path,
+        &targets,
+        8,
+        &ScanLimits::default(),
+        &mut ScanStats::default(),
+        |_| {},
+    )
+    .unwrap();
+    fs::remove_file(path).unwrap();
+
+    let ChunkOutcome::Matched(reports) = outcome else {
+        panic!("expected a match, got {:?}", outcome);
+    };
+    let report = &reports[0];
+    assert_eq!(report.format, "P2PK compressed");
+    assert_eq!(
+        report.address,
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+    );
+}
+
+#[test]
+fn test_scan_chunk_reports_symmetric_key() {
+    let path = "resources/tests/tmp_scan_symmetry.json";
+    // The compressed P2PKH address of λ·2, found while scanning key 2
+    let lambda_two = Variant::new(1, false).private_key(&{
+        let mut key = [0u8; 32];
+        key[31] = 2;
+        key
+    });
+    let address = derive_addresses(&lambda_two).unwrap()[0].to_string();
+    let scan = |symmetry: Symmetry| {
+        let targets = TargetSet::new([&address]).with_symmetry(symmetry);
+        let mut meta = saved_chunk(path, 0x01, 0x10);
+        let outcome = scan_chunk(
+            &mut meta,
+            path,
+            &targets,
+            8,
+            &ScanLimits::default(),
+            &mut ScanStats::default(),
+            |_| {},
+        )
+        .unwrap();
+        (outcome, ChunkMetadata::load(path).unwrap())
+    };
+
+    let (outcome, meta) = scan(Symmetry::Negation);
+    assert_eq!(outcome, ChunkOutcome::Finished);
+    assert_eq!(meta.symmetry, Some(Symmetry::Negation));
+
+    let (outcome, _) = scan(Symmetry::Full);
+    fs::remove_file(path).unwrap();
+    let ChunkOutcome::Matched(reports) = outcome else {
+        panic!("expected a match, got {:?}", outcome);
+    };
+    let report = &reports[0];
+    assert_eq!(report.hex_key, hex::encode(lambda_two));
+    assert_eq!(report.address, address);
+}
+
+#[test]
+fn test_scan_chunk_follows_its_walk() {
+    let path = "resources/tests/tmp_scan_walk.json";
+    let address_of = |key: u8| {
+        let mut raw = [0u8; 32];
+        raw[31] = key;
+        derive_addresses(&raw).unwrap()[0].to_string()
+    };
+    let scan = |target: u8| {
+        let mut meta = saved_chunk(path, 0x01, 0x10);
+        meta.set_walk("stride:4".parse().unwrap()).unwrap();
+        let mut stats = ScanStats::default();
+        let outcome = scan_chunk(
+            &mut meta,
+            path,
+            &TargetSet::new(vec![address_of(target)]),
+            3,
+            &ScanLimits::default(),
+            &mut stats,
+            |_| {},
+        )
+        .unwrap();
+        (outcome, stats.keys, ChunkMetadata::load(path).unwrap())
+    };
+
+    // Keys 1, 5, 9 and 13
+    let (outcome, keys, saved) = scan(10);
+    assert_eq!(outcome, ChunkOutcome::Finished);
+    assert_eq!(keys, 4);
+    assert_eq!(saved.last_processed_hex, format!("{:064x}", 13));
+    assert_eq!(saved.keys_done().unwrap(), 4u32.into());
+
+    let (outcome, _, _) = scan(9);
+    fs::remove_file(path).unwrap();
+    let ChunkOutcome::Matched(reports) = outcome else {
+        panic!("expected a match, got {:?}", outcome);
+    };
+    let report = &reports[0];
+    assert_eq!(report.hex_key, format!("{:064x}", 9));
+}
+
+#[test]
+fn test_scan_chunk_continues_past_non_stopping_matches() {
+    let path = "resources/tests/tmp_scan_groups.json";
+    let address_of = |key: u8| {
+        let mut raw = [0u8; 32];
+        raw[31] = key;
+        derive_addresses(&raw).unwrap()[0].to_string()
+    };
+    let targets = TargetSet::empty(Network::Mainnet)
+        .with_group("canary", 0, MatchAction::Continue, [address_of(3)])
+        .with_group(
+            "watch",
+            1,
+            MatchAction::Notify,
+            [address_of(3), address_of(9), address_of(13)],
+        )
+        .with_group("puzzles", 5, MatchAction::Stop, [address_of(14)]);
+    let mut meta = saved_chunk(path, 0x01, 0x10);
+    let mut seen = Vec::new();
+    let outcome = scan_chunk(
+        &mut meta,
+        path,
+        &targets,
+        4,
+        &ScanLimits::default(),
+        &mut ScanStats::default(),
+        |progress| seen.extend(progress.matches.iter().cloned()),
+    )
+    .unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(seen.len(), 2);
+    assert_eq!(seen[0].hex_key, format!("{:064x}", 3));
+    assert_eq!(seen[0].sets, ["watch", "canary"]);
+    assert_eq!(seen[0].action, MatchAction::Notify);
+    assert_eq!(seen[1].sets, ["watch"]);
+    let ChunkOutcome::Matched(reports) = outcome else {
+        panic!("expected a match, got {:?}", outcome);
+    };
+    let report = &reports[0];
+    // The notify match in the stopping batch is returned along with it
+    assert_eq!(reports.len(), 2);
+    assert_eq!(report.hex_key, format!("{:064x}", 13));
+    assert_eq!(report.action, MatchAction::Notify);
+    assert_eq!(reports[1].hex_key, format!("{:064x}", 14));
+    assert_eq!(reports[1].sets, ["puzzles"]);
+    assert!(reports[1].to_csv().ends_with(";puzzles\n"));
+}
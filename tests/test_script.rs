@@ -0,0 +1,35 @@
+use btc_key_matcher::script::{ScriptType, ScriptTypes};
+
+#[test]
+fn test_script_types_parse_and_display() {
+    let types: ScriptTypes = " P2TR, p2pkh-c ".parse().unwrap();
+    assert_eq!(types.to_string(), "p2pkh-c,p2tr");
+    assert_eq!(
+        types.iter().collect::<Vec<_>>(),
+        vec![ScriptType::P2pkhCompressed, ScriptType::P2tr]
+    );
+    assert_eq!(
+        ScriptTypes::DEFAULT.to_string(),
+        "p2pkh-c,p2pkh-u,p2sh-p2wpkh,p2wpkh"
+    );
+
+    assert!("p2pk".parse::<ScriptTypes>().is_err());
+    assert!(",".parse::<ScriptTypes>().is_err());
+}
+
+#[test]
+fn test_script_types_set_operations_and_serde() {
+    let compressed: ScriptTypes = "p2pkh-c".parse().unwrap();
+    assert!(ScriptTypes::DEFAULT.is_superset(compressed));
+    assert!(!compressed.is_superset(ScriptTypes::DEFAULT));
+    assert_eq!(
+        ScriptTypes::DEFAULT.intersection("p2pkh-c,p2tr".parse().unwrap()),
+        compressed
+    );
+
+    let json = serde_json::to_string(&compressed.with(ScriptType::P2wpkh)).unwrap();
+    assert_eq!(json, r#"["p2pkh-c","p2wpkh"]"#);
+    let back: ScriptTypes = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, compressed.with(ScriptType::P2wpkh));
+    assert!(serde_json::from_str::<ScriptTypes>("[]").is_err());
+}
@@ -1,8 +1,15 @@
-use btc_key_matcher::search::{binary_search, load_sorted_addresses};
+use btc_key_matcher::address::{derive, derive_addresses, public_key};
+use btc_key_matcher::codec::Network;
+use btc_key_matcher::scanner::MatchReport;
+use btc_key_matcher::script::{ScriptType, ScriptTypes};
+use btc_key_matcher::search::{
+    MatchAction, TargetSet, binary_search, build_address_database, first_unsorted,
+    load_sorted_addresses,
+};
 
 #[test]
 fn test_search_found_and_not_found() {
-    let addresses = load_sorted_addresses("resources/tests/test_addresses_sorted.txt");
+    let addresses = load_sorted_addresses("resources/tests/test_addresses_sorted.txt").unwrap();
 
     assert!(binary_search(
         &addresses,
@@ -13,3 +20,141 @@ fn test_search_found_and_not_found() {
         "bc1noexistaddressxxxxxxxxxxxxxxxx"
     ));
 }
+
+#[test]
+fn test_load_missing_address_file() {
+    assert!(load_sorted_addresses("resources/tests/missing_addresses.txt").is_err());
+}
+
+#[test]
+fn test_build_address_database_sorts_and_dedups() {
+    let input = "resources/tests/tmp_raw_addresses.csv";
+    let output = "resources/tests/tmp_built_addresses.txt";
+    std::fs::write(
+        input,
+        "# exported list\nBC1QC7KA984JGN755JJWDK5WCCASKR8R22JYME9C3M,123\n\n1KCohbCE8t97TRFT35szYC9srochLfzTs5;9\n1KCohbCE8t97TRFT35szYC9srochLfzTs5\n1KCohbCE8t97TRFT35szYC9srochLfzTs6\naddress,balance\n0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798 p2pk\n",
+    )
+    .unwrap();
+
+    let report = build_address_database(&[input.to_string()], output, Network::Mainnet).unwrap();
+    let built = load_sorted_addresses(output).unwrap();
+    std::fs::remove_file(input).unwrap();
+    std::fs::remove_file(output).unwrap();
+
+    assert_eq!(report.written, 3);
+    let rejected: Vec<_> = report.rejected.iter().map(|(at, _)| at.as_str()).collect();
+    assert_eq!(
+        rejected,
+        vec![
+            "resources/tests/tmp_raw_addresses.csv:6",
+            "resources/tests/tmp_raw_addresses.csv:7"
+        ]
+    );
+    assert_eq!(
+        built,
+        vec![
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            "1KCohbCE8t97TRFT35szYC9srochLfzTs5",
+            "bc1qc7ka984jgn755jjwdk5wccaskr8r22jyme9c3m"
+        ]
+    );
+    assert_eq!(first_unsorted(&built), None);
+    assert_eq!(
+        first_unsorted(&[built[1].clone(), built[0].clone()]),
+        Some(1)
+    );
+}
+
+#[test]
+fn test_target_set_matches_by_payload() {
+    let mut key = [0u8; 32];
+    key[31] = 1;
+    let derived = derive_addresses(&key).unwrap();
+
+    // Bech32 targets match whatever their case, and testnet or P2WSH
+    // entries can never be produced by a key
+    let targets = TargetSet::new([
+        "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+        "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4",
+        "mrCDrCybB6J1vRfbwM5hemdJz73FwDBC8r",
+        "bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3",
+        "not an address",
+    ]);
+    assert_eq!(targets.len(), 5);
+    assert_eq!(targets.unmatchable(), 3);
+    assert!(targets.contains(&derived[0]));
+    assert!(!targets.contains(&derived[1]));
+    assert!(!targets.contains(&derived[2]));
+    assert!(targets.contains(&derived[3]));
+}
+
+#[test]
+fn test_target_set_matches_p2pk_keys() {
+    let mut key = [0u8; 32];
+    key[31] = 1;
+    let derived = derive(
+        &public_key(&key).unwrap(),
+        Network::Mainnet,
+        ScriptTypes::ALL,
+    )
+    .unwrap();
+
+    let targets = TargetSet::new([
+        "0479BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8",
+    ]);
+    assert_eq!(
+        targets.unreachable(ScriptTypes::DEFAULT),
+        vec![(ScriptType::P2pkUncompressed, 1)]
+    );
+    assert!(targets.unreachable(ScriptTypes::ALL).is_empty());
+    let matched: Vec<_> = derived.iter().filter(|a| targets.contains(a)).collect();
+    assert_eq!(matched.len(), 1);
+    assert_eq!(matched[0].script, ScriptType::P2pkUncompressed);
+}
+
+#[test]
+fn test_target_groups_report_matches_by_priority() {
+    let mut key = [0u8; 32];
+    key[31] = 1;
+    let derived = derive_addresses(&key).unwrap();
+    let compressed = "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH";
+    let uncompressed = "1EHNa6Q4Jz2uvNExL497mE43ikXhwF6kZm";
+
+    let targets = TargetSet::empty(Network::Mainnet)
+        .with_group("canary", -1, MatchAction::Continue, [compressed])
+        .with_group("puzzles", 10, MatchAction::Stop, [uncompressed, "bad"])
+        .with_group("watch", 0, MatchAction::Notify, [compressed]);
+    assert_eq!(targets.len(), 4);
+    assert_eq!(targets.unmatchable(), 1);
+    let labels: Vec<_> = targets.groups().iter().map(|g| g.label()).collect();
+    assert_eq!(labels, ["puzzles", "watch", "canary"]);
+
+    let matching = |derived: &[_]| -> Vec<String> {
+        targets
+            .groups_containing(derived)
+            .map(|g| g.label().to_string())
+            .collect()
+    };
+    assert!(targets.contains(&derived[0]));
+    assert_eq!(matching(&derived[..1]), ["watch", "canary"]);
+    assert_eq!(matching(&derived[..2]), ["puzzles", "watch", "canary"]);
+    assert!(matching(&derived[2..]).is_empty());
+
+    // A stopping group wins over a higher-priority group that continues
+    let report = |targets: &TargetSet| {
+        MatchReport::new(&key, &derived[0]).in_groups(targets.groups_containing(&derived[..1]))
+    };
+    assert_eq!(report(&targets).action, MatchAction::Notify);
+    let targets = TargetSet::empty(Network::Mainnet)
+        .with_group("canary", 10, MatchAction::Continue, [compressed])
+        .with_group("puzzles", 0, MatchAction::Stop, [compressed]);
+    let stopping = report(&targets);
+    assert_eq!(stopping.sets, ["canary", "puzzles"]);
+    assert_eq!(stopping.action, MatchAction::Stop);
+
+    assert_eq!(
+        "Notify".parse::<MatchAction>().unwrap(),
+        MatchAction::Notify
+    );
+    assert!("pause".parse::<MatchAction>().is_err());
+}
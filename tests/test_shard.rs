@@ -0,0 +1,180 @@
+use btc_key_matcher::chunk::{ChunkMetadata, ChunkStatus};
+use btc_key_matcher::chunk_manager::{ChunkFilter, acquire_chunk, acquire_range_chunk};
+use btc_key_matcher::merge::merge_chunk_folders;
+use btc_key_matcher::puzzles::PuzzleRange;
+use btc_key_matcher::shard::{Shard, ShardLayout, chunk_id_space, random_chunk_id_in_shard};
+use num_bigint::BigUint;
+use std::collections::BTreeSet;
+use std::fs;
+
+fn range(start: u64, end: u64) -> PuzzleRange {
+    PuzzleRange {
+        start: BigUint::from(start),
+        end: BigUint::from(end),
+    }
+}
+
+#[test]
+fn test_parse_worker_spec() {
+    let shard = Shard::parse("2/5", ShardLayout::Contiguous).unwrap();
+    assert_eq!((shard.index, shard.count), (2, 5));
+    assert_eq!(shard.to_string(), "2/5 (contiguous)");
+    assert_eq!("interleaved".parse(), Ok(ShardLayout::Interleaved));
+
+    for bad in ["5/5", "0/0", "1", "a/4", "1/-2"] {
+        assert!(
+            Shard::parse(bad, ShardLayout::Interleaved).is_err(),
+            "{}",
+            bad
+        );
+    }
+}
+
+#[test]
+fn test_shards_partition_id_space() {
+    let total = BigUint::from(23u32);
+    for layout in [ShardLayout::Interleaved, ShardLayout::Contiguous] {
+        let shards: Vec<Shard> = (0..4)
+            .map(|i| Shard::parse(&format!("{}/4", i), layout).unwrap())
+            .collect();
+        let mut counted = BigUint::ZERO;
+        for id in 0u32..23 {
+            let id = BigUint::from(id);
+            let owners = shards.iter().filter(|s| s.owns(&id, &total)).count();
+            assert_eq!(owners, 1, "{} {}", layout, id);
+        }
+        for shard in &shards {
+            let owned = shard.chunk_count(&total);
+            let mut k = BigUint::ZERO;
+            while k < owned {
+                assert!(shard.owns(&shard.nth_chunk(&k, &total), &total));
+                k += 1u32;
+            }
+            counted += owned;
+        }
+        assert_eq!(counted, total);
+    }
+}
+
+#[test]
+fn test_random_ids_stay_in_shard() {
+    let puzzle = range(0x10000, 0x1ffff);
+    let chunk_size = BigUint::from(1000u32);
+    let total = chunk_id_space(&chunk_size, Some(&puzzle));
+    let shard = Shard::parse("1/3", ShardLayout::Interleaved).unwrap();
+    for _ in 0..50 {
+        let id = random_chunk_id_in_shard(&chunk_size, Some(&puzzle), &shard).unwrap();
+        assert!(shard.owns(&id, &total));
+    }
+
+    let filter = ChunkFilter {
+        shard: Some(shard),
+        ..Default::default()
+    };
+    let base = "resources/tests/tmp_shard_cli";
+    let _ = fs::remove_dir_all(base);
+    let foreign = BigUint::from(3u32);
+    assert!(acquire_chunk(base, &chunk_size, Some(foreign), Some(&puzzle), &filter).is_err());
+    let (_, id) = acquire_chunk(base, &chunk_size, None, Some(&puzzle), &filter).unwrap();
+    assert!(shard.owns(&id, &total));
+    fs::remove_dir_all(base).unwrap();
+}
+
+#[test]
+fn test_sharded_range_workers_cover_range_once() {
+    let custom = range(0x1000, 0x1fff);
+    let chunk_size = BigUint::from(0x100u32);
+    let bases = [
+        "resources/tests/tmp_shard_w0",
+        "resources/tests/tmp_shard_w1",
+    ];
+
+    let mut seen = BTreeSet::new();
+    for (i, base) in bases.iter().enumerate() {
+        let _ = fs::remove_dir_all(base);
+        let filter = ChunkFilter {
+            shard: Some(Shard::parse(&format!("{}/2", i), ShardLayout::Interleaved).unwrap()),
+            ..Default::default()
+        };
+        while let Some((mut meta, id)) =
+            acquire_range_chunk(base, &chunk_size, &custom, &filter).unwrap()
+        {
+            meta.status = ChunkStatus::Finished;
+            meta.save(&format!("{}/chunk_{}.json", base, id)).unwrap();
+            assert!(seen.insert(meta.start_hex.clone()));
+        }
+    }
+    assert_eq!(seen.len(), 16);
+
+    let merged = "resources/tests/tmp_shard_merged";
+    let _ = fs::remove_dir_all(merged);
+    let sources: Vec<String> = bases.iter().map(|b| b.to_string()).collect();
+    let report = merge_chunk_folders(&sources, merged, Some(&custom), false).unwrap();
+    assert_eq!(report.copied, 16);
+    assert!(report.skipped.is_empty());
+
+    for base in bases.iter().chain([&merged]) {
+        fs::remove_dir_all(base).unwrap();
+    }
+}
+
+#[test]
+fn test_merge_keeps_most_advanced_copy() {
+    let a = "resources/tests/tmp_merge_a";
+    let b = "resources/tests/tmp_merge_b";
+    let dest = "resources/tests/tmp_merge_dest";
+    for dir in [a, b, dest] {
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+    }
+    let chunk = |id: u32, last: u64, status: ChunkStatus| {
+        let start = 0x100 * id as u64;
+        ChunkMetadata {
+            last_processed_hex: format!("{:064x}", start + last),
+            status,
+            ..ChunkMetadata::with_bounds(&id.into(), &start.into(), &(start + 0xff).into())
+        }
+    };
+
+    chunk(1, 0x10, ChunkStatus::Processing)
+        .save(&format!("{}/chunk_1.json", a))
+        .unwrap();
+    chunk(2, 0x80, ChunkStatus::Pending)
+        .save(&format!("{}/chunk_2.json", a))
+        .unwrap();
+    chunk(2, 0x20, ChunkStatus::Pending)
+        .save(&format!("{}/chunk_2.json", b))
+        .unwrap();
+    let mut conflicting = chunk(1, 0xff, ChunkStatus::Finished);
+    conflicting.end_hex = format!("{:064x}", 0x17f);
+    conflicting.save(&format!("{}/chunk_1.json", b)).unwrap();
+    chunk(3, 0xff, ChunkStatus::Finished)
+        .save(&format!("{}/chunk_3.json", b))
+        .unwrap();
+
+    let dry = merge_chunk_folders(&[a.to_string(), b.to_string()], dest, None, true).unwrap();
+    assert_eq!(dry.copied, 3);
+    assert!(fs::read_dir(dest).unwrap().next().is_none());
+
+    let report = merge_chunk_folders(&[a.to_string(), b.to_string()], dest, None, false).unwrap();
+    assert_eq!((report.copied, report.updated, report.unchanged), (3, 0, 1));
+    assert_eq!(report.skipped.len(), 1);
+
+    let first = ChunkMetadata::load(&format!("{}/chunk_1.json", dest)).unwrap();
+    assert_eq!(first.status, ChunkStatus::Pending);
+    let second = ChunkMetadata::load(&format!("{}/chunk_2.json", dest)).unwrap();
+    assert_eq!(second.last_processed_hex, format!("{:064x}", 0x280));
+
+    // A later pass with a finished copy upgrades the merged chunk
+    chunk(2, 0xff, ChunkStatus::Finished)
+        .save(&format!("{}/chunk_2.json", b))
+        .unwrap();
+    let again = merge_chunk_folders(&[b.to_string()], dest, None, false).unwrap();
+    assert_eq!(again.updated, 1);
+    let second = ChunkMetadata::load(&format!("{}/chunk_2.json", dest)).unwrap();
+    assert_eq!(second.status, ChunkStatus::Finished);
+
+    for dir in [a, b, dest] {
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,63 @@
+use btc_key_matcher::address::public_key;
+use btc_key_matcher::symmetry::{Symmetry, Variant, curve_order, mirror, symmetric_points};
+use num_bigint::BigUint;
+
+fn key(hex_key: &str) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    hex::decode_to_slice(format!("{:0>64}", hex_key), &mut key).unwrap();
+    key
+}
+
+#[test]
+fn test_symmetric_points_match_their_private_keys() {
+    for raw in [
+        key("1"),
+        key("2c0a7e3f1b9d6e4c8a5f3d2b1e0c9a8b7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f2a"),
+    ] {
+        let points = symmetric_points(&public_key(&raw).unwrap(), Symmetry::Full).unwrap();
+        assert_eq!(points.len(), 6);
+        assert_eq!(points[0].0, Variant::IDENTITY);
+        for (variant, point) in points {
+            let derived = variant.private_key(&raw);
+            assert_eq!(public_key(&derived).unwrap(), point, "{:?}", variant);
+        }
+    }
+}
+
+#[test]
+fn test_variant_keys() {
+    let n = curve_order();
+    let one = key("1");
+    assert_eq!(
+        BigUint::from_bytes_be(&Variant::new(0, true).private_key(&one)),
+        n - BigUint::from(1u8)
+    );
+    // λ is a cube root of unity, so applying it three times is the identity
+    assert_eq!(Variant::new(3, false), Variant::IDENTITY);
+    let lambda = Variant::new(1, false).private_key(&one);
+    assert_eq!(
+        hex::encode(lambda),
+        "5363ad4cc05c30e0a5261c028812645a122e22ea20816678df02967c1b23bd72"
+    );
+    assert_eq!(Variant::new(2, false).private_key(&lambda), one);
+}
+
+#[test]
+fn test_mirror_and_symmetry_names() {
+    let n = curve_order();
+    let big = BigUint::from;
+    assert_eq!(
+        mirror(&big(0u8), &big(5u8)),
+        Some((n - big(5u8), n - big(1u8)))
+    );
+    assert_eq!(mirror(&big(0u8), &big(0u8)), None);
+
+    assert_eq!("Negation".parse::<Symmetry>().unwrap(), Symmetry::Negation);
+    assert!("both".parse::<Symmetry>().is_err());
+    assert_eq!(Symmetry::Full.keys_per_point(), 6);
+    assert!(Symmetry::Full > Symmetry::Negation);
+    assert_eq!(
+        serde_json::to_string(&Symmetry::Negation).unwrap(),
+        "\"negation\""
+    );
+}
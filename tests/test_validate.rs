@@ -0,0 +1,134 @@
+use btc_key_matcher::chunk::{ChunkMetadata, ChunkStatus, parse_chunk_id};
+use btc_key_matcher::chunk_manager::{ChunkFilter, acquire_chunk};
+use btc_key_matcher::puzzles::get_puzzle_ranges;
+use btc_key_matcher::validate::{
+    ChunkIssue, QUARANTINE_FOLDER, fsck, quarantine_file, validate_chunk,
+};
+use num_bigint::BigUint;
+use num_traits::Zero;
+use std::fs;
+use std::path::Path;
+
+fn chunk(id: &str, start: &str, end: &str, last: &str) -> ChunkMetadata {
+    ChunkMetadata {
+        chunk_id: id.to_string(),
+        start_hex: start.to_string(),
+        end_hex: end.to_string(),
+        last_processed_hex: last.to_string(),
+        status: ChunkStatus::Pending,
+        ..ChunkMetadata::with_bounds(&BigUint::zero(), &BigUint::zero(), &BigUint::zero())
+    }
+}
+
+#[test]
+fn test_parse_all_zero_chunk_id() {
+    assert_eq!(parse_chunk_id("00000").unwrap(), BigUint::zero());
+    assert_eq!(parse_chunk_id("00042").unwrap(), BigUint::from(42u32));
+    assert!(parse_chunk_id("").is_err());
+    assert!(parse_chunk_id("12a").is_err());
+}
+
+#[test]
+fn test_validate_chunk_detects_issues() {
+    let low = format!("{:064x}", 0x10);
+    let mid = format!("{:064x}", 0x20);
+    let high = format!("{:064x}", 0x30);
+
+    assert!(validate_chunk(&chunk("00001", &low, &high, &mid), None, None).is_empty());
+
+    assert_eq!(
+        validate_chunk(&chunk("00001", &high, &low, &mid), None, None),
+        vec![ChunkIssue::StartAfterEnd]
+    );
+    assert_eq!(
+        validate_chunk(&chunk("00001", &low, &mid, &high), None, None),
+        vec![ChunkIssue::LastProcessedOutOfRange]
+    );
+    let strided = ChunkMetadata {
+        walk: Some(Box::new("stride:3".parse().unwrap())),
+        ..chunk("00001", &low, &high, &format!("{:064x}", 0x1f))
+    };
+    assert!(validate_chunk(&strided, None, None).is_empty());
+    let off_walk = ChunkMetadata {
+        last_processed_hex: mid.clone(),
+        ..strided
+    };
+    assert!(matches!(
+        validate_chunk(&off_walk, None, None).as_slice(),
+        [ChunkIssue::InvalidWalk(_)]
+    ));
+    assert_eq!(
+        validate_chunk(&chunk("00001", "10", &high, &mid), None, None),
+        vec![ChunkIssue::BadHexWidth {
+            field: "start_hex",
+            len: 2
+        }]
+    );
+    assert_eq!(
+        validate_chunk(
+            &chunk("00001", &low, &high, &mid),
+            Some(&BigUint::from(2u32)),
+            None
+        ),
+        vec![ChunkIssue::ChunkIdMismatch {
+            file_id: BigUint::from(2u32),
+            meta_id: "00001".to_string()
+        }]
+    );
+
+    let ranges = get_puzzle_ranges();
+    assert_eq!(
+        validate_chunk(&chunk("00001", &low, &high, &mid), None, ranges.get(&71)),
+        vec![ChunkIssue::OutsidePuzzleRange]
+    );
+}
+
+#[test]
+fn test_fsck_and_acquire_quarantine_broken_files() {
+    let base = "resources/tests/tmp_fsck";
+    let _ = fs::remove_dir_all(base);
+    fs::create_dir_all(base).unwrap();
+
+    let low = format!("{:064x}", 0x10);
+    let high = format!("{:064x}", 0x30);
+    chunk("00005", &low, &high, &low)
+        .save(&format!("{}/chunk_5.json", base))
+        .unwrap();
+    chunk("00001", &high, &low, &low)
+        .save(&format!("{}/chunk_1.json", base))
+        .unwrap();
+    fs::write(format!("{}/chunk_2.json", base), "{ not json").unwrap();
+
+    let report = fsck(base, None, true).unwrap();
+    assert_eq!(report.checked, 3);
+    assert_eq!(report.broken.len(), 2);
+    assert!(report.quarantined.is_empty());
+
+    let (meta, id) = acquire_chunk(
+        base,
+        &BigUint::from(100u32),
+        None,
+        None,
+        &ChunkFilter::default(),
+    )
+    .unwrap();
+    assert_eq!(id, BigUint::from(5u32));
+    assert_eq!(meta.status, ChunkStatus::Processing);
+
+    let quarantine = Path::new(base).join(QUARANTINE_FOLDER);
+    assert!(quarantine.join("chunk_1.json").exists());
+    assert!(quarantine.join("chunk_2.json").exists());
+    let report_lines = fs::read_to_string(quarantine.join("report.jsonl")).unwrap();
+    assert_eq!(report_lines.lines().count(), 2);
+
+    // A second broken file of the same name keeps the first one
+    fs::write(format!("{}/chunk_2.json", base), "{ still not json").unwrap();
+    let stored = quarantine_file(base, &Path::new(base).join("chunk_2.json"), &[]).unwrap();
+    assert_eq!(stored, quarantine.join("chunk_2-1.json"));
+    assert_eq!(
+        fs::read_to_string(quarantine.join("chunk_2.json")).unwrap(),
+        "{ not json"
+    );
+
+    fs::remove_dir_all(base).unwrap();
+}
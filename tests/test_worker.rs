@@ -0,0 +1,194 @@
+use btc_key_matcher::chunk::ChunkStatus;
+use btc_key_matcher::coordinator::{Coordinator, serve};
+use btc_key_matcher::proof::compute_proof;
+use btc_key_matcher::protocol::{Request, Response, send_request};
+use btc_key_matcher::puzzles::PuzzleRange;
+use btc_key_matcher::worker::{LeaseOutcome, WorkerCache, WorkerClient};
+use num_bigint::BigUint;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::net::TcpListener;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Start an in-process coordinator over a small range and return its address
+fn start_coordinator(base: &str) -> String {
+    let range = PuzzleRange {
+        start: BigUint::from(0x1000u32),
+        end: BigUint::from(0x1fffu32),
+    };
+    let coordinator = Coordinator::open(
+        base,
+        BigUint::from(0x100u32),
+        Some(range),
+        Duration::from_secs(60),
+    )
+    .unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || serve(listener, Arc::new(Mutex::new(coordinator))));
+    addr
+}
+
+/// An address nobody listens on
+fn dead_addr() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+#[test]
+fn test_worker_queues_reports_while_offline() {
+    let base = "resources/tests/tmp_worker_offline";
+    let _ = fs::remove_dir_all(base);
+    let addr = start_coordinator(&format!("{}/store", base));
+    let cache_folder = format!("{}/cache", base);
+
+    let online = WorkerClient::new(&addr, "worker-a", WorkerCache::open(&cache_folder).unwrap());
+    let LeaseOutcome::Leased {
+        lease_id,
+        mut chunk,
+        ..
+    } = online.lease().unwrap()
+    else {
+        panic!("expected a lease");
+    };
+
+    // The link drops: reports pile up in the outbox, newest checkpoint only
+    let offline = WorkerClient::new(
+        &dead_addr(),
+        "worker-a",
+        WorkerCache::open(&cache_folder).unwrap(),
+    );
+    let start = BigUint::parse_bytes(chunk.start_hex.as_bytes(), 16).unwrap();
+    for step in [0x10u32, 0x20] {
+        chunk.last_processed_hex = format!("{:064x}", &start + step);
+        assert_eq!(offline.heartbeat(lease_id, &chunk).unwrap(), None);
+    }
+    chunk.last_processed_hex = chunk.end_hex.clone();
+    chunk.proof = Some(compute_proof(&chunk).unwrap());
+    assert_eq!(offline.finish(lease_id, &chunk).unwrap(), None);
+    assert_eq!(offline.cache().outbox().unwrap().len(), 2);
+    assert!(offline.cache().resume().unwrap().is_none());
+
+    // Back online, the queue is delivered in order
+    assert_eq!(online.cache().flush(&addr).unwrap(), 2);
+    assert!(online.cache().outbox().unwrap().is_empty());
+    match send_request(&addr, &Request::Status).unwrap() {
+        Response::Status {
+            active_leases,
+            finished,
+            ..
+        } => {
+            assert_eq!(active_leases, 0);
+            assert_eq!(finished, 1);
+        }
+        other => panic!("expected status, got {:?}", other),
+    }
+
+    fs::remove_dir_all(base).unwrap();
+}
+
+#[test]
+fn test_worker_resumes_cached_chunk() {
+    let base = "resources/tests/tmp_worker_resume";
+    let _ = fs::remove_dir_all(base);
+    let addr = start_coordinator(&format!("{}/store", base));
+    let cache = WorkerCache::open(&format!("{}/cache", base)).unwrap();
+    let client = WorkerClient::new(&addr, "worker-a", cache);
+
+    let LeaseOutcome::Leased {
+        lease_id, chunk, ..
+    } = client.lease().unwrap()
+    else {
+        panic!("expected a lease");
+    };
+
+    // A restarted worker picks its cached chunk up again, even offline
+    let offline = WorkerClient::new(
+        &dead_addr(),
+        "worker-a",
+        WorkerCache::open(&format!("{}/cache", base)).unwrap(),
+    );
+    match offline.lease().unwrap() {
+        LeaseOutcome::Leased {
+            lease_id: resumed,
+            chunk: resumed_chunk,
+            ..
+        } => {
+            assert_eq!(resumed, lease_id);
+            assert_eq!(resumed_chunk.chunk_id, chunk.chunk_id);
+            assert_eq!(resumed_chunk.status, ChunkStatus::Processing);
+        }
+        other => panic!("expected the cached chunk, got {:?}", other),
+    }
+
+    offline.release(lease_id, &chunk).unwrap();
+    assert!(matches!(offline.lease().unwrap(), LeaseOutcome::Offline));
+
+    fs::remove_dir_all(base).unwrap();
+}
+
+#[test]
+fn test_worker_process_scans_leased_chunks() {
+    let base = "resources/tests/tmp_worker_process";
+    let _ = fs::remove_dir_all(base);
+    let mut coordinator = Command::new(env!("CARGO_BIN_EXE_btc_key_matcher"))
+        .args([
+            "coordinator",
+            "--listen",
+            "127.0.0.1:0",
+            "--puzzle-id",
+            "71",
+            "--chunk-folder",
+            &format!("{}/store", base),
+            "--chunk-size",
+            "200",
+        ])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(coordinator.stdout.take().unwrap());
+    let mut first_line = String::new();
+    stdout.read_line(&mut first_line).unwrap();
+    let addr = first_line.trim().rsplit(' ').next().unwrap().to_string();
+
+    let worker = Command::new(env!("CARGO_BIN_EXE_btc_key_matcher"))
+        .args([
+            "worker",
+            "--coordinator",
+            &format!("tcp://{}", addr),
+            "--cache-folder",
+            &format!("{}/cache", base),
+            "--addr-file",
+            "resources/tests/test_addresses_sorted.txt",
+            "--chunk-size",
+            "200",
+            "--batch-size",
+            "50",
+            "--heartbeat-secs",
+            "0",
+            "--max-chunks",
+            "2",
+        ])
+        .output()
+        .unwrap();
+    let status = send_request(&addr, &Request::Status).unwrap();
+
+    coordinator.kill().unwrap();
+    coordinator.wait().unwrap();
+    fs::remove_dir_all(base).unwrap();
+
+    assert!(worker.status.success());
+    assert_eq!(
+        status,
+        Response::Status {
+            active_leases: 0,
+            pending: 0,
+            processing: 0,
+            finished: 2,
+            matches: 0,
+        }
+    );
+}